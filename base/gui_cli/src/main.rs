@@ -163,6 +163,7 @@ fn main(_: isize, _: *const *const u8) -> isize {
 					WRITE_HANDLE => return 0,
 					_ => unreachable!(),
 				},
+				Request::Cancel { .. } => continue,
 				e => todo!(),
 			};
 			table.enqueue(job_id, resp);
@@ -42,6 +42,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 	let root = rt::Object::from_raw(0);
 	let drivers = root.open(b"drivers").unwrap();
 	let process_root = root.open(b"process").unwrap();
+	let random = root.open(b"random").unwrap();
 
 	// Read arguments
 	log!("Parsing drivers/init.toml");
@@ -140,7 +141,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 				.into_iter()
 				.chain(file_root.map(|r| (rt::args::ID_FILE_ROOT, r)))
 				.chain(net_root.map(|r| (rt::args::ID_NET_ROOT, r)))
-				.chain(proc_root.map(|r| (rt::args::ID_PROCESS_ROOT, r))),
+				.chain(proc_root.map(|r| (rt::args::ID_PROCESS_ROOT, r)))
+				.chain([(rt::args::ID_RANDOM, (&random).into())]),
 				[name]
 					.into_iter()
 					.chain(program.args.iter().flat_map(|i| i.iter()))
@@ -1,21 +1,183 @@
-#![feature(norostb)]
-
-use std::{io, os::norostb::prelude::*, process::Command};
-
-fn main() -> Result<(), io::Error> {
-	let mut args = std::env::args_os().skip(1);
-	let dir = args.next().unwrap();
-	assert_eq!(args.next().unwrap().as_bytes(), b"--");
-	let program = args.next().unwrap();
-	dbg!(&dir, &program);
-	Command::new(program)
-		.current_dir(dir)
-		.spawn()
-		.map(|_| ())
-		.unwrap();
+//! # Jail
+//!
+//! Runs a program with a restricted set of objects. A new root is created that only contains
+//! the given entries and is passed as the file root of the program. The net and process roots
+//! are only passed if requested.
+//!
+//! Usage: `jail [options] -- <program> [args...]`
+//!
+//! * `--file <name>=<path>[:<rwx>]`: add the object at `path` in the file root of the jail as
+//!   `name`. If permissions are given the object is wrapped in a permission mask, which is
+//!   currently only supported for objects that can be mapped.
+//! * `--net <path>`: pass the object at `path` in the net root of the jail as net root. An empty
+//!   path passes the net root of the jail itself.
+//! * `--process <path>`: likewise for the process root.
+//! * `--profile <path>`: read options from a TOML file, e.g.:
+//!
+//! ```toml
+//! net_root = ""
+//!
+//! [file_root]
+//! drivers = "drivers"
+//! framebuffer = "gpu/fb:r"
+//! ```
+//!
+//! The jail exits with the exit code of the program.
 
+#![feature(let_else)]
+
+use async_std::{object::AsyncObject, process::Command};
+use serde_derive::Deserialize;
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Default, Deserialize)]
+struct Profile {
+	#[serde(default)]
+	file_root: BTreeMap<String, String>,
+	net_root: Option<String>,
+	process_root: Option<String>,
+}
+
+fn main() {
+	let mut args = std::env::args().skip(1);
+	let mut profile = Profile::default();
 	loop {
-		// TODO ditto
-		std::thread::sleep(std::time::Duration::MAX);
+		let Some(opt) = args.next() else { usage() };
+		if opt == "--" {
+			break;
+		}
+		let Some(value) = args.next() else { usage() };
+		match opt.as_str() {
+			"--file" => {
+				let Some((name, path)) = value.split_once('=') else {
+					usage()
+				};
+				profile.file_root.insert(name.into(), path.into());
+			}
+			"--net" => profile.net_root = Some(value),
+			"--process" => profile.process_root = Some(value),
+			"--profile" => {
+				let p = std::fs::read(&value)
+					.unwrap_or_else(|e| fail(format_args!("{}: {}", value, e)));
+				let p = toml::from_slice::<Profile>(&p).unwrap_or_else(|e| fail(e));
+				profile.file_root.extend(p.file_root);
+				profile.net_root = p.net_root.or(profile.net_root);
+				profile.process_root = p.process_root.or(profile.process_root);
+			}
+			_ => usage(),
+		}
+	}
+	let Some(program) = args.next() else { usage() };
+
+	let (root, _) =
+		rt::Object::new(rt::NewObject::Root).unwrap_or_else(|e| fail(format_args!("{:?}", e)));
+	// A root only holds weak references, so keep the entries alive until the program exits.
+	let mut entries = Vec::with_capacity(profile.file_root.len());
+	for (name, path) in &profile.file_root {
+		let obj = open_entry(path)
+			.and_then(|obj| {
+				root.create(name.as_bytes())?.share(&obj)?;
+				Ok(obj)
+			})
+			.unwrap_or_else(|e| fail(format_args!("failed to add {:?}: {:?}", name, e)));
+		entries.push(obj);
+	}
+
+	let code = async_std::task::block_on(async {
+		let mut cmd = Command::new(program).await;
+		cmd.args(args).await.file_root(Some(root.into())).await;
+		let net_root = open_root(&profile.net_root, rt::io::net_root)
+			.unwrap_or_else(|e| fail(format_args!("failed to open net root: {:?}", e)));
+		if let Some(r) = net_root {
+			cmd.net_root(r).await;
+		}
+		let process_root = open_root(&profile.process_root, rt::io::process_root)
+			.unwrap_or_else(|e| fail(format_args!("failed to open process root: {:?}", e)));
+		if let Some(r) = process_root {
+			cmd.process_root(r).await;
+		}
+		let child = cmd
+			.spawn()
+			.await
+			.unwrap_or_else(|e| fail(format_args!("failed to spawn: {:?}", e)));
+		match child.wait().await {
+			Ok(status) => status.code().unwrap_or(1),
+			Err(e) => fail(format_args!("failed to wait: {:?}", e)),
+		}
+	});
+	drop(entries);
+	std::process::exit(code)
+}
+
+/// Open an object in the file root, optionally with restricted permissions.
+fn open_entry(entry: &str) -> rt::io::Result<rt::Object> {
+	let (path, rwx) = parse_entry(entry).ok_or(rt::Error::InvalidData)?;
+	let obj = rt::io::file_root()
+		.ok_or(rt::Error::DoesNotExist)?
+		.open(path.as_bytes())?;
+	match rwx {
+		None => Ok(obj),
+		Some(rwx) => rt::Object::new(rt::NewObject::PermissionMask {
+			handle: obj.as_raw(),
+			rwx,
+		})
+		.map(|(o, _)| o),
+	}
+}
+
+/// Split an entry in a path and optional permissions.
+fn parse_entry(entry: &str) -> Option<(&str, Option<rt::RWX>)> {
+	let Some((path, perm)) = entry.rsplit_once(':') else {
+		return Some((entry, None));
+	};
+	let (r, w, x) = (perm.contains('r'), perm.contains('w'), perm.contains('x'));
+	if perm.chars().any(|c| !"rwx".contains(c)) {
+		return None;
+	}
+	rt::RWX::from_flags(r, w, x)
+		.ok()
+		.map(|rwx| (path, Some(rwx)))
+}
+
+/// Open the root to pass to the program.
+///
+/// Returns `None` if the root of the jail should be inherited.
+fn open_root(
+	path: &Option<String>,
+	root: fn() -> Option<rt::RefObject<'static>>,
+) -> rt::io::Result<Option<Option<AsyncObject>>> {
+	Ok(match path.as_deref() {
+		None => Some(None),
+		Some("") => None,
+		Some(path) => {
+			let root = root().ok_or(rt::Error::DoesNotExist)?;
+			Some(Some(root.open(path.as_bytes())?.into()))
+		}
+	})
+}
+
+fn usage() -> ! {
+	fail(concat!(
+		"usage: jail [--file <name>=<path>[:<rwx>]] [--net <path>] [--process <path>] ",
+		"[--profile <path>] -- <program> [args...]",
+	))
+}
+
+fn fail(msg: impl fmt::Display) -> ! {
+	eprintln!("{}", msg);
+	std::process::exit(1)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn entry() {
+		assert_eq!(parse_entry("drivers"), Some(("drivers", None)));
+		assert_eq!(parse_entry("gpu/fb:r"), Some(("gpu/fb", Some(rt::RWX::R))));
+		assert_eq!(parse_entry("a:b:rw"), Some(("a:b", Some(rt::RWX::RW))));
+		assert_eq!(parse_entry("gpu/fb:q"), None);
+		assert_eq!(parse_entry("gpu/fb:"), None);
 	}
 }
@@ -3,6 +3,8 @@
 mod term;
 
 use self::term::AnsiTerminal;
+use async_std::{io::Read as _, object::RefAsyncObject};
+use futures_lite::future;
 use std::fs;
 use std::io::{self, Read, Write};
 use std::iter::Filter;
@@ -12,7 +14,7 @@ fn main() -> std::io::Result<()> {
 	let mut term = self::term::AnsiTerminal::new(std::io::stdin(), std::io::stderr());
 	term.set_prefix(">> ");
 
-	let mut buf @ mut buf2 = [0; 4096];
+	let mut buf = [0; 4096];
 	let mut vars = std::collections::HashMap::<Box<str>, _>::new();
 
 	loop {
@@ -86,21 +88,21 @@ fn main() -> std::io::Result<()> {
 			b"open" => {
 				let Some(name) = next_str(&mut term, &mut args)? else { continue; };
 				let Some(path) = next_str(&mut term, &mut args)? else { continue; };
-				match fs::File::open(path) {
+				match file_root().and_then(|r| r.open(path.as_bytes())) {
 					Ok(f) => {
 						vars.insert(name.into(), f);
 					}
-					Err(e) => writeln!(term, "Failed to open \"{}\": {}", path, e)?,
+					Err(e) => writeln!(term, "Failed to open \"{}\": {:?}", path, e)?,
 				}
 			}
 			b"create" => {
 				let Some(name) = next_str(&mut term, &mut args)? else { continue; };
 				let Some(path) = next_str(&mut term, &mut args)? else { continue; };
-				match fs::File::create(path) {
+				match file_root().and_then(|r| r.create(path.as_bytes())) {
 					Ok(f) => {
 						vars.insert(name.into(), f);
 					}
-					Err(e) => writeln!(term, "Failed to open \"{}\": {}", path, e)?,
+					Err(e) => writeln!(term, "Failed to open \"{}\": {:?}", path, e)?,
 				}
 			}
 			b"destroy" => {
@@ -127,17 +129,18 @@ fn main() -> std::io::Result<()> {
 					writeln!(term, "Length is not a valid number")?;
 					continue;
 				};
-				let len = len.min(buf2.len());
-				let Some(mut f) = vars.get(name) else {
+				let len = len.min(buf.len());
+				let Some(f) = vars.get(name) else {
 					writeln!(term, "No variable named \"{}\"", name)?;
 					continue;
 				};
-				match f.read(&mut buf2[..len]) {
-					Ok(l) => {
-						term.write(&buf2[..l])?;
+				match read_interruptible(f, len) {
+					Ok(Some(data)) => {
+						term.write(&data)?;
 						writeln!(term)?;
 					}
-					Err(e) => writeln!(term, "Failed to read from \"{}\": {}", name, e)?,
+					Ok(None) => writeln!(term, "Interrupted")?,
+					Err(e) => writeln!(term, "Failed to read from \"{}\": {:?}", name, e)?,
 				}
 			}
 			b"write" => {
@@ -148,13 +151,13 @@ fn main() -> std::io::Result<()> {
 					.filter(|s| !s.is_empty())
 					.last()
 					.unwrap();
-				let Some(mut f) = vars.get(name) else {
+				let Some(f) = vars.get(name) else {
 					writeln!(term, "No variable named \"{}\"", name)?;
 					continue;
 				};
 				match f.write(data) {
 					Ok(l) => writeln!(term, "Wrote {} bytes", l)?,
-					Err(e) => writeln!(term, "Failed to read from \"{}\": {}", name, e)?,
+					Err(e) => writeln!(term, "Failed to read from \"{}\": {:?}", name, e)?,
 				}
 			}
 			b"vars" => {
@@ -182,3 +185,32 @@ fn main() -> std::io::Result<()> {
 		};
 	}
 }
+
+fn file_root() -> rt::io::Result<rt::RefObject<'static>> {
+	rt::io::file_root().ok_or(rt::Error::DoesNotExist)
+}
+
+/// Read from an object, giving up if `^C` is pressed.
+///
+/// Returns `None` if the read was interrupted.
+fn read_interruptible(obj: &rt::Object, len: usize) -> rt::io::Result<Option<Vec<u8>>> {
+	async_std::task::block_on(async {
+		let read = async {
+			let (r, buf) = RefAsyncObject::from(obj)
+				.read(Vec::with_capacity(len))
+				.await;
+			r.map(|_| Some(buf))
+		};
+		let interrupt = async {
+			// If stdin is not a PTY nothing can interrupt us.
+			let Some(stdin) = rt::io::stdin() else { return future::pending().await };
+			let stdin = RefAsyncObject::from(stdin);
+			match stdin.get_meta(b"pty/interrupt", Vec::new()).await.0 {
+				Ok(_) => Ok(None),
+				Err(_) => future::pending().await,
+			}
+		};
+		// The read is cancelled if the interrupt comes first and vice versa.
+		future::or(read, interrupt).await
+	})
+}
@@ -0,0 +1,306 @@
+//! # Network filter
+//!
+//! This server exposes the same interface as a network table but forwards all requests to
+//! an upstream network table, which is the net root of this process. Any attempts to listen
+//! or connect are checked against a list of rules first.
+//!
+//! Sockets that are allowed are handed to the client directly, so data doesn't pass through
+//! the filter.
+//!
+//! Usage: `net_filter <table name> <rules path>`
+
+mod rules;
+
+use async_std::{
+	io::{Read, Seek, Write},
+	object::{AsyncObject, RefAsyncObject},
+};
+use driver_utils::os::stream_table::{Data, JobId, Request, Response, StreamTable};
+use futures::{
+	future::{self, AbortHandle, FutureExt},
+	stream::{FuturesUnordered, StreamExt},
+};
+use rt::io::Pow2Size;
+use rules::{Action, Operation, Rules};
+use std::{collections::BTreeMap, future::Future, pin::Pin, rc::Rc, str, task::Poll};
+
+/// The maximum amount of data to read in a single request.
+const MAX_READ: u32 = 1 << 12;
+
+/// The result of a forwarded request.
+enum Reply {
+	Object(AsyncObject),
+	/// An object to give to the client instead of proxying it.
+	Direct(AsyncObject),
+	Data(Vec<u8>),
+	Amount(usize),
+	Position(u64),
+	Share(u64),
+	Error(rt::Error),
+}
+
+type Job = Pin<Box<dyn Future<Output = Reply>>>;
+
+fn main() {
+	let mut args = std::env::args().skip(1);
+	let table_name = args.next().expect("expected table name");
+	let rules_path = args.next().expect("expected rules path");
+
+	let rules = std::fs::read(&rules_path).expect("failed to read rules");
+	let rules = match toml::from_slice::<Rules>(&rules) {
+		Ok(r) => r,
+		Err(e) => {
+			eprintln!("{}", e);
+			std::process::exit(1);
+		}
+	};
+
+	let upstream = rt::io::net_root().expect("no net root");
+	// The net root is never closed, so wrapping it in an AsyncObject is fine.
+	let upstream = Rc::new(AsyncObject::from_raw(upstream.as_raw()));
+
+	// Create a new table.
+	let tbl = {
+		let (buf, _) = rt::Object::new(rt::NewObject::SharedMemory { size: 1 << 16 }).unwrap();
+		StreamTable::new(&buf, Pow2Size(9), (1 << 12) - 1)
+	};
+	tbl.set_vectored_writes(true);
+	rt::io::file_root()
+		.unwrap()
+		.create(table_name.as_bytes())
+		.unwrap()
+		.share(tbl.public())
+		.unwrap();
+
+	async_std::task::block_on(serve(&tbl, upstream, &rules))
+}
+
+async fn serve(tbl: &StreamTable, upstream: Rc<AsyncObject>, rules: &Rules) -> ! {
+	let mut objects = driver_utils::Arena::<Rc<AsyncObject>>::new();
+	let mut jobs = FuturesUnordered::new();
+	// Handles to abort jobs that the client cancels, by job ID.
+	let mut aborts = BTreeMap::<u32, AbortHandle>::new();
+	let mut notify = RefAsyncObject::from(tbl.notifier()).read(());
+
+	loop {
+		// Wait for either new requests or a finished job.
+		let finished = future::poll_fn(|cx| {
+			if Pin::new(&mut notify).poll(cx).is_ready() {
+				return Poll::Ready(None);
+			}
+			match jobs.poll_next_unpin(cx) {
+				Poll::Ready(Some(r)) => Poll::Ready(Some(r)),
+				_ => Poll::Pending,
+			}
+		})
+		.await;
+
+		match finished {
+			Some((job_id, reply)) => {
+				aborts.remove(&job_id.get());
+				respond(tbl, &mut objects, job_id, reply)
+			}
+			None => {
+				notify = RefAsyncObject::from(tbl.notifier()).read(());
+				while let Some((handle, req)) = tbl.dequeue() {
+					match req {
+						Request::Close => {
+							objects.remove(handle);
+							continue;
+						}
+						Request::Cancel { job_id } => {
+							// The job replies with an error when it is aborted.
+							if let Some(a) = aborts.remove(&job_id.get()) {
+								a.abort();
+							}
+							continue;
+						}
+						_ => {}
+					}
+					let obj = if handle == rt::Handle::MAX {
+						upstream.clone()
+					} else {
+						objects[handle].clone()
+					};
+					match handle_request(rules, obj, handle, req) {
+						Ok((job_id, job)) => {
+							let (job, abort) = future::abortable(job);
+							aborts.insert(job_id.get(), abort);
+							jobs.push(job.map(move |r| {
+								(job_id, r.unwrap_or(Reply::Error(rt::Error::Cancelled)))
+							}));
+						}
+						Err((job_id, reply)) => respond(tbl, &mut objects, job_id, reply),
+					}
+				}
+			}
+		}
+		tbl.flush();
+	}
+}
+
+/// Forward a request, returning a job if it has to wait for the upstream server.
+fn handle_request(
+	rules: &Rules,
+	obj: Rc<AsyncObject>,
+	handle: rt::Handle,
+	req: Request<'_>,
+) -> Result<(JobId, Job), (JobId, Reply)> {
+	Ok(match req {
+		Request::Open { job_id, path } => {
+			let path = copy_data(path);
+			let job = Box::pin(async move {
+				let (r, _) = obj.open(path).await;
+				r.map_or_else(Reply::Error, Reply::Object)
+			});
+			(job_id, job)
+		}
+		Request::Create { job_id, path } => {
+			let path = copy_data(path);
+			if handle == rt::Handle::MAX {
+				let op = str::from_utf8(&path).ok().and_then(Operation::parse);
+				// Deny anything we don't understand.
+				if op.map_or(Action::Deny, |op| rules.check(&op)) == Action::Deny {
+					eprintln!("denied {:?}", String::from_utf8_lossy(&path));
+					return Err((job_id, Reply::Error(rt::Error::InvalidOperation)));
+				}
+			}
+			let job = Box::pin(async move {
+				let (r, _) = obj.create(path).await;
+				let reply = match handle {
+					rt::Handle::MAX => Reply::Direct,
+					_ => Reply::Object,
+				};
+				r.map_or_else(Reply::Error, reply)
+			});
+			(job_id, job)
+		}
+		Request::Read {
+			job_id,
+			amount,
+			peek,
+		} => {
+			let buf = Vec::with_capacity(amount.min(MAX_READ) as usize);
+			let job = Box::pin(async move {
+				let (r, buf) = if peek {
+					let h = obj.as_raw();
+					async_std::queue::submit_mut(|q, b| q.submit_peek(h, b), buf).await
+				} else {
+					obj.read(buf).await
+				};
+				r.map_or_else(Reply::Error, |_| Reply::Data(buf))
+			});
+			(job_id, job)
+		}
+		Request::Write { job_id, data } => {
+			let data = copy_data(data);
+			let job = Box::pin(async move {
+				let (r, _) = obj.write(data).await;
+				r.map_or_else(Reply::Error, Reply::Amount)
+			});
+			(job_id, job)
+		}
+		Request::WriteVectored { job_id, data } => {
+			let data = data.into_iter().flat_map(copy_data).collect::<Vec<_>>();
+			let job = Box::pin(async move {
+				let (r, _) = obj.write(data).await;
+				r.map_or_else(Reply::Error, Reply::Amount)
+			});
+			(job_id, job)
+		}
+		Request::ReadAt {
+			job_id,
+			offset,
+			amount,
+		} => {
+			let buf = Vec::with_capacity(amount.min(MAX_READ) as usize);
+			let job = Box::pin(async move {
+				let h = obj.as_raw();
+				let (r, buf) =
+					async_std::queue::submit_mut(|q, b| q.submit_read_at(h, b, offset), buf).await;
+				r.map_or_else(Reply::Error, |_| Reply::Data(buf))
+			});
+			(job_id, job)
+		}
+		Request::WriteAt {
+			job_id,
+			offset,
+			data,
+		} => {
+			let mut buf = vec![0; data.len()];
+			data.copy_to(0, &mut buf);
+			data.manual_drop();
+			let job = Box::pin(async move {
+				let h = obj.as_raw();
+				let (r, _) =
+					async_std::queue::submit(|q, b| q.submit_write_at(h, b, offset), buf).await;
+				r.map_or_else(Reply::Error, Reply::Amount)
+			});
+			(job_id, job)
+		}
+		Request::Seek { job_id, from } => {
+			let job = Box::pin(async move {
+				let r = obj.seek(from).await;
+				r.map_or_else(Reply::Error, Reply::Position)
+			});
+			(job_id, job)
+		}
+		Request::Share { job_id, share } => {
+			let share = AsyncObject::from(share);
+			let job = Box::pin(async move {
+				let r = obj.share(&share).await;
+				r.map_or_else(Reply::Error, Reply::Share)
+			});
+			(job_id, job)
+		}
+		Request::Destroy { job_id, path } => {
+			path.manual_drop();
+			return Err((job_id, Reply::Error(rt::Error::InvalidOperation)));
+		}
+		Request::GetMeta { job_id, property } => {
+			property.manual_drop();
+			return Err((job_id, Reply::Error(rt::Error::InvalidOperation)));
+		}
+		Request::SetMeta {
+			job_id,
+			property_value,
+		} => {
+			property_value.manual_drop();
+			return Err((job_id, Reply::Error(rt::Error::InvalidOperation)));
+		}
+		// Handled by the caller.
+		Request::Close | Request::Cancel { .. } => unreachable!(),
+	})
+}
+
+fn respond(
+	tbl: &StreamTable,
+	objects: &mut driver_utils::Arena<Rc<AsyncObject>>,
+	job_id: JobId,
+	reply: Reply,
+) {
+	let resp = match reply {
+		Reply::Object(o) => Response::Handle(objects.insert(Rc::new(o))),
+		Reply::Direct(o) => Response::Object(o.into()),
+		Reply::Data(d) => match tbl.alloc(d.len()) {
+			Some(b) => {
+				b.copy_from(0, &d);
+				Response::Data(b)
+			}
+			// The client has to retry, which it should do once it freed some buffers.
+			None => Response::Error(rt::Error::CantCreateObject),
+		},
+		Reply::Amount(n) => Response::Amount(n.try_into().unwrap()),
+		Reply::Position(n) => Response::Position(n),
+		Reply::Share(n) => Response::Share(n),
+		Reply::Error(e) => Response::Error(e),
+	};
+	tbl.enqueue(job_id, resp);
+}
+
+fn copy_data(data: Data<'_>) -> Vec<u8> {
+	let mut v = vec![0; data.len()];
+	data.copy_to(0, &mut v);
+	data.manual_drop();
+	v
+}
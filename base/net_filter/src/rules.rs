@@ -0,0 +1,314 @@
+//! # Filter rules
+//!
+//! Rules are checked in order. The first rule that matches decides whether an operation
+//! is allowed. If no rule matches the default action is taken.
+
+use serde_derive::Deserialize;
+use std::{fmt, net::Ipv6Addr, str::FromStr};
+
+#[derive(Debug, Deserialize)]
+pub struct Rules {
+	/// The action to take if no rule matches.
+	default: Action,
+	#[serde(default, rename = "rule")]
+	rules: Vec<Rule>,
+}
+
+impl Rules {
+	/// Determine whether an operation is allowed.
+	pub fn check(&self, op: &Operation) -> Action {
+		self.rules
+			.iter()
+			.find(|r| r.matches(op))
+			.map_or(self.default, |r| r.action)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+	Allow,
+	Deny,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+	Tcp,
+	Udp,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Type {
+	Listen,
+	Connect,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+	action: Action,
+	protocol: Option<Protocol>,
+	#[serde(rename = "type")]
+	ty: Option<Type>,
+	/// For `listen` and `udp` this is the local address, for `connect` it is the remote
+	/// address.
+	address: Option<Cidr>,
+	/// For `listen` this is the local port, for `connect` it is the remote port.
+	port: Option<u16>,
+}
+
+impl Rule {
+	fn matches(&self, op: &Operation) -> bool {
+		let (protocol, ty, address, port) = match *op {
+			Operation::TcpListen { address, port } => {
+				(Protocol::Tcp, Some(Type::Listen), address, Some(port))
+			}
+			Operation::TcpConnect { address, port } => (
+				Protocol::Tcp,
+				Some(Type::Connect),
+				Some(address),
+				Some(port),
+			),
+			Operation::Udp { address } => (Protocol::Udp, None, address, None),
+		};
+		let address_matches = |c: Cidr| match address {
+			Some(a) if !a.is_unspecified() => c.contains(a),
+			// If the address is not known (i.e. `default`, `::` or an interface name) it may be
+			// any address, so fail toward denying the operation.
+			_ => self.action == Action::Deny,
+		};
+		self.protocol.map_or(true, |p| p == protocol)
+			&& self.ty.map_or(true, |t| Some(t) == ty)
+			&& self.address.map_or(true, address_matches)
+			&& self.port.map_or(true, |p| Some(p) == port)
+	}
+}
+
+/// An IP address with a prefix. IPv4 addresses are stored as IPv4-mapped IPv6 addresses.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Cidr {
+	address: Ipv6Addr,
+	prefix: u8,
+}
+
+impl Cidr {
+	pub fn contains(&self, address: Ipv6Addr) -> bool {
+		let mask = u128::MAX
+			.checked_shl(128 - u32::from(self.prefix))
+			.unwrap_or(0);
+		u128::from(self.address) & mask == u128::from(address) & mask
+	}
+}
+
+impl FromStr for Cidr {
+	type Err = InvalidCidr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (address, prefix) = s.split_once('/').map_or((s, None), |(a, p)| (a, Some(p)));
+		let prefix = prefix
+			.map(|p| p.parse::<u8>().map_err(|_| InvalidCidr))
+			.transpose()?;
+		let (address, prefix) = match std::net::IpAddr::from_str(address) {
+			Ok(std::net::IpAddr::V4(a)) => {
+				(a.to_ipv6_mapped(), prefix.map(|p| p.saturating_add(96)))
+			}
+			Ok(std::net::IpAddr::V6(a)) => (a, prefix),
+			Err(_) => return Err(InvalidCidr),
+		};
+		let prefix = prefix.unwrap_or(128);
+		(prefix <= 128)
+			.then(|| Self { address, prefix })
+			.ok_or(InvalidCidr)
+	}
+}
+
+impl TryFrom<String> for Cidr {
+	type Error = InvalidCidr;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+#[derive(Debug)]
+pub struct InvalidCidr;
+
+impl fmt::Display for InvalidCidr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		"invalid address".fmt(f)
+	}
+}
+
+/// An operation on the root of a network table.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Operation {
+	TcpListen {
+		address: Option<Ipv6Addr>,
+		port: u16,
+	},
+	TcpConnect {
+		address: Ipv6Addr,
+		port: u16,
+	},
+	Udp {
+		address: Option<Ipv6Addr>,
+	},
+}
+
+impl Operation {
+	/// Parse a path passed to `create`, i.e. `<addr>/tcp/listen/<port>`,
+	/// `<addr>/tcp/connect/<addr>/<port>` or `<addr>/udp`.
+	///
+	/// `<addr>` may also be `default` or the name of an interface, in which case the address
+	/// is not known.
+	pub fn parse(path: &str) -> Option<Self> {
+		let mut parts = path.split('/');
+		let source = parts.next()?;
+		let source = match Ipv6Addr::from_str(source) {
+			Ok(a) => Some(a),
+			Err(_) if !source.is_empty() && source.bytes().all(|c| c.is_ascii_alphanumeric()) => {
+				None
+			}
+			Err(_) => return None,
+		};
+		// The source address of a connection is not checked but it must still be valid.
+		let op = match (parts.next()?, parts.next()) {
+			("tcp", Some("listen")) => Self::TcpListen {
+				address: source,
+				port: parts.next()?.parse().ok()?,
+			},
+			("tcp", Some("connect")) => Self::TcpConnect {
+				address: Ipv6Addr::from_str(parts.next()?).ok()?,
+				port: parts.next()?.parse().ok()?,
+			},
+			("udp", None) => return Some(Self::Udp { address: source }),
+			_ => return None,
+		};
+		parts.next().is_none().then(|| op)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn rules(s: &str) -> Rules {
+		toml::from_str(s).unwrap()
+	}
+
+	#[test]
+	fn parse_connect() {
+		assert_eq!(
+			Operation::parse("default/tcp/connect/::ffff:10.0.2.2/80"),
+			Some(Operation::TcpConnect {
+				address: "::ffff:10.0.2.2".parse().unwrap(),
+				port: 80,
+			}),
+		);
+	}
+
+	#[test]
+	fn parse_invalid() {
+		assert_eq!(Operation::parse("default/tcp/connect/::1"), None);
+		assert_eq!(Operation::parse("default/tcp/listen/80/x"), None);
+		assert_eq!(Operation::parse("no:pe/udp"), None);
+		assert_eq!(Operation::parse("/udp"), None);
+	}
+
+	#[test]
+	fn parse_iface() {
+		assert_eq!(
+			Operation::parse("eth1/tcp/listen/22"),
+			Some(Operation::TcpListen {
+				address: None,
+				port: 22,
+			}),
+		);
+	}
+
+	#[test]
+	fn cidr_ipv4() {
+		let c = Cidr::from_str("10.0.0.0/8").unwrap();
+		assert!(c.contains("::ffff:10.1.2.3".parse().unwrap()));
+		assert!(!c.contains("::ffff:11.1.2.3".parse().unwrap()));
+	}
+
+	#[test]
+	fn first_match_wins() {
+		let r = rules(
+			r#"
+			default = "deny"
+
+			[[rule]]
+			action = "deny"
+			address = "10.0.2.3"
+
+			[[rule]]
+			action = "allow"
+			protocol = "tcp"
+			type = "connect"
+			address = "10.0.0.0/8"
+			"#,
+		);
+		let op = |a: &str| Operation::TcpConnect {
+			address: a.parse().unwrap(),
+			port: 80,
+		};
+		assert_eq!(r.check(&op("::ffff:10.0.2.2")), Action::Allow);
+		assert_eq!(r.check(&op("::ffff:10.0.2.3")), Action::Deny);
+		assert_eq!(r.check(&op("::1")), Action::Deny);
+	}
+
+	#[test]
+	fn unknown_address() {
+		let r = rules(
+			r#"
+			default = "allow"
+
+			[[rule]]
+			action = "deny"
+			type = "listen"
+			port = 22
+			"#,
+		);
+		let op = |port| Operation::TcpListen {
+			address: None,
+			port,
+		};
+		assert_eq!(r.check(&op(22)), Action::Deny);
+		assert_eq!(r.check(&op(80)), Action::Allow);
+	}
+
+	#[test]
+	fn unknown_address_denied() {
+		let r = rules(
+			r#"
+			default = "allow"
+
+			[[rule]]
+			action = "deny"
+			type = "listen"
+			address = "10.0.2.15"
+			"#,
+		);
+		for source in ["default", "eth0", "::"] {
+			let op = Operation::parse(&format!("{}/tcp/listen/80", source)).unwrap();
+			assert_eq!(r.check(&op), Action::Deny, "{}", source);
+		}
+
+		let r = rules(
+			r#"
+			default = "deny"
+
+			[[rule]]
+			action = "allow"
+			protocol = "udp"
+			address = "10.0.0.0/8"
+			"#,
+		);
+		let op = Operation::parse("default/udp").unwrap();
+		assert_eq!(r.check(&op), Action::Deny);
+	}
+}
@@ -0,0 +1,200 @@
+//! Server side of the SCP protocol.
+//!
+//! This is started by the SSH server when a client runs `scp`. `-t` receives files and `-f`
+//! sends files. All paths are resolved relative to the file root of the process.
+
+use file_util::{file_size, metadata, normalize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read, Write};
+
+const BUFFER_SIZE: usize = 1 << 15;
+
+struct Options {
+	to: bool,
+	from: bool,
+	recursive: bool,
+	target_is_dir: bool,
+	paths: Vec<String>,
+}
+
+fn main() {
+	let mut opt = Options {
+		to: false,
+		from: false,
+		recursive: false,
+		target_is_dir: false,
+		paths: Vec::new(),
+	};
+	let mut args = std::env::args().skip(1);
+	while let Some(a) = args.next() {
+		match a.as_str() {
+			"-t" => opt.to = true,
+			"-f" => opt.from = true,
+			"-r" => opt.recursive = true,
+			"-d" => opt.target_is_dir = true,
+			// Verbose output and preserving times are not supported but harmless.
+			"-v" | "-p" => {}
+			"--" => {
+				opt.paths.extend(args);
+				break;
+			}
+			_ => opt.paths.push(a),
+		}
+	}
+
+	let (stdin, stdout) = (io::stdin(), io::stdout());
+	let (mut stdin, mut stdout) = (stdin.lock(), stdout.lock());
+	let res = match (opt.to, opt.from, &opt.paths[..]) {
+		(true, false, [target]) => sink(&mut stdin, &mut stdout, &opt, &normalize(target)),
+		(false, true, [_, ..]) => opt
+			.paths
+			.iter()
+			.try_for_each(|p| source(&mut stdin, &mut stdout, &normalize(p))),
+		_ => Err(error("usage: scp [-r] [-d] -t <target> | -f <path>...")),
+	};
+	if let Err(e) = res {
+		// Let the client know why we failed.
+		let _ = write!(stdout, "\x01scp: {}\n", e);
+		let _ = stdout.flush();
+		std::process::exit(1);
+	}
+}
+
+/// Receive files.
+fn sink(
+	stdin: &mut impl BufRead,
+	stdout: &mut impl Write,
+	opt: &Options,
+	target: &str,
+) -> io::Result<()> {
+	let target_is_dir = opt.target_is_dir || metadata(target).map_or(false, |m| m.dir);
+	let mut dirs = Vec::new();
+	ack(stdout)?;
+	let mut line = Vec::new();
+	loop {
+		line.clear();
+		if stdin.read_until(b'\n', &mut line)? == 0 {
+			return Ok(());
+		}
+		let line = std::str::from_utf8(&line)
+			.map_err(|_| error("invalid command"))?
+			.trim_end_matches('\n');
+		let (cmd, args) = line.split_at(line.len().min(1));
+		match cmd {
+			"C" => {
+				let (size, name) = parse_entry(args)?;
+				let path = if target_is_dir || !dirs.is_empty() {
+					join(target, &dirs, name)
+				} else {
+					target.into()
+				};
+				let mut f = File::create(&path)?;
+				ack(stdout)?;
+				copy(stdin, &mut f, size)?;
+				// The data is followed by a status byte.
+				let mut status = [0];
+				stdin.read_exact(&mut status)?;
+				ack(stdout)?;
+			}
+			"D" if opt.recursive => {
+				let (_, name) = parse_entry(args)?;
+				let path = join(target, &dirs, name);
+				// If the target doesn't exist yet, the first directory becomes the target.
+				if dirs.is_empty() && !target_is_dir {
+					create_dir(target)?;
+					dirs.push(String::new());
+				} else {
+					create_dir(&path)?;
+					dirs.push(name.into());
+				}
+				ack(stdout)?;
+			}
+			"E" if !dirs.is_empty() => {
+				dirs.pop();
+				ack(stdout)?;
+			}
+			// Times are not supported.
+			"T" => ack(stdout)?,
+			"\x01" => eprintln!("scp: {}", args),
+			"\x02" => return Err(error(args)),
+			_ => return Err(error("unsupported command")),
+		}
+	}
+}
+
+/// Send a single file.
+fn source(stdin: &mut impl Read, stdout: &mut impl Write, path: &str) -> io::Result<()> {
+	wait_ack(stdin)?;
+	let mut f = File::open(path)?;
+	let size = file_size(&mut f)?;
+	let name = path.rsplit('/').next().unwrap_or(path);
+	write!(stdout, "C0644 {} {}\n", size, name)?;
+	stdout.flush()?;
+	wait_ack(stdin)?;
+	copy(&mut f, stdout, size)?;
+	ack(stdout)?;
+	Ok(())
+}
+
+/// Parse the `<mode> <size> <name>` part of a `C` or `D` command.
+fn parse_entry(args: &str) -> io::Result<(u64, &str)> {
+	let mut it = args.splitn(3, ' ');
+	let _mode = it.next();
+	let size = it.next().and_then(|s| s.parse().ok());
+	let name = it
+		.next()
+		.filter(|n| !n.is_empty() && *n != "." && *n != "..");
+	match (size, name) {
+		(Some(size), Some(name)) if !name.contains('/') => Ok((size, name)),
+		_ => Err(error("invalid entry")),
+	}
+}
+
+fn copy(from: &mut impl Read, to: &mut impl Write, mut size: u64) -> io::Result<()> {
+	let mut buf = vec![0; BUFFER_SIZE];
+	while size > 0 {
+		let n = buf.len().min(size.try_into().unwrap_or(usize::MAX));
+		let n = from.read(&mut buf[..n])?;
+		if n == 0 {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+		to.write_all(&buf[..n])?;
+		size -= n as u64;
+	}
+	to.flush()
+}
+
+fn ack(stdout: &mut impl Write) -> io::Result<()> {
+	stdout.write_all(&[0])?;
+	stdout.flush()
+}
+
+fn wait_ack(stdin: &mut impl Read) -> io::Result<()> {
+	let mut b = [0];
+	stdin.read_exact(&mut b)?;
+	match b[0] {
+		0 => Ok(()),
+		_ => Err(error("transfer aborted by client")),
+	}
+}
+
+fn create_dir(path: &str) -> io::Result<()> {
+	match fs::create_dir(path) {
+		Err(e) if e.kind() != io::ErrorKind::AlreadyExists => Err(e),
+		_ => Ok(()),
+	}
+}
+
+fn join(target: &str, dirs: &[String], name: &str) -> String {
+	[target]
+		.into_iter()
+		.chain(dirs.iter().map(|s| s.as_str()))
+		.chain([name])
+		.filter(|s| !s.is_empty())
+		.collect::<Vec<_>>()
+		.join("/")
+}
+
+fn error(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, msg)
+}
@@ -0,0 +1,333 @@
+//! SFTP (version 3) server.
+//!
+//! The protocol is spoken over stdin and stdout, so it can be started by the SSH server like any
+//! other command. All paths are resolved relative to the file root of the process.
+//!
+//! The SSH server can't start it as the `sftp` subsystem yet, as nora_ssh doesn't report
+//! subsystem requests. Clients have to run it as a command instead, e.g.
+//! `sftp -s sftp_server <host>`.
+//!
+//! File attributes can't be changed, so `SETSTAT` and `FSETSTAT` are answered with
+//! `SSH_FX_OP_UNSUPPORTED`.
+
+#![feature(let_else)]
+
+mod wire;
+
+use file_util::{file_size, normalize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions, ReadDir};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use wire::{Packet, Reader};
+
+const VERSION: u32 = 3;
+
+const FXP_INIT: u8 = 1;
+const FXP_VERSION: u8 = 2;
+const FXP_OPEN: u8 = 3;
+const FXP_CLOSE: u8 = 4;
+const FXP_READ: u8 = 5;
+const FXP_WRITE: u8 = 6;
+const FXP_LSTAT: u8 = 7;
+const FXP_FSTAT: u8 = 8;
+const FXP_SETSTAT: u8 = 9;
+const FXP_FSETSTAT: u8 = 10;
+const FXP_OPENDIR: u8 = 11;
+const FXP_READDIR: u8 = 12;
+const FXP_REMOVE: u8 = 13;
+const FXP_REALPATH: u8 = 16;
+const FXP_STAT: u8 = 17;
+const FXP_STATUS: u8 = 101;
+const FXP_HANDLE: u8 = 102;
+const FXP_DATA: u8 = 103;
+const FXP_NAME: u8 = 104;
+const FXP_ATTRS: u8 = 105;
+
+const FXF_READ: u32 = 1;
+const FXF_WRITE: u32 = 2;
+const FXF_APPEND: u32 = 4;
+const FXF_CREAT: u32 = 8;
+const FXF_TRUNC: u32 = 0x10;
+const FXF_EXCL: u32 = 0x20;
+
+const ATTR_SIZE: u32 = 1;
+const ATTR_PERMISSIONS: u32 = 4;
+
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+
+/// The maximum amount of data returned by a single read.
+const MAX_READ: u32 = 1 << 15;
+/// The maximum amount of entries returned by a single readdir.
+const MAX_ENTRIES: usize = 64;
+
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum Status {
+	Ok = 0,
+	Eof = 1,
+	NoSuchFile = 2,
+	PermissionDenied = 3,
+	Failure = 4,
+	BadMessage = 5,
+	OpUnsupported = 8,
+}
+
+impl Status {
+	fn message(self) -> &'static str {
+		match self {
+			Self::Ok => "ok",
+			Self::Eof => "end of file",
+			Self::NoSuchFile => "no such file",
+			Self::PermissionDenied => "permission denied",
+			Self::Failure => "failure",
+			Self::BadMessage => "bad message",
+			Self::OpUnsupported => "operation unsupported",
+		}
+	}
+}
+
+impl From<io::Error> for Status {
+	fn from(e: io::Error) -> Self {
+		match e.kind() {
+			io::ErrorKind::NotFound => Self::NoSuchFile,
+			io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+			io::ErrorKind::Unsupported => Self::OpUnsupported,
+			_ => Self::Failure,
+		}
+	}
+}
+
+enum Handle {
+	File(File),
+	Dir(ReadDir),
+}
+
+struct Attrs {
+	size: u64,
+	dir: bool,
+}
+
+impl Attrs {
+	fn encode(&self, pkt: Packet) -> Packet {
+		let mode = if self.dir {
+			S_IFDIR | 0o755
+		} else {
+			S_IFREG | 0o644
+		};
+		pkt.u32(ATTR_SIZE | ATTR_PERMISSIONS)
+			.u64(self.size)
+			.u32(mode)
+	}
+
+	/// Format an entry like `ls -l` does, which is what clients show to users.
+	fn long_name(&self, name: &str) -> String {
+		let mode = if self.dir { "drwxr-xr-x" } else { "-rw-r--r--" };
+		format!("{} 1 0 0 {:>8} Jan  1  1970 {}", mode, self.size, name)
+	}
+}
+
+struct Server<W: Write> {
+	out: W,
+	handles: HashMap<u32, Handle>,
+	next_handle: u32,
+}
+
+fn main() {
+	let (stdin, stdout) = (io::stdin(), io::stdout());
+	let mut stdin = stdin.lock();
+	let mut server = Server {
+		out: stdout.lock(),
+		handles: HashMap::new(),
+		next_handle: 0,
+	};
+	loop {
+		match wire::read_packet(&mut stdin) {
+			Ok(Some(pkt)) => {
+				if server.handle(&pkt).is_err() {
+					break;
+				}
+			}
+			Ok(None) => break,
+			Err(e) => {
+				eprintln!("failed to read packet: {}", e);
+				break;
+			}
+		}
+	}
+}
+
+impl<W: Write> Server<W> {
+	fn handle(&mut self, pkt: &[u8]) -> io::Result<()> {
+		let mut r = Reader(pkt);
+		let ty = r.u8().ok_or(io::ErrorKind::InvalidData)?;
+		if ty == FXP_INIT {
+			// Extensions aren't supported so ignore whatever the client sends.
+			return Packet::new(FXP_VERSION).u32(VERSION).send(&mut self.out);
+		}
+		let id = r.u32().ok_or(io::ErrorKind::InvalidData)?;
+		// Requests either get a specific reply or a status.
+		let res = match ty {
+			FXP_OPEN => self.open(id, &mut r),
+			FXP_CLOSE => self.close(&mut r),
+			FXP_READ => self.read(id, &mut r),
+			FXP_WRITE => self.write(&mut r),
+			FXP_LSTAT | FXP_STAT => self.stat(id, &mut r),
+			FXP_FSTAT => self.fstat(id, &mut r),
+			// Attributes can't be changed.
+			FXP_SETSTAT | FXP_FSETSTAT => Err(Status::OpUnsupported),
+			FXP_OPENDIR => self.open_dir(id, &mut r),
+			FXP_READDIR => self.read_dir(id, &mut r),
+			FXP_REMOVE => self.remove(&mut r),
+			FXP_REALPATH => self.real_path(id, &mut r),
+			_ => Err(Status::OpUnsupported),
+		};
+		match res {
+			Ok(pkt) => pkt.send(&mut self.out),
+			Err(s) => Packet::new(FXP_STATUS)
+				.u32(id)
+				.u32(s as u32)
+				.string(s.message())
+				.string("")
+				.send(&mut self.out),
+		}
+	}
+
+	fn open(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let path = normalize(r.str().ok_or(Status::BadMessage)?);
+		let flags = r.u32().ok_or(Status::BadMessage)?;
+		let f = OpenOptions::new()
+			.read(flags & FXF_READ != 0)
+			.write(flags & FXF_WRITE != 0)
+			.append(flags & FXF_APPEND != 0)
+			.create(flags & FXF_CREAT != 0)
+			.truncate(flags & FXF_TRUNC != 0)
+			.create_new(flags & FXF_EXCL != 0)
+			.open(path)?;
+		Ok(self.add_handle(id, Handle::File(f)))
+	}
+
+	fn close(&mut self, r: &mut Reader) -> Result<Packet, Status> {
+		let h = handle(r)?;
+		self.handles.remove(&h).ok_or(Status::Failure)?;
+		Err(Status::Ok)
+	}
+
+	fn read(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let h = handle(r)?;
+		let offset = r.u64().ok_or(Status::BadMessage)?;
+		let len = r.u32().ok_or(Status::BadMessage)?.min(MAX_READ);
+		let Some(Handle::File(f)) = self.handles.get_mut(&h) else {
+			return Err(Status::Failure);
+		};
+		f.seek(SeekFrom::Start(offset))?;
+		let mut buf = vec![0; len as usize];
+		let n = f.read(&mut buf)?;
+		if n == 0 && len > 0 {
+			return Err(Status::Eof);
+		}
+		Ok(Packet::new(FXP_DATA).u32(id).string(&buf[..n]))
+	}
+
+	fn write(&mut self, r: &mut Reader) -> Result<Packet, Status> {
+		let h = handle(r)?;
+		let offset = r.u64().ok_or(Status::BadMessage)?;
+		let data = r.string().ok_or(Status::BadMessage)?;
+		let Some(Handle::File(f)) = self.handles.get_mut(&h) else {
+			return Err(Status::Failure);
+		};
+		f.seek(SeekFrom::Start(offset))?;
+		f.write_all(data)?;
+		Err(Status::Ok)
+	}
+
+	fn stat(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let path = normalize(r.str().ok_or(Status::BadMessage)?);
+		Ok(stat(&path)?.encode(Packet::new(FXP_ATTRS).u32(id)))
+	}
+
+	fn fstat(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let h = handle(r)?;
+		let attrs = match self.handles.get_mut(&h).ok_or(Status::Failure)? {
+			Handle::File(f) => Attrs {
+				size: file_size(f)?,
+				dir: false,
+			},
+			Handle::Dir(_) => Attrs { size: 0, dir: true },
+		};
+		Ok(attrs.encode(Packet::new(FXP_ATTRS).u32(id)))
+	}
+
+	fn open_dir(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let path = normalize(r.str().ok_or(Status::BadMessage)?);
+		let d = fs::read_dir(&path)?;
+		Ok(self.add_handle(id, Handle::Dir(d)))
+	}
+
+	fn read_dir(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let h = handle(r)?;
+		let Some(Handle::Dir(d)) = self.handles.get_mut(&h) else {
+			return Err(Status::Failure);
+		};
+		let mut entries = Vec::new();
+		for e in d.take(MAX_ENTRIES) {
+			let e = e?;
+			let path = e.path();
+			let Some(path) = path.to_str() else { continue };
+			let name = path.rsplit('/').next().unwrap_or(path);
+			// Not all entries can be inspected, e.g. due to lack of permissions.
+			let attrs = stat(path).unwrap_or(Attrs {
+				size: 0,
+				dir: false,
+			});
+			entries.push((name.to_string(), attrs));
+		}
+		if entries.is_empty() {
+			return Err(Status::Eof);
+		}
+		let mut pkt = Packet::new(FXP_NAME).u32(id).u32(entries.len() as u32);
+		for (name, attrs) in entries {
+			pkt = pkt.string(&name).string(attrs.long_name(&name));
+			pkt = attrs.encode(pkt);
+		}
+		Ok(pkt)
+	}
+
+	fn remove(&mut self, r: &mut Reader) -> Result<Packet, Status> {
+		let path = normalize(r.str().ok_or(Status::BadMessage)?);
+		fs::remove_file(path)?;
+		Err(Status::Ok)
+	}
+
+	fn real_path(&mut self, id: u32, r: &mut Reader) -> Result<Packet, Status> {
+		let path = format!("/{}", normalize(r.str().ok_or(Status::BadMessage)?));
+		// Attributes are optional for this reply, so don't bother with them.
+		Ok(Packet::new(FXP_NAME)
+			.u32(id)
+			.u32(1)
+			.string(&path)
+			.string(&path)
+			.u32(0))
+	}
+
+	fn add_handle(&mut self, id: u32, handle: Handle) -> Packet {
+		let h = self.next_handle;
+		self.next_handle = self.next_handle.wrapping_add(1);
+		self.handles.insert(h, handle);
+		Packet::new(FXP_HANDLE).u32(id).string(h.to_be_bytes())
+	}
+}
+
+fn handle(r: &mut Reader) -> Result<u32, Status> {
+	let h = r.string().ok_or(Status::BadMessage)?;
+	Ok(u32::from_be_bytes(
+		h.try_into().map_err(|_| Status::Failure)?,
+	))
+}
+
+fn stat(path: &str) -> io::Result<Attrs> {
+	file_util::metadata(path).map(|m| Attrs {
+		size: m.size,
+		dir: m.dir,
+	})
+}
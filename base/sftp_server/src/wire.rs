@@ -0,0 +1,89 @@
+//! Encoding and decoding of SFTP packets.
+
+use std::io::{self, Read, Write};
+
+/// The largest packet we accept. Clients send at most 32 KiB of data per write by default.
+const MAX_PACKET_SIZE: usize = 1 << 18;
+
+/// Read a single packet, without the length prefix.
+pub fn read_packet(r: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+	let mut len = [0; 4];
+	match r.read_exact(&mut len) {
+		Ok(()) => {}
+		Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e),
+	}
+	let len = u32::from_be_bytes(len) as usize;
+	if len == 0 || len > MAX_PACKET_SIZE {
+		return Err(io::ErrorKind::InvalidData.into());
+	}
+	let mut pkt = vec![0; len];
+	r.read_exact(&mut pkt)?;
+	Ok(Some(pkt))
+}
+
+pub struct Reader<'a>(pub &'a [u8]);
+
+impl<'a> Reader<'a> {
+	pub fn u8(&mut self) -> Option<u8> {
+		let (&b, rest) = self.0.split_first()?;
+		self.0 = rest;
+		Some(b)
+	}
+
+	pub fn u32(&mut self) -> Option<u32> {
+		let b = self.0.get(..4)?.try_into().ok()?;
+		self.0 = &self.0[4..];
+		Some(u32::from_be_bytes(b))
+	}
+
+	pub fn u64(&mut self) -> Option<u64> {
+		let b = self.0.get(..8)?.try_into().ok()?;
+		self.0 = &self.0[8..];
+		Some(u64::from_be_bytes(b))
+	}
+
+	pub fn string(&mut self) -> Option<&'a [u8]> {
+		let len = self.u32()? as usize;
+		let s = self.0.get(..len)?;
+		self.0 = &self.0[len..];
+		Some(s)
+	}
+
+	pub fn str(&mut self) -> Option<&'a str> {
+		std::str::from_utf8(self.string()?).ok()
+	}
+}
+
+/// A packet that is being built. The length is filled in by [`Packet::send`].
+pub struct Packet(Vec<u8>);
+
+impl Packet {
+	pub fn new(ty: u8) -> Self {
+		Self(vec![0, 0, 0, 0, ty])
+	}
+
+	pub fn u32(mut self, n: u32) -> Self {
+		self.0.extend(n.to_be_bytes());
+		self
+	}
+
+	pub fn u64(mut self, n: u64) -> Self {
+		self.0.extend(n.to_be_bytes());
+		self
+	}
+
+	pub fn string(mut self, s: impl AsRef<[u8]>) -> Self {
+		let s = s.as_ref();
+		self = self.u32(s.len() as u32);
+		self.0.extend(s);
+		self
+	}
+
+	pub fn send(mut self, w: &mut impl Write) -> io::Result<()> {
+		let len = (self.0.len() - 4) as u32;
+		self.0[..4].copy_from_slice(&len.to_be_bytes());
+		w.write_all(&self.0)?;
+		w.flush()
+	}
+}
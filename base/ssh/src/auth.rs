@@ -0,0 +1,164 @@
+//! User authentication.
+//!
+//! Users are authenticated with the `passwords` file in the configuration directory. It has one
+//! user per line in the form `<user> pbkdf2-sha256$<rounds>$<base64 salt>$<base64 hash>`.
+//!
+//! Empty lines and lines starting with `#` are ignored.
+
+use hmac::Hmac;
+use sha2::Sha256;
+
+const PASSWORD_SCHEME: &str = "pbkdf2-sha256";
+const SSH_MSG_USERAUTH_REQUEST: u8 = 50;
+
+/// A parsed `SSH_MSG_USERAUTH_REQUEST` as described in RFC 4252.
+pub struct Request<'a> {
+	pub user: &'a str,
+	pub method: Method<'a>,
+}
+
+pub enum Method<'a> {
+	None,
+	Password(&'a [u8]),
+	Other(&'a [u8]),
+}
+
+impl Method<'_> {
+	pub fn name(&self) -> &str {
+		match self {
+			Self::None => "none",
+			Self::Password(_) => "password",
+			Self::Other(m) => core::str::from_utf8(m).unwrap_or("unknown method"),
+		}
+	}
+}
+
+impl<'a> Request<'a> {
+	/// Parse the payload of a request, which must start with the message number.
+	pub fn parse(data: &'a [u8]) -> Option<Self> {
+		let mut r = Reader(data.strip_prefix(&[SSH_MSG_USERAUTH_REQUEST])?);
+		let user = core::str::from_utf8(r.string()?).ok()?;
+		let _service = r.string()?;
+		let method = match r.string()? {
+			b"none" => Method::None,
+			b"password" => {
+				// Password changes are not supported.
+				if r.bool()? {
+					return None;
+				}
+				Method::Password(r.string()?)
+			}
+			m => Method::Other(m),
+		};
+		Some(Self { user, method })
+	}
+}
+
+/// Check a password against the hash stored for the user.
+pub fn is_password_valid(passwords: &str, user: &str, password: &[u8]) -> bool {
+	entries(passwords)
+		.filter(|(u, _)| *u == user)
+		.any(|(_, hash)| check_password(hash, password).unwrap_or(false))
+}
+
+fn check_password(hash: &str, password: &[u8]) -> Option<bool> {
+	let mut f = hash.split('$');
+	if f.next()? != PASSWORD_SCHEME {
+		return None;
+	}
+	let rounds = f.next()?.parse().ok()?;
+	let salt = base64::decode(f.next()?).ok()?;
+	let expected = base64::decode(f.next()?).ok()?;
+	if f.next().is_some() {
+		return None;
+	}
+	let hash = pbkdf2(password, &salt, rounds);
+	// Compare in constant time to avoid leaking how much of the hash matched.
+	Some(
+		expected.len() == hash.len()
+			&& expected.iter().zip(hash).fold(0, |d, (a, b)| d | (a ^ b)) == 0,
+	)
+}
+
+fn pbkdf2(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 32] {
+	let mut hash = [0; 32];
+	pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, rounds, &mut hash);
+	hash
+}
+
+/// Split a configuration file in `(user, rest)` pairs.
+fn entries(file: &str) -> impl Iterator<Item = (&str, &str)> {
+	file.lines()
+		.map(str::trim)
+		.filter(|l| !l.is_empty() && !l.starts_with('#'))
+		.filter_map(|l| l.split_once(|c: char| c.is_ascii_whitespace()))
+		.map(|(u, r)| (u, r.trim_start()))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+	fn bool(&mut self) -> Option<bool> {
+		let (&b, rest) = self.0.split_first()?;
+		self.0 = rest;
+		Some(b != 0)
+	}
+
+	fn string(&mut self) -> Option<&'a [u8]> {
+		let len = self.0.get(..4)?.try_into().ok()?;
+		let len = u32::from_be_bytes(len).try_into().ok()?;
+		let s = self.0[4..].get(..len)?;
+		self.0 = &self.0[4 + len..];
+		Some(s)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use alloc::{string::String, vec::Vec};
+
+	fn hash_password(password: &[u8], salt: &[u8], rounds: u32) -> String {
+		let hash = pbkdf2(password, salt, rounds);
+		alloc::format!(
+			"{}${}${}${}",
+			PASSWORD_SCHEME,
+			rounds,
+			base64::encode(salt),
+			base64::encode(hash)
+		)
+	}
+
+	fn string(v: &mut Vec<u8>, s: &[u8]) {
+		v.extend((s.len() as u32).to_be_bytes());
+		v.extend(s);
+	}
+
+	#[test]
+	fn parse_password() {
+		let mut v = Vec::from([SSH_MSG_USERAUTH_REQUEST]);
+		string(&mut v, b"alice");
+		string(&mut v, b"ssh-connection");
+		string(&mut v, b"password");
+		v.push(0);
+		string(&mut v, b"hunter2");
+		let r = Request::parse(&v).unwrap();
+		assert_eq!(r.user, "alice");
+		assert!(matches!(r.method, Method::Password(b"hunter2")));
+		assert!(Request::parse(&v[1..]).is_none());
+		assert!(Request::parse(&v[..v.len() - 1]).is_none());
+	}
+
+	#[test]
+	fn password() {
+		let passwords = alloc::format!(
+			"# comment\n\nbob {}\nalice {}\n",
+			hash_password(b"bob", b"pepper", 10),
+			hash_password(b"hunter2", b"salt", 10),
+		);
+		assert!(is_password_valid(&passwords, "alice", b"hunter2"));
+		assert!(!is_password_valid(&passwords, "alice", b"hunter3"));
+		assert!(!is_password_valid(&passwords, "alice", b"bob"));
+		assert!(!is_password_valid(&passwords, "carol", b"hunter2"));
+	}
+}
@@ -1,11 +1,18 @@
+#![feature(let_else)]
+
 extern crate alloc;
 
-use alloc::{boxed::Box, rc::Rc};
+mod auth;
+mod session;
+
+use alloc::{boxed::Box, rc::Rc, string::String, vec::Vec};
 
 use async_std::{
 	compat::{AsyncWrapR, AsyncWrapRW, AsyncWrapW},
-	env,
+	env, eprintln, fs,
+	io::{self, Write},
 	net::{Ipv4Addr, TcpListener, TcpStream},
+	object::file_root,
 	process,
 };
 use core::{
@@ -29,29 +36,91 @@ use nora_ssh::{
 };
 use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 
+/// Directory with the host key and authentication files if none is given as argument.
+const DEFAULT_CONFIG_DIR: &str = "file/ssh";
+
 fn main() -> ! {
 	async_std::task::block_on(async {
-		let mut rng = rand::rngs::StdRng::seed_from_u64(0);
-		let server_secret = ecdsa::SigningKey::<p256::NistP256>::random(&mut rng);
+		let config_dir = std::env::args()
+			.nth(1)
+			.unwrap_or_else(|| DEFAULT_CONFIG_DIR.into());
+		let server_secret = load_host_key(&config_dir).await;
 
 		let addr = (Ipv4Addr::UNSPECIFIED, 22);
 		let listener = TcpListener::bind(addr).await.unwrap();
 		let server = Server::new(
 			Identifier::new(b"SSH-2.0-nora_ssh example").unwrap(),
 			server_secret,
-			Handlers { listener },
+			Handlers {
+				listener,
+				config_dir,
+			},
 		);
 
 		server.start().await
 	})
 }
 
+/// Load the host key or generate and store a new one if it doesn't exist yet.
+async fn load_host_key(config_dir: &str) -> ecdsa::SigningKey<p256::NistP256> {
+	let path = format!("{}/host_key", config_dir);
+	match fs::read(Vec::from(path.as_bytes())).await.0 {
+		Ok(key) => match ecdsa::SigningKey::from_bytes(&key) {
+			Ok(key) => return key,
+			Err(_) => eprintln!("{} is not a valid host key", path).await,
+		},
+		Err(io::Error::DoesNotExist) => {}
+		Err(e) => eprintln!("failed to read {}: {:?}", path, e).await,
+	}
+
+	let key = ecdsa::SigningKey::random(&mut StdRng::from_entropy());
+	let data = key.to_bytes().to_vec();
+	let len = data.len();
+	let res = match file_root().create(Vec::from(path.as_bytes())).await.0 {
+		Ok(f) => match f.write(data).await.0 {
+			Ok(n) if n == len => Ok(()),
+			Ok(_) => Err(io::Error::Unknown),
+			Err(e) => Err(e),
+		},
+		Err(e) => Err(e),
+	};
+	match res {
+		Ok(()) => eprintln!("generated new host key in {}", path).await,
+		Err(e) => {
+			eprintln!("failed to store host key in {}: {:?}", path, e).await;
+			eprintln!("the host key will change on every start").await;
+		}
+	}
+	key
+}
+
+/// Read an authentication file, treating a missing file as empty.
+async fn read_config(config_dir: &str, name: &str) -> String {
+	let path = format!("{}/{}", config_dir, name);
+	match fs::read(Vec::from(path.as_bytes())).await.0 {
+		Ok(f) => match String::from_utf8(f) {
+			Ok(f) => f,
+			Err(_) => {
+				eprintln!("{} is not valid UTF-8", path).await;
+				String::new()
+			}
+		},
+		Err(io::Error::DoesNotExist) => String::new(),
+		Err(e) => {
+			eprintln!("failed to read {}: {:?}", path, e).await;
+			String::new()
+		}
+	}
+}
+
 struct Handlers {
 	listener: TcpListener,
+	config_dir: String,
 }
 
 struct User {
 	name: Box<str>,
+	profile: session::Profile,
 	shell: Option<Rc<process::Child>>,
 }
 
@@ -72,8 +141,47 @@ impl ServerHandlers for Handlers {
 	}
 
 	async fn authenticate<'a>(&self, data: &'a [u8]) -> Result<Self::User, ()> {
+		let Some(req) = auth::Request::parse(data) else {
+			eprintln!("rejected malformed authentication request").await;
+			return Err(());
+		};
+		let ok = match &req.method {
+			auth::Method::Password(password) => {
+				let passwords = read_config(&self.config_dir, "passwords").await;
+				auth::is_password_valid(&passwords, req.user, password)
+			}
+			auth::Method::None | auth::Method::Other(_) => false,
+		};
+		if !ok {
+			eprintln!(
+				"rejected {} authentication for {:?}",
+				req.method.name(),
+				req.user
+			)
+			.await;
+			return Err(());
+		}
+		let sessions = read_config(&self.config_dir, "sessions.toml").await;
+		let profile = match session::Profile::find(&sessions, req.user) {
+			Ok(Some(p)) => p,
+			Ok(None) => {
+				eprintln!("rejected {:?}: no session profile", req.user).await;
+				return Err(());
+			}
+			Err(e) => {
+				eprintln!("rejected {:?}: invalid sessions.toml: {}", req.user, e).await;
+				return Err(());
+			}
+		};
+		eprintln!(
+			"accepted {} authentication for {:?}",
+			req.method.name(),
+			req.user
+		)
+		.await;
 		Ok(User {
-			name: "TODO".into(),
+			name: req.user.into(),
+			profile,
 			shell: None,
 		})
 	}
@@ -87,60 +195,61 @@ impl ServerHandlers for Handlers {
 		let wait = |child: Rc<process::Child>| async move {
 			child.wait().await.unwrap().code().unwrap_or(0) as u32
 		};
-		match ty {
+		// FIXME nora_ssh doesn't report pty-req, so the session can't get a PTY with the size of
+		// the client's window. Use pipes until it does.
+		let mut cmd = match ty {
 			SpawnType::Shell => {
-				let shell = "drivers/minish";
-				let shell = process::Command::new(shell)
-					.await
-					.stdin(process::Stdio::piped())
-					.await
-					.stdout(process::Stdio::piped())
-					.await
-					.stderr(process::Stdio::piped())
-					.await
-					.spawn()
-					.await
-					.unwrap();
-				let mut shell = Rc::new(shell);
-				let sh_mut = Rc::get_mut(&mut shell).unwrap();
-				let io = IoSet {
-					stdin: sh_mut.stdin.take().map(AsyncWrapW::new),
-					stdout: sh_mut.stdout.take().map(AsyncWrapR::new),
-					stderr: sh_mut.stderr.take().map(AsyncWrapR::new),
-					wait: Box::pin(wait(shell.clone())),
-				};
-				user.shell = Some(shell);
-				Ok(io)
+				process::Command::new(program(user.profile.shell().as_bytes())).await
 			}
+			// FIXME nora_ssh doesn't report subsystem requests, so the sftp subsystem isn't
+			// available. Clients can run sftp_server as a command instead.
 			SpawnType::Exec { command } => {
 				let mut args = command
 					.split(|c| c.is_ascii_whitespace())
 					.filter(|s| !s.is_empty());
-				let bin = args.next().unwrap();
-				let shell = process::Command::new(bin)
-					.await
-					.stdin(process::Stdio::piped())
-					.await
-					.stdout(process::Stdio::piped())
-					.await
-					.stderr(process::Stdio::piped())
-					.await
-					.args(args)
-					.await
-					.spawn()
-					.await
-					.unwrap();
-				let mut shell = Rc::new(shell);
-				let sh_mut = Rc::get_mut(&mut shell).unwrap();
-				let io = IoSet {
-					stdin: sh_mut.stdin.take().map(AsyncWrapW::new),
-					stdout: sh_mut.stdout.take().map(AsyncWrapR::new),
-					stderr: sh_mut.stderr.take().map(AsyncWrapR::new),
-					wait: Box::pin(wait(shell.clone())),
-				};
-				user.shell = Some(shell);
-				Ok(io)
+				let bin: &[u8] = args.next().ok_or(())?.as_ref();
+				let mut cmd = process::Command::new(program(bin)).await;
+				cmd.args(args).await;
+				cmd
 			}
+		};
+		cmd.stdin(process::Stdio::piped())
+			.await
+			.stdout(process::Stdio::piped())
+			.await
+			.stderr(process::Stdio::piped())
+			.await;
+		if let Err(e) = user.profile.apply(&mut cmd).await {
+			eprintln!("failed to open roots for {:?}: {:?}", user.name, e).await;
+			return Err(());
 		}
+		let shell = match cmd.spawn().await {
+			Ok(shell) => shell,
+			Err(e) => {
+				eprintln!("failed to spawn process for {:?}: {:?}", user.name, e).await;
+				return Err(());
+			}
+		};
+		let mut shell = Rc::new(shell);
+		let sh_mut = Rc::get_mut(&mut shell).unwrap();
+		let io = IoSet {
+			stdin: sh_mut.stdin.take().map(AsyncWrapW::new),
+			stdout: sh_mut.stdout.take().map(AsyncWrapR::new),
+			stderr: sh_mut.stderr.take().map(AsyncWrapR::new),
+			wait: Box::pin(wait(shell.clone())),
+		};
+		user.shell = Some(shell);
+		Ok(io)
+	}
+}
+
+/// Resolve the path of a program.
+///
+/// Clients invoke helpers such as `scp` by name only, so bare names refer to `drivers/`.
+fn program(name: &[u8]) -> Vec<u8> {
+	if name.contains(&b'/') {
+		name.into()
+	} else {
+		[&b"drivers/"[..], name].concat()
 	}
 }
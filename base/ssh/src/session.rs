@@ -0,0 +1,210 @@
+//! Per-user session profiles.
+//!
+//! Profiles are read from `sessions.toml` in the configuration directory, e.g.:
+//!
+//! ```toml
+//! [user.alice]
+//! shell = "drivers/minish"
+//! file_root = { path = "file/home/alice", allow = ["open", "create", "read", "write", "seek"] }
+//! net_root = ""
+//! ```
+//!
+//! Each root path is opened relative to the corresponding root of the SSH server. An empty path
+//! passes the server's own root. If a root is not specified the session doesn't get one at all.
+//!
+//! If `allow` is given the root is wrapped so only the listed operations can be used on it and
+//! on any object opened through it. The operations are `open`, `create`, `destroy`, `read`,
+//! `write`, `seek`, `share`, `get_meta`, `set_meta` and `map`.
+//!
+//! Users without a profile are not allowed to log in.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use async_std::{
+	io::{self, Operations},
+	object::{file_root, net_root, process_root, AsyncObject, RefAsyncObject},
+	process::Command,
+};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use serde_derive::Deserialize;
+
+const DEFAULT_SHELL: &str = "drivers/minish";
+
+const OPERATIONS: &[(&str, Operations)] = &[
+	("open", Operations::OPEN),
+	("create", Operations::CREATE),
+	("destroy", Operations::DESTROY),
+	("read", Operations::READ),
+	("write", Operations::WRITE),
+	("seek", Operations::SEEK),
+	("share", Operations::SHARE),
+	("get_meta", Operations::GET_META),
+	("set_meta", Operations::SET_META),
+	("map", Operations::MAP),
+];
+
+#[derive(Deserialize)]
+struct Sessions {
+	#[serde(default)]
+	user: BTreeMap<String, Profile>,
+}
+
+#[derive(Deserialize)]
+pub struct Profile {
+	shell: Option<String>,
+	file_root: Option<RootConfig>,
+	net_root: Option<RootConfig>,
+	process_root: Option<RootConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RootConfig {
+	Path(String),
+	Restricted { path: String, allow: Allow },
+}
+
+/// The operations that may be used on a root.
+struct Allow(Operations);
+
+/// The root a session receives.
+enum Root {
+	/// Pass the server's own root.
+	Inherit,
+	/// Don't pass a root.
+	None,
+	Object(AsyncObject),
+}
+
+impl Profile {
+	/// Find the profile of the given user.
+	pub fn find(sessions: &str, user: &str) -> Result<Option<Self>, toml::de::Error> {
+		toml::from_str::<Sessions>(sessions).map(|mut s| s.user.remove(user))
+	}
+
+	pub fn shell(&self) -> &str {
+		self.shell.as_deref().unwrap_or(DEFAULT_SHELL)
+	}
+
+	/// Restrict the roots of a command to those in this profile.
+	pub async fn apply(&self, cmd: &mut Command) -> io::Result<()> {
+		match open(&self.file_root, file_root).await? {
+			Root::Inherit => {}
+			Root::None => {
+				cmd.file_root(None).await;
+			}
+			Root::Object(r) => {
+				cmd.file_root(Some(r)).await;
+			}
+		}
+		match open(&self.net_root, net_root).await? {
+			Root::Inherit => {}
+			Root::None => {
+				cmd.net_root(None).await;
+			}
+			Root::Object(r) => {
+				cmd.net_root(Some(r)).await;
+			}
+		}
+		match open(&self.process_root, process_root).await? {
+			Root::Inherit => {}
+			Root::None => {
+				cmd.process_root(None).await;
+			}
+			Root::Object(r) => {
+				cmd.process_root(Some(r)).await;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl RootConfig {
+	fn path(&self) -> &str {
+		match self {
+			Self::Path(path) | Self::Restricted { path, .. } => path,
+		}
+	}
+
+	fn allow(&self) -> Option<Operations> {
+		match self {
+			Self::Path(_) => None,
+			Self::Restricted { allow, .. } => Some(allow.0),
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Allow {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Vec::<String>::deserialize(deserializer)?
+			.iter()
+			.try_fold(Operations::NONE, |allow, op| {
+				OPERATIONS
+					.iter()
+					.find(|(name, _)| name == op)
+					.map(|(_, o)| allow | *o)
+					.ok_or_else(|| D::Error::custom(format_args!("unknown operation {:?}", op)))
+			})
+			.map(Self)
+	}
+}
+
+/// Open the root to pass to a session.
+async fn open(
+	config: &Option<RootConfig>,
+	root: fn() -> RefAsyncObject<'static>,
+) -> io::Result<Root> {
+	let Some(config) = config else {
+		return Ok(Root::None);
+	};
+	let obj = match config.path() {
+		"" => None,
+		path => Some(root().open(path.as_bytes().to_vec()).await.0?),
+	};
+	match (obj, config.allow()) {
+		(None, None) => Ok(Root::Inherit),
+		(Some(obj), None) => Ok(Root::Object(obj)),
+		(obj, Some(allow)) => {
+			// The wrapper holds its own reference to the object.
+			let handle = obj
+				.as_ref()
+				.map_or_else(|| root().as_raw(), AsyncObject::as_raw);
+			let (wrapper, _) = io::new_object(io::NewObject::Attenuate {
+				handle,
+				allow,
+				prefix: &[],
+			})?;
+			Ok(Root::Object(AsyncObject::from_raw(wrapper)))
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn find() {
+		let s = r#"
+			[user.alice]
+			file_root = { path = "file/home/alice", allow = ["open", "read"] }
+			net_root = ""
+
+			[user.bob]
+			shell = "drivers/sh"
+		"#;
+		let p = Profile::find(s, "alice").unwrap().unwrap();
+		assert_eq!(p.shell(), DEFAULT_SHELL);
+		let f = p.file_root.unwrap();
+		assert_eq!(f.path(), "file/home/alice");
+		assert_eq!(f.allow(), Some(Operations::OPEN | Operations::READ));
+		let n = p.net_root.unwrap();
+		assert_eq!((n.path(), n.allow()), ("", None));
+		assert!(p.process_root.is_none());
+		let p = Profile::find(s, "bob").unwrap().unwrap();
+		assert_eq!(p.shell(), "drivers/sh");
+		assert!(Profile::find(s, "carol").unwrap().is_none());
+		assert!(Profile::find("[user", "alice").is_err());
+		let s = r#"user.alice.file_root = { path = "", allow = ["fly"] }"#;
+		assert!(Profile::find(s, "alice").is_err());
+	}
+}
@@ -1,31 +1,69 @@
 #![no_std]
+#![feature(let_else)]
 #![feature(start)]
 
 extern crate alloc;
 
-use alloc::{boxed::Box, vec::Vec};
+use alloc::{boxed::Box, format, string::String, vec::Vec};
 use async_std::{
 	eprintln,
-	io::{Buf, Error, Read, Write},
+	io::{Buf, Error, Read, Seek, SeekFrom, Write},
 	net::{Ipv4Addr, TcpListener, TcpStream},
+	object::{file_root, AsyncObject},
 	println,
 };
-use core::{
-	future::Future,
-	pin::Pin,
-	task::{Context, Poll},
-	time::Duration,
-};
+use core::fmt::Write as _;
 use futures::{
 	future::{self, Either},
 	stream::StreamExt,
 };
 use rt_default as _;
 
+/// The maximum size of the header of a request.
+const MAX_HEADER_SIZE: usize = 1 << 13;
+/// The size of the chunks files are sent in.
+const CHUNK_SIZE: usize = 1 << 15;
+
+const MOVED_PERMANENTLY: &[u8] = b"<!DOCTYPE html><h1>301 Moved Permanently</h1>";
 const BAD_REQUEST: &[u8] = b"<!DOCTYPE html><h1>400 Bad Request</h1>";
 const NOT_FOUND: &[u8] = b"<!DOCTYPE html><h1>404 Not Found</h1>";
+const METHOD_NOT_ALLOWED: &[u8] = b"<!DOCTYPE html><h1>405 Method Not Allowed</h1>";
+const RANGE_NOT_SATISFIABLE: &[u8] = b"<!DOCTYPE html><h1>416 Range Not Satisfiable</h1>";
 const INTERNAL_SERVER_ERROR: &[u8] = b"<!DOCTYPE html><h1>500 Internal Server Error</h1>";
 
+const MIME_TYPES: &[(&str, &str)] = &[
+	("html", "text/html; charset=utf-8"),
+	("htm", "text/html; charset=utf-8"),
+	("css", "text/css"),
+	("js", "text/javascript"),
+	("json", "application/json"),
+	("txt", "text/plain; charset=utf-8"),
+	("md", "text/markdown; charset=utf-8"),
+	("xml", "application/xml"),
+	("svg", "image/svg+xml"),
+	("png", "image/png"),
+	("jpg", "image/jpeg"),
+	("jpeg", "image/jpeg"),
+	("gif", "image/gif"),
+	("bmp", "image/bmp"),
+	("webp", "image/webp"),
+	("ico", "image/x-icon"),
+	("mp3", "audio/mpeg"),
+	("ogg", "audio/ogg"),
+	("wav", "audio/wav"),
+	("mp4", "video/mp4"),
+	("webm", "video/webm"),
+	("woff", "font/woff"),
+	("woff2", "font/woff2"),
+	("ttf", "font/ttf"),
+	("otf", "font/otf"),
+	("wasm", "application/wasm"),
+	("pdf", "application/pdf"),
+	("zip", "application/zip"),
+	("gz", "application/gzip"),
+	("tar", "application/x-tar"),
+];
+
 #[start]
 fn start(_: isize, _: *const *const u8) -> isize {
 	async_std::task::block_on(main())
@@ -41,32 +79,35 @@ async fn main() -> ! {
 	let mut accept = Box::pin(listener.accept());
 
 	let do_client = |c: TcpStream| async move {
+		let mut buf = Vec::with_capacity(MAX_HEADER_SIZE);
 		loop {
-			let (res, buf) = c.read(Vec::with_capacity(2048)).await;
-			let mut headers = [""; 128];
-			let (mut resp, keep_alive) = match res {
-				Ok(l) if l == 0 => return, // Client closed the connection prematurely
-				Ok(l) => match mhttp::RequestParser::parse(&buf, &mut headers) {
-					Ok((req, _)) => handle_client(Vec::new(), req).await,
-					Err(_) => bad_request(buf, false),
-				},
-				Err(_) => bad_request(buf, false),
-			};
-			let mut total = 0;
-			while let (Ok(n), r) = c.write(resp.slice(total..)).await {
-				resp = r.into_inner();
-				if n == 0 {
-					return; // Client closed the connection prematurely
+			// Read until we have the entire header. There may be more data after the header if
+			// the client pipelines requests.
+			let end = loop {
+				if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+					break i + 4;
 				}
-				total += n;
-				if total == resp.len() {
-					break; // We wrote all data
+				if buf.len() == buf.capacity() {
+					send_page(&c, Status::BadRequest, &[], BAD_REQUEST, false, false).await;
+					return;
 				}
-			}
+				let l = buf.len();
+				let (res, b) = c.read(buf.slice(l..)).await;
+				buf = b.into_inner();
+				match res {
+					Ok(0) | Err(_) => return, // Client closed the connection
+					Ok(_) => {}
+				}
+			};
+			let mut headers = [""; 128];
+			let keep_alive = match mhttp::RequestParser::parse(&buf[..end], &mut headers) {
+				Ok((req, _)) => handle_client(&c, &buf[..end], req).await,
+				Err(_) => send_page(&c, Status::BadRequest, &[], BAD_REQUEST, false, false).await,
+			};
 			if !keep_alive {
 				break;
 			}
-			break; // TODO
+			buf.drain(..end);
 		}
 	};
 	let mut clients = futures::stream::FuturesUnordered::new();
@@ -95,90 +136,394 @@ async fn main() -> ! {
 	}
 }
 
-async fn handle_client<'a>(buf: Vec<u8>, request: mhttp::RequestParser<'_, '_>) -> (Vec<u8>, bool) {
+/// Handle a single request. Returns `true` if the connection should be kept alive.
+async fn handle_client(
+	c: &TcpStream,
+	header: &[u8],
+	request: mhttp::RequestParser<'_, '_>,
+) -> bool {
 	println!("{:?}", request.path).await;
-	let keep_alive = request.header("connection") == Some("keep-alive");
-	match request.header("host") {
-		None => bad_request(buf, keep_alive),
-		Some(_) if !request.path.starts_with("/") => bad_request(buf, keep_alive),
-		Some(_) => {
-			let path = match &request.path[1..] {
-				"" => "index",
-				p => p,
-			};
-			match async_std::fs::read(Vec::from(path)).await.0 {
-				Ok(v) => create_response(
-					buf,
-					mhttp::Status::Ok,
-					v.len(),
-					|w| w.extend(&v),
-					keep_alive,
-				),
-				Err(e) => {
-					use mhttp::Status::*;
-					match e {
-						Error::DoesNotExist => create_response(
-							buf,
-							NotFound,
-							NOT_FOUND.len(),
-							|v| v.extend(NOT_FOUND),
-							keep_alive,
-						),
-						_ => create_response(
-							buf,
-							InternalServerError,
-							INTERNAL_SERVER_ERROR.len(),
-							|v| v.extend(INTERNAL_SERVER_ERROR),
-							keep_alive,
-						),
-					}
+
+	// The parser doesn't expose the method and version, so get them from the request line.
+	let line = header.split(|&c| c == b'\r').next().unwrap_or(&[]);
+	let mut line = line.split(|&c| c == b' ');
+	let (method, version) = (line.next(), line.nth(1));
+
+	let keep_alive = match request.header("connection") {
+		Some(v) if v.eq_ignore_ascii_case("close") => false,
+		Some(v) if v.eq_ignore_ascii_case("keep-alive") => true,
+		// Persistent connections are the default since HTTP/1.1
+		_ => version == Some(&b"HTTP/1.1"[..]),
+	};
+	let head = match method {
+		Some(b"GET") => false,
+		Some(b"HEAD") => true,
+		// Other methods may have a body we don't know how to skip, so close the connection.
+		_ => {
+			let allow = [("allow", "GET, HEAD")];
+			return send_page(
+				c,
+				Status::MethodNotAllowed,
+				&allow,
+				METHOD_NOT_ALLOWED,
+				false,
+				false,
+			)
+			.await;
+		}
+	};
+
+	let path = match (request.header("host"), request.path.strip_prefix('/')) {
+		(Some(_), Some(p)) => p,
+		_ => return send_page(c, Status::BadRequest, &[], BAD_REQUEST, head, keep_alive).await,
+	};
+	// Ignore the query string, if any.
+	let path = path.split_once('?').map_or(path, |(p, _)| p);
+	let path = match percent_decode(path) {
+		Some(p) if !p.split('/').any(|s| s == "..") => p,
+		_ => return send_page(c, Status::BadRequest, &[], BAD_REQUEST, head, keep_alive).await,
+	};
+
+	let range = request.header("range");
+	if path.is_empty() || path.ends_with('/') {
+		// Serve the index file if there is one, otherwise generate a listing.
+		let index = format!("{}index", path);
+		match file_root().open(Vec::from(index.as_bytes())).await.0 {
+			Ok(f) => send_file(c, &index, f, range, head, keep_alive).await,
+			Err(Error::DoesNotExist) => {
+				match file_root().open(Vec::from(path.as_bytes())).await.0 {
+					Ok(d) => send_index(c, &path, d, head, keep_alive).await,
+					Err(e) => send_error(c, e, head, keep_alive).await,
 				}
 			}
+			Err(e) => send_error(c, e, head, keep_alive).await,
 		}
+	} else {
+		let f = match file_root().open(Vec::from(path.as_bytes())).await.0 {
+			Ok(f) => f,
+			Err(e) => return send_error(c, e, head, keep_alive).await,
+		};
+		// Directories can't be seeked in. Redirect to the path with a trailing slash so the
+		// index is served and relative links in it resolve correctly.
+		if f.seek(SeekFrom::End(0)).await.is_err() {
+			let location = match request.path.split_once('?') {
+				Some((p, q)) => format!("{}/?{}", p, q),
+				None => format!("{}/", request.path),
+			};
+			let headers = [("location", &*location)];
+			return send_page(
+				c,
+				Status::MovedPermanently,
+				&headers,
+				MOVED_PERMANENTLY,
+				head,
+				keep_alive,
+			)
+			.await;
+		}
+		send_file(c, &path, f, range, head, keep_alive).await
 	}
 }
 
-fn bad_request(buf: Vec<u8>, keep_alive: bool) -> (Vec<u8>, bool) {
-	create_response(
-		buf,
-		mhttp::Status::BadRequest,
-		BAD_REQUEST.len(),
-		|v| v.extend(BAD_REQUEST),
-		keep_alive,
-	)
+/// Send (part of) a file in chunks, so large files don't need to be kept in memory entirely.
+async fn send_file(
+	c: &TcpStream,
+	path: &str,
+	file: AsyncObject,
+	range: Option<&str>,
+	head: bool,
+	keep_alive: bool,
+) -> bool {
+	let size = match file.seek(SeekFrom::End(0)).await {
+		Ok(s) => s,
+		Err(e) => return send_error(c, e, head, keep_alive).await,
+	};
+	let (status, start, end) = match parse_range(range, size) {
+		Range::Full => (Status::Ok, 0, size),
+		Range::Partial(start, end) => (Status::PartialContent, start, end),
+		Range::Unsatisfiable => {
+			let content_range = format!("bytes */{}", size);
+			let headers = [("content-range", &*content_range)];
+			return send_page(
+				c,
+				Status::RangeNotSatisfiable,
+				&headers,
+				RANGE_NOT_SATISFIABLE,
+				head,
+				keep_alive,
+			)
+			.await;
+		}
+	};
+
+	let length = format!("{}", end - start);
+	let content_range = format!("bytes {}-{}/{}", start, end.saturating_sub(1), size);
+	let mut headers = Vec::from([("content-length", &*length), ("accept-ranges", "bytes")]);
+	if let Some(ty) = content_type(path) {
+		headers.push(("content-type", ty));
+	}
+	if status == Status::PartialContent {
+		headers.push(("content-range", &content_range));
+	}
+	let buf = response_header(status, &headers, keep_alive);
+	let mut buf = match write_all(c, buf).await {
+		Some(b) if !head => b,
+		Some(_) => return keep_alive,
+		None => return false,
+	};
+
+	if file.seek(SeekFrom::Start(start)).await.is_err() {
+		return false;
+	}
+	let mut remaining = end - start;
+	buf.clear();
+	buf.reserve(CHUNK_SIZE);
+	while remaining > 0 {
+		let n = usize::try_from(remaining)
+			.unwrap_or(usize::MAX)
+			.min(CHUNK_SIZE);
+		buf.clear();
+		let (res, b) = file.read(buf.slice(..n)).await;
+		buf = b.into_inner();
+		match res {
+			// The file is shorter than advertised, which can't be reported to the client anymore.
+			Ok(0) | Err(_) => return false,
+			Ok(l) => remaining -= l as u64,
+		}
+		buf = match write_all(c, buf).await {
+			Some(b) => b,
+			None => return false,
+		};
+	}
+	keep_alive
 }
 
-fn num_to_str(mut n: usize, buf: &mut [u8]) -> &str {
-	let mut l = 0;
-	for w in buf.iter_mut().rev() {
-		*w = (n % 10) as u8 + b'0';
-		n /= 10;
-		l += 1;
-		if n == 0 {
-			break;
+/// Generate a page listing all entries in a directory.
+async fn send_index(
+	c: &TcpStream,
+	path: &str,
+	dir: AsyncObject,
+	head: bool,
+	keep_alive: bool,
+) -> bool {
+	let mut body = String::new();
+	body += "<!DOCTYPE html><title>Index of /";
+	escape_html(path, &mut body);
+	body += "</title><h1>Index of /";
+	escape_html(path, &mut body);
+	body += "</h1><ul>";
+	if !path.is_empty() {
+		body += "<li><a href=\"../\">../</a></li>";
+	}
+	let mut name = Vec::with_capacity(256);
+	loop {
+		name.clear();
+		let (res, n) = dir.read(name).await;
+		name = n;
+		match res {
+			Ok(0) => break,
+			Ok(_) => {
+				let name = String::from_utf8_lossy(&name);
+				body += "<li><a href=\"";
+				percent_encode(&name, &mut body);
+				body += "\">";
+				escape_html(&name, &mut body);
+				body += "</a></li>";
+			}
+			Err(e) => return send_error(c, e, head, keep_alive).await,
 		}
 	}
-	core::str::from_utf8(&buf[buf.len() - l..]).unwrap()
+	body += "</ul>";
+	send_page(c, Status::Ok, &[], body.as_bytes(), head, keep_alive).await
+}
+
+/// Send an error page appropriate for the given error.
+async fn send_error(c: &TcpStream, error: Error, head: bool, keep_alive: bool) -> bool {
+	match error {
+		Error::DoesNotExist => send_page(c, Status::NotFound, &[], NOT_FOUND, head, keep_alive),
+		_ => send_page(
+			c,
+			Status::InternalServerError,
+			&[],
+			INTERNAL_SERVER_ERROR,
+			head,
+			keep_alive,
+		),
+	}
+	.await
 }
 
-fn create_response(
-	mut buf: Vec<u8>,
-	status: mhttp::Status,
-	body_len: usize,
-	body: impl FnOnce(&mut Vec<u8>),
+/// Send a HTML page. Returns `true` if the connection should be kept alive.
+async fn send_page(
+	c: &TcpStream,
+	status: Status,
+	headers: &[(&str, &str)],
+	body: &[u8],
+	head: bool,
 	keep_alive: bool,
-) -> (Vec<u8>, bool) {
-	let mut l = [0; 20];
-	let mut h = [0; 256];
-	let h = mhttp::ResponseBuilder::new(&mut h, status)
-		.unwrap()
-		.add_header("content-length", num_to_str(body_len, &mut l))
-		.unwrap()
-		.add_header("connection", "close") // TODO
-		.unwrap()
-		.finish()
-		.0;
-	buf.extend(h);
-	body(&mut buf);
-	(buf, keep_alive)
+) -> bool {
+	let length = format!("{}", body.len());
+	let mut h = Vec::from([
+		("content-type", "text/html; charset=utf-8"),
+		("content-length", &*length),
+	]);
+	h.extend(headers);
+	let mut buf = response_header(status, &h, keep_alive);
+	if !head {
+		buf.extend(body);
+	}
+	write_all(c, buf).await.is_some() && keep_alive
+}
+
+/// Write all data, returning the buffer so it can be reused.
+///
+/// Returns `None` if the client closed the connection prematurely.
+async fn write_all(c: &TcpStream, mut buf: Vec<u8>) -> Option<Vec<u8>> {
+	let mut total = 0;
+	while total < buf.len() {
+		match c.write(buf.slice(total..)).await {
+			(Ok(0), _) | (Err(_), _) => return None,
+			(Ok(n), b) => {
+				buf = b.into_inner();
+				total += n;
+			}
+		}
+	}
+	Some(buf)
+}
+
+fn response_header(status: Status, headers: &[(&str, &str)], keep_alive: bool) -> Vec<u8> {
+	let mut h = String::new();
+	let _ = write!(h, "HTTP/1.1 {} {}\r\n", status as u16, status.reason());
+	for (k, v) in headers {
+		let _ = write!(h, "{}: {}\r\n", k, v);
+	}
+	let connection = if keep_alive { "keep-alive" } else { "close" };
+	let _ = write!(h, "connection: {}\r\n\r\n", connection);
+	h.into()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+	Ok = 200,
+	PartialContent = 206,
+	MovedPermanently = 301,
+	BadRequest = 400,
+	NotFound = 404,
+	MethodNotAllowed = 405,
+	RangeNotSatisfiable = 416,
+	InternalServerError = 500,
+}
+
+impl Status {
+	fn reason(self) -> &'static str {
+		match self {
+			Self::Ok => "OK",
+			Self::PartialContent => "Partial Content",
+			Self::MovedPermanently => "Moved Permanently",
+			Self::BadRequest => "Bad Request",
+			Self::NotFound => "Not Found",
+			Self::MethodNotAllowed => "Method Not Allowed",
+			Self::RangeNotSatisfiable => "Range Not Satisfiable",
+			Self::InternalServerError => "Internal Server Error",
+		}
+	}
+}
+
+enum Range {
+	Full,
+	/// The start and end (exclusive) of the range.
+	Partial(u64, u64),
+	Unsatisfiable,
+}
+
+/// Parse a `Range` header. Only single byte ranges are supported, any other kind of range is
+/// ignored.
+fn parse_range(header: Option<&str>, size: u64) -> Range {
+	let Some(range) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+		return Range::Full;
+	};
+	// Multiple ranges require a multipart response, so send the entire file instead.
+	if range.contains(',') {
+		return Range::Full;
+	}
+	let Some((start, end)) = range.trim().split_once('-') else {
+		return Range::Full;
+	};
+	let (start, end) = match (start, end) {
+		// The last N bytes
+		("", n) => match n.parse::<u64>() {
+			Ok(0) => return Range::Unsatisfiable,
+			Ok(n) => (size.saturating_sub(n), size),
+			Err(_) => return Range::Full,
+		},
+		(s, "") => match s.parse() {
+			Ok(s) => (s, size),
+			Err(_) => return Range::Full,
+		},
+		(s, e) => match (s.parse::<u64>(), e.parse::<u64>()) {
+			(Ok(s), Ok(e)) if s <= e => (s, e.saturating_add(1).min(size)),
+			_ => return Range::Full,
+		},
+	};
+	if start < size {
+		Range::Partial(start, end)
+	} else {
+		Range::Unsatisfiable
+	}
+}
+
+/// Determine the MIME type of a file from its extension.
+///
+/// Files without an extension have no type, which lets the client guess it.
+fn content_type(path: &str) -> Option<&'static str> {
+	let name = path.rsplit('/').next().unwrap_or(path);
+	let (_, ext) = name.rsplit_once('.')?;
+	Some(
+		MIME_TYPES
+			.iter()
+			.find(|(e, _)| e.eq_ignore_ascii_case(ext))
+			.map_or("application/octet-stream", |(_, t)| t),
+	)
+}
+
+fn escape_html(s: &str, out: &mut String) {
+	for c in s.chars() {
+		match c {
+			'&' => *out += "&amp;",
+			'<' => *out += "&lt;",
+			'>' => *out += "&gt;",
+			'"' => *out += "&quot;",
+			'\'' => *out += "&#39;",
+			c => out.push(c),
+		}
+	}
+}
+
+fn percent_encode(s: &str, out: &mut String) {
+	for b in s.bytes() {
+		match b {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+				out.push(b.into())
+			}
+			b => {
+				let _ = write!(out, "%{:02X}", b);
+			}
+		}
+	}
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+	let mut out = Vec::with_capacity(s.len());
+	let mut it = s.bytes();
+	while let Some(b) = it.next() {
+		if b == b'%' {
+			let h = [it.next()?, it.next()?];
+			let h = core::str::from_utf8(&h).ok()?;
+			out.push(u8::from_str_radix(h, 16).ok()?);
+		} else {
+			out.push(b);
+		}
+	}
+	String::from_utf8(out).ok()
 }
@@ -305,6 +305,19 @@ fn main(_: isize, _: *const *const u8) -> isize {
 					};
 					(job_id, r)
 				}
+				// Only reads can be pending.
+				Request::Cancel { job_id } => match manager.window_mut(handle) {
+					Some(w) => {
+						let l = w.user_data.event_listeners.get_mut();
+						let len = l.len();
+						l.retain(|id| id.get() != job_id.get());
+						if l.len() == len {
+							continue;
+						}
+						(job_id, Response::Error(Error::Cancelled))
+					}
+					None => continue,
+				},
 				_ => todo!(),
 			};
 			table.enqueue(job_id, response);
@@ -29,6 +29,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 		let (buf, _) = rt::Object::new(rt::NewObject::SharedMemory { size: 1 << 16 }).unwrap();
 		StreamTable::new(&buf, Pow2Size(9), (1 << 12) - 1)
 	};
+	tbl.set_vectored_writes(true);
 	rt::io::file_root()
 		.unwrap()
 		.create(table_name.as_bytes())
@@ -149,6 +150,69 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 						Object::Query(_, _) => Response::Error(rt::Error::InvalidOperation),
 					},
 				),
+				Request::WriteVectored { job_id, data } => (
+					job_id,
+					match &mut objects[handle] {
+						Object::File(path, offset) => {
+							let mut l = 0;
+							for d in data {
+								d.copy_to(0, &mut buf[l..l + d.len()]);
+								l += d.len();
+								d.manual_drop();
+							}
+							let mut file = fs.root_dir().open_file(path).unwrap();
+							file.seek(std::io::SeekFrom::Start(*offset)).unwrap();
+							let l = file.write(&buf[..l]).unwrap();
+							*offset += u64::try_from(l).unwrap();
+							Response::Amount(l.try_into().unwrap())
+						}
+						Object::Query(_, _) => {
+							data.into_iter().for_each(|d| d.manual_drop());
+							Response::Error(rt::Error::InvalidOperation)
+						}
+					},
+				),
+				Request::ReadAt {
+					job_id,
+					offset,
+					amount,
+				} => (
+					job_id,
+					match &objects[handle] {
+						Object::File(path, _) => {
+							let mut file = fs.root_dir().open_file(path).unwrap();
+							file.seek(std::io::SeekFrom::Start(offset)).unwrap();
+							let len = amount.min(4096) as usize;
+							let len = file.read(&mut buf[..len]).unwrap();
+							let data = tbl.alloc(len).expect("out of buffers");
+							data.copy_from(0, &buf[..len]);
+							Response::Data(data)
+						}
+						Object::Query(_, _) => Response::Error(rt::Error::InvalidOperation),
+					},
+				),
+				Request::WriteAt {
+					job_id,
+					offset,
+					data,
+				} => (
+					job_id,
+					match &objects[handle] {
+						Object::File(path, _) => {
+							let l = data.len();
+							data.copy_to(0, &mut buf[..l]);
+							data.manual_drop();
+							let mut file = fs.root_dir().open_file(path).unwrap();
+							file.seek(std::io::SeekFrom::Start(offset)).unwrap();
+							let l = file.write(&buf[..l]).unwrap();
+							Response::Amount(l.try_into().unwrap())
+						}
+						Object::Query(_, _) => {
+							data.manual_drop();
+							Response::Error(rt::Error::InvalidOperation)
+						}
+					},
+				),
 				Request::Seek { job_id, from } => (job_id, {
 					use rt::io::SeekFrom;
 					match &mut objects[handle] {
@@ -178,7 +242,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 					objects.remove(handle);
 					continue;
 				}
-				Request::Share { .. } => todo!(),
+				Request::Cancel { .. } => continue,
+				Request::Share { job_id, .. } => {
+					(job_id, Response::Error(rt::Error::InvalidOperation))
+				}
 				Request::Destroy { .. } => todo!(),
 				Request::GetMeta { .. } => todo!(),
 				Request::SetMeta { .. } => todo!(),
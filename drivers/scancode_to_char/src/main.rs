@@ -12,10 +12,10 @@ use async_std::{
 use core::{
 	cell::{Cell, RefCell},
 	future::Future,
-	task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+	task::{Context, RawWaker, RawWakerVTable, Waker},
 	time::Duration,
 };
-use driver_utils::os::stream_table::{Request, Response, StreamTable};
+use driver_utils::os::stream_table::{JobId, Request, Response, StreamTable};
 use norostb_kernel::{error::Error, object::Pow2Size, RWX};
 use rt_default as _;
 
@@ -43,7 +43,7 @@ fn main(_: isize, _: *const *const u8) -> isize {
 
 	let char_buf = RefCell::new(VecDeque::new());
 	let readers = RefCell::new(driver_utils::Arena::new());
-	let pending_read = Cell::new(None);
+	let pending_reads = RefCell::new(VecDeque::<JobId>::new());
 	let shifts = Cell::new(0);
 
 	let do_read = || async {
@@ -91,18 +91,16 @@ fn main(_: isize, _: *const *const u8) -> isize {
 			Event::Release(_) => None,
 		};
 		if let Some(chr) = chr {
-			if let Some(job_id) = pending_read.take() {
+			if let Some(job_id) = pending_reads.borrow_mut().pop_front() {
 				buf.clear();
 				let data = table.alloc(1).expect("out of buffers");
 				data.copy_from(0, &[chr]);
 				table.enqueue(job_id, Response::Data(data));
 				table.flush();
-				return true;
 			} else {
 				char_buf.borrow_mut().push_back(chr);
 			}
 		}
-		false
 	};
 
 	let do_job = || async {
@@ -129,7 +127,16 @@ fn main(_: isize, _: *const *const u8) -> isize {
 					readers.borrow_mut().remove(handle).unwrap();
 				}
 				// The kernel does not expect a response
-				return true;
+				return;
+			}
+			Request::Cancel { job_id } => {
+				let mut pending_reads = pending_reads.borrow_mut();
+				let id = job_id.get();
+				match pending_reads.iter().position(|j| j.get() == id) {
+					Some(i) => pending_reads.remove(i),
+					None => return,
+				};
+				(job_id, Response::Error(Error::Cancelled))
 			}
 			Request::Read {
 				job_id,
@@ -142,10 +149,9 @@ fn main(_: isize, _: *const *const u8) -> isize {
 					let mut char_buf = char_buf.borrow_mut();
 					if char_buf.is_empty() {
 						// There is currently no data, so delay a response until there is
-						// data. Don't enqueue a new TAKE_JOB either so we have a slot
-						// free for when we can send a reply
-						pending_read.set(Some(job_id));
-						return false;
+						// data.
+						pending_reads.borrow_mut().push_back(job_id);
+						return;
 					} else {
 						let l = tiny_buf.len();
 						let mut b = &mut tiny_buf[..l.min(char_buf.len())];
@@ -162,7 +168,6 @@ fn main(_: isize, _: *const *const u8) -> isize {
 		};
 		table.enqueue(job_id, resp);
 		table.flush();
-		true
 	};
 
 	unsafe fn clone(p: *const ()) -> RawWaker {
@@ -188,23 +193,13 @@ fn main(_: isize, _: *const *const u8) -> isize {
 
 	loop {
 		while read_notif.get() || job_notif.get() {
-			if read_notif.take() {
-				if let Poll::Ready(new_job) = read_task.as_mut().poll(&mut read_cx) {
-					read_task = Box::pin(do_read());
-					read_notif.set(true);
-					if new_job {
-						job_notif.set(true);
-						job_task = Box::pin(do_job());
-					}
-				}
+			if read_notif.take() && read_task.as_mut().poll(&mut read_cx).is_ready() {
+				read_task = Box::pin(do_read());
+				read_notif.set(true);
 			}
-			if job_notif.take() {
-				if let Poll::Ready(new_job) = job_task.as_mut().poll(&mut job_cx) {
-					if new_job {
-						job_notif.set(true);
-						job_task = Box::pin(do_job());
-					}
-				}
+			if job_notif.take() && job_task.as_mut().poll(&mut job_cx).is_ready() {
+				job_task = Box::pin(do_job());
+				job_notif.set(true);
 			}
 		}
 		async_std::queue::wait(Duration::MAX);
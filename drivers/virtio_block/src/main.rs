@@ -174,6 +174,70 @@ fn main() -> ! {
 
 					(job_id, Response::Amount(len.try_into().unwrap()))
 				}
+				Request::ReadAt {
+					job_id,
+					offset,
+					amount,
+				} => {
+					(
+						job_id,
+						if handle == Handle::MAX {
+							Response::Error(rt::Error::InvalidOperation)
+						} else {
+							// TODO ditto
+							assert!(amount % SECTOR_SIZE == 0);
+							assert!(offset % u64::from(SECTOR_SIZE) == 0);
+							let amount = amount.min(1 << 13);
+
+							let data = tbl
+								.alloc(amount.try_into().unwrap())
+								.expect("out of buffers");
+							let sectors = data.blocks().map(|b| virtio::PhysRegion {
+								base: virtio::PhysAddr::new(dma_phys + u64::from(b.0) * 512),
+								size: 512,
+							});
+
+							let offset = offset / u64::from(SECTOR_SIZE);
+							let tk = unsafe { dev.read(sectors, offset).unwrap() };
+							// TODO proper async
+							while dev.poll_finished(|t| assert_eq!(t, tk)) != 1 {
+								wait();
+							}
+
+							Response::Data(data)
+						},
+					)
+				}
+				Request::WriteAt {
+					job_id,
+					offset,
+					data,
+				} => {
+					// TODO ditto
+					assert!(data.len() % Sector::SIZE == 0);
+					assert!(offset % u64::from(SECTOR_SIZE) == 0);
+
+					// Skip the blocks with the offset.
+					let sectors =
+						data.blocks()
+							.take(data.len() / Sector::SIZE)
+							.map(|b| virtio::PhysRegion {
+								base: virtio::PhysAddr::new(dma_phys + u64::from(b.0) * 512),
+								size: 512,
+							});
+
+					let offset = offset / u64::from(SECTOR_SIZE);
+					let tk = unsafe { dev.write(sectors, offset).unwrap() };
+					// TODO proper async
+					while dev.poll_finished(|t| assert_eq!(t, tk)) != 1 {
+						wait();
+					}
+					let len = data.len();
+
+					data.manual_drop();
+
+					(job_id, Response::Amount(len.try_into().unwrap()))
+				}
 				Request::Seek { job_id, from } => {
 					let offset = match from {
 						rt::io::SeekFrom::Start(n) => n,
@@ -189,14 +253,21 @@ fn main() -> ! {
 					// The kernel does not expect a response.
 					continue;
 				}
+				Request::Cancel { .. } => continue,
 				Request::Create { job_id, path } => {
 					path.manual_drop();
 					(job_id, Response::Error(rt::Error::InvalidOperation))
 				}
-				Request::Share { .. } => todo!(),
+				Request::Share { job_id, .. } => {
+					(job_id, Response::Error(rt::Error::InvalidOperation))
+				}
 				Request::GetMeta { .. } => todo!(),
 				Request::SetMeta { .. } => todo!(),
 				Request::Destroy { .. } => todo!(),
+				Request::WriteVectored { job_id, data } => {
+					data.into_iter().for_each(|d| d.manual_drop());
+					(job_id, Response::Error(rt::Error::InvalidOperation))
+				}
 			};
 			tbl.enqueue(job_id, resp);
 			flush = true;
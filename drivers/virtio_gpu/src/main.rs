@@ -328,11 +328,21 @@ fn main(_: isize, _: *const *const u8) -> isize {
 					Handle::MAX | SYNC_HANDLE => continue,
 					_ => unreachable!(),
 				},
+				Request::Cancel { .. } => continue,
 				Request::Create { job_id, path } => {
 					path.manual_drop();
 					(job_id, Response::Error(Error::InvalidOperation as _))
 				}
+				Request::WriteAt { job_id, data, .. } => {
+					data.manual_drop();
+					(job_id, Response::Error(Error::InvalidOperation as _))
+				}
+				Request::WriteVectored { job_id, data } => {
+					data.into_iter().for_each(|d| d.manual_drop());
+					(job_id, Response::Error(Error::InvalidOperation as _))
+				}
 				Request::Read { job_id, .. }
+				| Request::ReadAt { job_id, .. }
 				| Request::Destroy { job_id, .. }
 				| Request::Seek { job_id, .. } => (job_id, Response::Error(Error::InvalidOperation as _)),
 			};
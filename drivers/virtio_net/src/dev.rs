@@ -9,6 +9,8 @@ use smoltcp::time::Instant;
 use virtio::{PhysAddr, PhysRegion};
 use virtio_net::Packet;
 
+use crate::loopback::{Loopback, LoopbackRxToken, LoopbackTxToken};
+
 const MAX_RX_PKT: usize = 8;
 const MAX_TX_PKT: usize = 8;
 
@@ -223,3 +225,85 @@ impl Drop for DevTxToken<'_, '_> {
 		self.dev.borrow_mut().tx_avail_map |= 1 << self.index;
 	}
 }
+
+/// Any device an interface can be bound to.
+pub enum AnyDev<'d> {
+	Virtio(Dev<'d>),
+	Loopback(Loopback),
+}
+
+impl<'d> AnyDev<'d> {
+	/// Collect received packets & finished transactions.
+	///
+	/// Returns `true` if any RX packets are available.
+	pub fn process(&mut self) -> bool {
+		match self {
+			Self::Virtio(d) => d.process(),
+			Self::Loopback(_) => false,
+		}
+	}
+}
+
+impl<'a, 'd: 'a> Device<'a> for AnyDev<'d> {
+	type RxToken = AnyRxToken<'a, 'd>;
+	type TxToken = AnyTxToken<'a, 'd>;
+
+	fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+		match self {
+			Self::Virtio(d) => d
+				.receive()
+				.map(|(rx, tx)| (AnyRxToken::Virtio(rx), AnyTxToken::Virtio(tx))),
+			Self::Loopback(d) => d
+				.receive()
+				.map(|(rx, tx)| (AnyRxToken::Loopback(rx), AnyTxToken::Loopback(tx))),
+		}
+	}
+
+	fn transmit(&'a mut self) -> Option<Self::TxToken> {
+		match self {
+			Self::Virtio(d) => d.transmit().map(AnyTxToken::Virtio),
+			Self::Loopback(d) => d.transmit().map(AnyTxToken::Loopback),
+		}
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		match self {
+			Self::Virtio(d) => d.capabilities(),
+			Self::Loopback(d) => d.capabilities(),
+		}
+	}
+}
+
+pub enum AnyRxToken<'a, 'd: 'a> {
+	Virtio(DevRxToken<'a, 'd>),
+	Loopback(LoopbackRxToken),
+}
+
+impl<'a, 'd: 'a> RxToken for AnyRxToken<'a, 'd> {
+	fn consume<R, F>(self, timestamp: Instant, f: F) -> smoltcp::Result<R>
+	where
+		F: FnOnce(&mut [u8]) -> smoltcp::Result<R>,
+	{
+		match self {
+			Self::Virtio(t) => t.consume(timestamp, f),
+			Self::Loopback(t) => t.consume(timestamp, f),
+		}
+	}
+}
+
+pub enum AnyTxToken<'a, 'd: 'a> {
+	Virtio(DevTxToken<'a, 'd>),
+	Loopback(LoopbackTxToken<'a>),
+}
+
+impl<'a, 'd: 'a> TxToken for AnyTxToken<'a, 'd> {
+	fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> smoltcp::Result<R>
+	where
+		F: FnOnce(&mut [u8]) -> smoltcp::Result<R>,
+	{
+		match self {
+			Self::Virtio(t) => t.consume(timestamp, len, f),
+			Self::Loopback(t) => t.consume(timestamp, len, f),
+		}
+	}
+}
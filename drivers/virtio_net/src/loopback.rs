@@ -0,0 +1,82 @@
+use alloc::{collections::VecDeque, vec::Vec};
+use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
+use smoltcp::time::Instant;
+
+/// The maximum amount of packets that can be in flight at any time.
+///
+/// If the queue is full any new packets are dropped.
+const MAX_QUEUED_PKT: usize = 64;
+
+/// A virtual device that immediately "receives" any packet sent to it.
+pub struct Loopback {
+	queue: VecDeque<Vec<u8>>,
+}
+
+impl Loopback {
+	pub fn new() -> Self {
+		Self {
+			queue: Default::default(),
+		}
+	}
+}
+
+impl<'a> Device<'a> for Loopback {
+	type RxToken = LoopbackRxToken;
+	type TxToken = LoopbackTxToken<'a>;
+
+	fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+		self.queue.pop_front().map(move |buffer| {
+			let rx = LoopbackRxToken { buffer };
+			let tx = LoopbackTxToken {
+				queue: &mut self.queue,
+			};
+			(rx, tx)
+		})
+	}
+
+	fn transmit(&'a mut self) -> Option<Self::TxToken> {
+		(self.queue.len() < MAX_QUEUED_PKT).then(|| LoopbackTxToken {
+			queue: &mut self.queue,
+		})
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		let mut cap = DeviceCapabilities::default();
+		cap.max_transmission_unit = 65535;
+		cap.max_burst_size = Some(MAX_QUEUED_PKT);
+		cap.medium = Medium::Ethernet;
+		cap
+	}
+}
+
+pub struct LoopbackRxToken {
+	buffer: Vec<u8>,
+}
+
+impl RxToken for LoopbackRxToken {
+	fn consume<R, F>(mut self, _: Instant, f: F) -> smoltcp::Result<R>
+	where
+		F: FnOnce(&mut [u8]) -> smoltcp::Result<R>,
+	{
+		f(&mut self.buffer)
+	}
+}
+
+pub struct LoopbackTxToken<'a> {
+	queue: &'a mut VecDeque<Vec<u8>>,
+}
+
+impl<'a> TxToken for LoopbackTxToken<'a> {
+	fn consume<R, F>(self, _: Instant, len: usize, f: F) -> smoltcp::Result<R>
+	where
+		F: FnOnce(&mut [u8]) -> smoltcp::Result<R>,
+	{
+		let mut buffer = Vec::new();
+		buffer.resize(len, 0);
+		let r = f(&mut buffer)?;
+		if self.queue.len() < MAX_QUEUED_PKT {
+			self.queue.push_back(buffer);
+		}
+		Ok(r)
+	}
+}
@@ -5,12 +5,13 @@
 #![feature(type_alias_impl_trait)]
 
 mod dev;
+mod loopback;
 mod tcp;
 mod udp;
 
 extern crate alloc;
 
-use alloc::{format, vec::Vec};
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
 
 use async_std::{
 	io::{Read, Write},
@@ -18,19 +19,44 @@ use async_std::{
 	object::{AsyncObject, RefAsyncObject},
 };
 use core::{
-	future::Future,
-	pin::Pin,
 	str::{self, FromStr},
 	task::Poll,
 	time::Duration,
 };
+use dev::AnyDev;
 use driver_utils::os::stream_table::{Data, JobId, Request, Response, StreamTable};
 use futures::stream::{FuturesUnordered, StreamExt};
+use loopback::Loopback;
 use rt::Error;
-use smoltcp::wire;
+use smoltcp::{iface, wire};
 use tcp::{TcpConnection, TcpListener};
 use udp::UdpSocket;
 
+type Interface = iface::Interface<'static, AnyDev<'static>>;
+
+/// The maximum amount of data returned by a single read request.
+const MAX_READ: usize = 1 << 15;
+
+/// The (locally administered) hardware address of the loopback interface.
+const LOOPBACK_MAC: wire::EthernetAddress = wire::EthernetAddress([0x02, 0, 0, 0, 0, 1]);
+
+/// A virtio-net device with its own interface.
+struct Nic {
+	dhcp: iface::SocketHandle,
+	/// The default gateway as received from DHCP.
+	gateway: Option<wire::Ipv4Address>,
+	poll_handle: AsyncObject,
+	poll_job: <AsyncObject as Read<()>>::Future,
+}
+
+/// The address or interface specified in the first part of a path.
+#[derive(Clone, Copy)]
+enum Source {
+	Default,
+	Iface(usize),
+	Addr(wire::IpAddress),
+}
+
 enum Socket {
 	TcpListener(TcpListener<5>),
 	TcpConnection(TcpConnection),
@@ -50,80 +76,46 @@ fn main() {
 		.next()
 		.expect("expected table name");
 
-	let dev_handle = {
-		let s = b" 1af4:1000";
-		let it = file_root.open(b"pci/info").unwrap();
-		let mut buf = [0; 64];
-		loop {
-			let l = it.read(&mut buf).unwrap();
-			assert!(l != 0, "device not found");
-			let dev = &buf[..l];
-			if dev.ends_with(s) {
-				let mut path = Vec::from(*b"pci/");
-				path.extend(&dev[..7]);
-				break file_root.open(&path).unwrap().into_raw();
-			}
-		}
-	};
-	let poll_handle = rt::io::open(dev_handle, b"poll").unwrap();
-	let poll_handle = AsyncObject::from_raw(poll_handle);
-
-	let pci_config = rt::io::map_object(dev_handle, None, rt::RWX::R, 0, usize::MAX).unwrap();
-
-	let pci = unsafe { pci::Pci::new(pci_config.0.cast(), 0, 0, &[]) };
-
-	let dev = pci.get(0, 0, 0).unwrap();
-	// FIXME figure out why InterfaceBuilder causes a 'static lifetime requirement
-	let dev = unsafe { core::mem::transmute::<&_, &_>(&dev) };
-
-	let (dev, addr) = {
-		match dev {
-			pci::Header::H0(h) => {
-				let map_bar = |bar: u8| {
-					assert!(bar < 6);
-					let mut s = *b"bar0";
-					s[3] += bar;
-					rt::RefObject::from_raw(dev_handle)
-						.open(&s)
-						.unwrap()
-						.map_object(None, rt::io::RWX::RW, 0, usize::MAX)
-						.unwrap()
-						.0
-						.cast()
-				};
-				let dma_alloc = |size: usize, _align| -> Result<_, ()> {
-					let (d, a, _) = driver_utils::dma::alloc_dma(size.try_into().unwrap()).unwrap();
-					Ok((d.cast(), virtio::PhysAddr::new(a.try_into().unwrap())))
-				};
-
-				let msix = virtio_net::Msix {
-					receive_queue: Some(0),
-					transmit_queue: Some(1),
-				};
-
-				unsafe { virtio_net::Device::new(h, map_bar, dma_alloc, msix).unwrap() }
-			}
-			_ => unreachable!(),
-		}
-	};
+	use smoltcp::{socket, time};
+
+	// The interface of each NIC has the same index as the NIC itself. The loopback interface
+	// comes last.
+	let mut ifaces = Vec::<Interface>::new();
+	let mut nics = Vec::<Nic>::new();
+
+	for dev_handle in find_devices(file_root) {
+		let (dev, addr) = init_device(dev_handle);
+		let mut iface = new_iface(
+			AnyDev::Virtio(dev::Dev::new(dev)),
+			wire::EthernetAddress(*addr.as_ref()),
+			Vec::from([wire::IpCidr::new(wire::Ipv4Address::UNSPECIFIED.into(), 0)]),
+		);
+
+		// Get an IP address using DHCP
+		let dhcp = iface.add_socket(socket::Dhcpv4Socket::new());
+
+		let poll_handle = rt::io::open(dev_handle, b"poll").unwrap();
+		let poll_handle = AsyncObject::from_raw(poll_handle);
+		let poll_job = poll_handle.read(());
+		nics.push(Nic {
+			dhcp,
+			gateway: None,
+			poll_handle,
+			poll_job,
+		});
+		ifaces.push(iface);
+	}
 
-	// Wrap the device for use with smoltcp
-	use smoltcp::{iface, socket, time};
-	let dev = dev::Dev::new(dev);
-	let mut ip_addrs = [wire::IpCidr::new(wire::Ipv4Address::UNSPECIFIED.into(), 0)];
-	let mut sockets = Vec::new();
-	sockets.resize_with(1024, || iface::SocketStorage::EMPTY);
-	let mut neighbors = [None; 8];
-	let mut routes = [None; 8];
-	let mut iface = iface::InterfaceBuilder::new(dev, sockets)
-		.ip_addrs(&mut ip_addrs[..])
-		.hardware_addr(wire::EthernetAddress(*addr.as_ref()).into())
-		.neighbor_cache(iface::NeighborCache::new(&mut neighbors[..]))
-		.routes(iface::Routes::new(&mut routes[..]))
-		.finalize();
-
-	// Get an IP address using DHCP
-	let dhcp = iface.add_socket(socket::Dhcpv4Socket::new());
+	// Always provide a loopback interface so local processes can communicate even if there
+	// is no NIC.
+	ifaces.push(new_iface(
+		AnyDev::Loopback(Loopback::new()),
+		LOOPBACK_MAC,
+		Vec::from([
+			wire::IpCidr::new(wire::Ipv4Address::new(127, 0, 0, 1).into(), 8),
+			wire::IpCidr::new(wire::Ipv6Address::LOOPBACK.into(), 128),
+		]),
+	));
 
 	let mut alloc_port = 50_000u16;
 	let mut alloc_port = || {
@@ -139,23 +131,30 @@ fn main() {
 	let mut table = Table::new(table_name);
 	let mut table_notify = RefAsyncObject::from(table.table.notifier()).read(());
 
-	let mut poll_job = poll_handle.read(());
+	// The objects to wait on for packets and requests.
+	let wait_handles = nics
+		.iter()
+		.map(|nic| nic.poll_handle.as_raw())
+		.chain([table.table.notifier().as_raw()])
+		.collect::<Vec<_>>();
 
 	struct PendingWrite {
 		handle: rt::Handle,
 		job_id: JobId,
-		data: alloc::boxed::Box<[u8]>,
+		data: Data<'static>,
 	}
 	struct PendingRead {
 		handle: rt::Handle,
 		job_id: JobId,
-		len: u32,
+		len: usize,
+		peek: bool,
 	}
 	// FIXME avoid closing before finishing.
 	let mut pending_writes = Vec::<PendingWrite>::new();
 	let mut pending_reads = Vec::<PendingRead>::new();
 
 	let mut t;
+	// Only used for paths. Data is copied directly between table buffers and sockets.
 	let mut buf = [0; 2048];
 	loop {
 		// Finish pending writes
@@ -163,10 +162,16 @@ fn main() {
 			let p = &mut pending_writes[i];
 			match &mut table.objects[p.handle] {
 				Object::Socket(Socket::TcpConnection(sock)) => {
-					if let Some(r) = sock.write_all(&p.data, &mut iface) {
-						r.unwrap();
-						table.amount(p.job_id, p.data.len() as _);
-						pending_writes.swap_remove(i);
+					match sock.write_from(&p.data, &mut ifaces) {
+						Ok(0) => {}
+						r => {
+							let p = pending_writes.swap_remove(i);
+							p.data.manual_drop();
+							match r {
+								Ok(l) => table.amount(p.job_id, l),
+								Err(_) => table.error(p.job_id, Error::Unknown),
+							}
+						}
 					}
 				}
 				Object::Socket(Socket::Udp(_)) => todo!(),
@@ -179,10 +184,14 @@ fn main() {
 			let p = &mut pending_reads[i];
 			match &mut table.objects[p.handle] {
 				Object::Socket(Socket::TcpConnection(sock)) => {
-					match sock.read(&mut buf[..p.len as _], &mut iface) {
-						Ok(0) => {}
-						Ok(l) => {
-							table.data(p.job_id, &buf[..l]);
+					match sock.read_into(table.table, p.len, p.peek, &mut ifaces) {
+						Ok(None) => {}
+						Ok(Some(d)) => {
+							table.send_data(p.job_id, d);
+							pending_reads.swap_remove(i);
+						}
+						Err(smoltcp::Error::Illegal) | Err(smoltcp::Error::Finished) => {
+							table.error(p.job_id, Error::Unknown);
 							pending_reads.swap_remove(i);
 						}
 						Err(e) => todo!("{:?}", e),
@@ -196,19 +205,19 @@ fn main() {
 		// Advance TCP connection state.
 		for i in (0..connecting_tcp_sockets.len()).rev() {
 			let (sock, _) = &connecting_tcp_sockets[i];
-			if sock.ready(&mut iface) {
+			if sock.ready(&mut ifaces) {
 				let (sock, job_id) = connecting_tcp_sockets.swap_remove(i);
 				table.insert(job_id, Object::Socket(Socket::TcpConnection(sock)));
-			} else if !sock.active(&mut iface) {
+			} else if !sock.active(&mut ifaces) {
 				todo!()
 			}
 		}
 		for i in (0..accepted_tcp_sockets.len()).rev() {
 			let (sock, _) = &accepted_tcp_sockets[i];
-			if sock.ready(&mut iface) {
+			if sock.ready(&mut ifaces) {
 				let (sock, job_id) = accepted_tcp_sockets.swap_remove(i);
 				table.insert(job_id, Object::Socket(Socket::TcpConnection(sock)));
-			} else if !sock.active(&mut iface) {
+			} else if !sock.active(&mut ifaces) {
 				// Try again
 				todo!()
 			}
@@ -217,7 +226,7 @@ fn main() {
 		// Remove closed TCP connections.
 		for i in (0..closing_tcp_sockets.len()).rev() {
 			let sock = &mut closing_tcp_sockets[i];
-			if sock.remove(&mut iface) {
+			if sock.remove(&mut ifaces) {
 				closing_tcp_sockets.swap_remove(i);
 			}
 		}
@@ -226,7 +235,7 @@ fn main() {
 		for i in (0..accepting_tcp_sockets.len()).rev() {
 			let (handle, _) = &accepting_tcp_sockets[i];
 			let c = match &mut table.objects[*handle] {
-				Object::Socket(Socket::TcpListener(l)) => l.accept(&mut iface),
+				Object::Socket(Socket::TcpListener(l)) => l.accept(&mut ifaces),
 				_ => unreachable!(),
 			};
 			if let Some(sock) = c {
@@ -235,12 +244,17 @@ fn main() {
 			}
 		}
 
-		let w = driver_utils::task::waker::dummy();
-		let mut cx = core::task::Context::from_waker(&w);
-
 		// Handle incoming requests
 		while let Some((handle, req)) = table.table.dequeue() {
 			match req {
+				Request::Open { job_id, path } if path.len() > buf.len() => {
+					path.manual_drop();
+					table.error(job_id, Error::InvalidData);
+				}
+				Request::Create { job_id, path } if path.len() > buf.len() => {
+					path.manual_drop();
+					table.error(job_id, Error::InvalidData);
+				}
 				Request::Open { job_id, path } => {
 					let l = path.len();
 					path.copy_to(0, &mut buf[..l]);
@@ -253,7 +267,12 @@ fn main() {
 						let query = match (path.next().unwrap(), path.next(), path.next()) {
 							("", None, _) => Query::Root(QueryRoot::Default),
 							("default", None, _) | ("default", Some(""), None) => {
-								let addr = into_ip6(iface.ip_addrs()[0].address());
+								let i = default_iface(&nics);
+								let addr = into_ip6(ifaces[i].ip_addrs()[0].address());
+								Query::SourceAddr(addr, Protocol::Tcp)
+							},
+							(name, None, _) | (name, Some(""), None) if let Some(i) = iface_by_name(&nics, name) => {
+								let addr = into_ip6(ifaces[i].ip_addrs()[0].address());
 								Query::SourceAddr(addr, Protocol::Tcp)
 							},
 							(addr, None, _) | (addr, Some(""), None) if let Ok(addr) = wire::IpAddress::from_str(addr) => todo!(),
@@ -283,15 +302,9 @@ fn main() {
 						let path = str::from_utf8(&buf[..path.len()]).unwrap();
 						let mut parts = path.split('/');
 						let source = match parts.next().unwrap() {
-							"default" => iface.ip_addrs()[0].address(),
-							source => {
-								let source = Ipv6Addr::from_str(source).unwrap();
-								if let Some(source) = source.to_ipv4() {
-									wire::IpAddress::Ipv4(wire::Ipv4Address(source.octets()))
-								} else {
-									wire::IpAddress::Ipv6(wire::Ipv6Address(source.octets()))
-								}
-							}
+							"default" => Source::Default,
+							name if let Some(i) = iface_by_name(&nics, name) => Source::Iface(i),
+							source => Source::Addr(parse_ip(source)),
 						};
 						table.insert(
 							job_id,
@@ -302,25 +315,51 @@ fn main() {
 										// type
 										"listen" => {
 											let port = parts.next().unwrap().parse().unwrap();
-											let source = wire::IpEndpoint { addr: source, port };
+											let (bind, addr) = match source {
+												// Listen on all interfaces
+												Source::Addr(addr) if addr.is_unspecified() => {
+													((0..ifaces.len()).collect(), addr)
+												}
+												Source::Addr(addr) => {
+													match find_iface(&ifaces, addr) {
+														Some(i) => (Vec::from([i]), addr),
+														None => {
+															table
+																.error(job_id, Error::DoesNotExist);
+															continue;
+														}
+													}
+												}
+												Source::Default | Source::Iface(_) => {
+													let i = match source {
+														Source::Iface(i) => i,
+														_ => default_iface(&nics),
+													};
+													(
+														Vec::from([i]),
+														ifaces[i].ip_addrs()[0].address(),
+													)
+												}
+											};
+											let source = wire::IpEndpoint { addr, port };
 											Socket::TcpListener(TcpListener::new(
-												&mut iface, source,
+												&mut ifaces,
+												bind,
+												source,
 											))
 										}
 										"connect" => {
-											let dest = parts.next().unwrap();
-											let dest = Ipv6Addr::from_str(dest).unwrap();
-											let dest = dest.to_ipv4().map_or(
-												wire::IpAddress::Ipv6(wire::Ipv6Address(
-													dest.octets(),
-												)),
-												|dest| {
-													wire::IpAddress::Ipv4(wire::Ipv4Address(
-														dest.octets(),
-													))
-												},
-											);
+											let dest = parse_ip(parts.next().unwrap());
 											let port = parts.next().unwrap().parse().unwrap();
+											let default = default_iface(&nics);
+											let (i, source) =
+												match route(&ifaces, default, source, dest) {
+													Some(r) => r,
+													None => {
+														table.error(job_id, Error::DoesNotExist);
+														continue;
+													}
+												};
 											let source = wire::IpEndpoint {
 												addr: source,
 												port: alloc_port(),
@@ -328,7 +367,7 @@ fn main() {
 											let dest = wire::IpEndpoint { addr: dest, port };
 
 											connecting_tcp_sockets.push((
-												TcpConnection::new(&mut iface, source, dest),
+												TcpConnection::new(&mut ifaces, i, source, dest),
 												job_id,
 											));
 											continue;
@@ -337,7 +376,22 @@ fn main() {
 										_ => todo!(),
 									}
 								}
-								"udp" => Socket::Udp(UdpSocket::new(&mut iface)),
+								"udp" => {
+									let i = match source {
+										Source::Addr(addr) if !addr.is_unspecified() => {
+											match find_iface(&ifaces, addr) {
+												Some(i) => i,
+												None => {
+													table.error(job_id, Error::DoesNotExist);
+													continue;
+												}
+											}
+										}
+										Source::Iface(i) => i,
+										_ => default_iface(&nics),
+									};
+									Socket::Udp(UdpSocket::new(&mut ifaces, i))
+								}
 								_ => todo!(),
 							}),
 						);
@@ -352,24 +406,20 @@ fn main() {
 					job_id,
 					amount,
 				} => {
-					let len = (amount as usize).min(buf.len());
+					let len = (amount as usize).min(MAX_READ);
 					match &mut table.objects[handle] {
 						Object::Socket(Socket::TcpListener(_)) => {
 							table.error(job_id, Error::InvalidOperation)
 						}
 						Object::Socket(Socket::TcpConnection(sock)) => {
-							let r = if peek {
-								sock.peek(&mut buf[..len], &mut iface)
-							} else {
-								sock.read(&mut buf[..len], &mut iface)
-							};
-							match r {
-								Ok(0) => pending_reads.push(PendingRead {
+							match sock.read_into(table.table, len, peek, &mut ifaces) {
+								Ok(None) => pending_reads.push(PendingRead {
 									handle,
 									job_id,
-									len: len.try_into().unwrap(),
+									len,
+									peek,
 								}),
-								Ok(len) => table.data(job_id, &buf[..len]),
+								Ok(Some(d)) => table.send_data(job_id, d),
 								Err(smoltcp::Error::Illegal) | Err(smoltcp::Error::Finished) => {
 									table.error(job_id, Error::Unknown)
 								}
@@ -388,15 +438,26 @@ fn main() {
 							}
 							Some(Query::Root(q @ QueryRoot::Global)) => {
 								if !peek {
-									*q = QueryRoot::IpAddr(0);
+									*q = QueryRoot::Iface(0);
 								}
 								table.data(job_id, b"::")
 							}
+							Some(Query::Root(QueryRoot::Iface(i))) => {
+								let name = iface_name(&nics, *i);
+								if !peek {
+									*i += 1;
+									if *i >= ifaces.len() {
+										*q = Some(Query::Root(QueryRoot::IpAddr(0)));
+									}
+								}
+								table.data(job_id, name.as_bytes())
+							}
 							Some(Query::Root(QueryRoot::IpAddr(i))) => {
-								let ip = into_ip6(iface.ip_addrs()[*i].address());
+								let mut addrs = ifaces.iter().flat_map(|i| i.ip_addrs());
+								let ip = into_ip6(addrs.nth(*i).unwrap().address());
 								if !peek {
 									*i += 1;
-									if *i >= iface.ip_addrs().len() {
+									if addrs.next().is_none() {
 										*q = None;
 									}
 								}
@@ -423,19 +484,23 @@ fn main() {
 				Request::Write { job_id, data } => match &mut table.objects[handle] {
 					Object::Socket(Socket::TcpListener(_)) => todo!(),
 					Object::Socket(Socket::TcpConnection(sock)) => {
-						let len = data.len().min(buf.len());
-						data.copy_to(0, &mut buf[..len]);
-						data.manual_drop();
-						match sock.write(&buf[..len], &mut iface) {
-							Ok(l) if l == 0 => {
+						match sock.write_from(&data, &mut ifaces) {
+							// Keep the buffer around until there is room in the socket.
+							Ok(0) if data.len() > 0 => {
 								pending_writes.push(PendingWrite {
 									handle,
 									job_id,
-									data: buf[..len].into(),
+									data,
 								});
 							}
-							Ok(l) => table.amount(job_id, l),
-							Err(smoltcp::Error::Illegal) => table.error(job_id, Error::Unknown),
+							Ok(l) => {
+								data.manual_drop();
+								table.amount(job_id, l);
+							}
+							Err(smoltcp::Error::Illegal) => {
+								data.manual_drop();
+								table.error(job_id, Error::Unknown);
+							}
 							Err(e) => todo!("handle {:?}", e),
 						}
 					}
@@ -448,52 +513,271 @@ fn main() {
 					match table.objects.remove(handle).unwrap() {
 						Object::Socket(Socket::TcpListener(_)) => todo!(),
 						Object::Socket(Socket::TcpConnection(mut sock)) => {
-							sock.close(&mut iface);
+							sock.close(&mut ifaces);
 							closing_tcp_sockets.push(sock);
 						}
-						Object::Socket(Socket::Udp(sock)) => sock.close(&mut iface),
+						Object::Socket(Socket::Udp(sock)) => sock.close(&mut ifaces),
 						Object::Query(_) => {}
 					}
 					continue;
 				}
-				Request::Seek { job_id, .. } => table.error(job_id, Error::InvalidOperation),
+				Request::Seek { job_id, .. } | Request::ReadAt { job_id, .. } => {
+					table.error(job_id, Error::InvalidOperation)
+				}
+				Request::WriteAt { job_id, data, .. } => {
+					data.manual_drop();
+					table.error(job_id, Error::InvalidOperation)
+				}
+				Request::WriteVectored { job_id, data } => {
+					data.into_iter().for_each(|d| d.manual_drop());
+					table.error(job_id, Error::InvalidOperation)
+				}
 				Request::GetMeta { .. } => todo!(),
 				Request::SetMeta { .. } => todo!(),
 				Request::Destroy { .. } => todo!(),
-				Request::Share { .. } => todo!(),
+				Request::Share { job_id, .. } => table.error(job_id, Error::InvalidOperation),
+				Request::Cancel { job_id } => {
+					let id = job_id.get();
+					if let Some(i) = pending_reads.iter().position(|p| p.job_id.get() == id) {
+						pending_reads.swap_remove(i);
+					} else if let Some(i) = pending_writes.iter().position(|p| p.job_id.get() == id)
+					{
+						pending_writes.swap_remove(i).data.manual_drop();
+					} else if let Some(i) = accepting_tcp_sockets
+						.iter()
+						.position(|(_, j)| j.get() == id)
+					{
+						accepting_tcp_sockets.swap_remove(i);
+					} else if let Some(i) = connecting_tcp_sockets
+						.iter()
+						.position(|(_, j)| j.get() == id)
+					{
+						let (mut sock, _) = connecting_tcp_sockets.swap_remove(i);
+						sock.close(&mut ifaces);
+						closing_tcp_sockets.push(sock);
+					} else if let Some(i) =
+						accepted_tcp_sockets.iter().position(|(_, j)| j.get() == id)
+					{
+						let (mut sock, _) = accepted_tcp_sockets.swap_remove(i);
+						sock.close(&mut ifaces);
+						closing_tcp_sockets.push(sock);
+					} else {
+						// The job finished already.
+						continue;
+					}
+					table.error(job_id, Error::Cancelled);
+				}
 			}
 		}
 		table.flush();
 
-		let dhcp = iface.get_socket::<socket::Dhcpv4Socket>(dhcp);
-		if let Some(s) = dhcp.poll() {
-			if let socket::Dhcpv4Event::Configured(s) = s {
-				iface.update_ip_addrs(|i| i[0] = s.address.into());
-				if let Some(r) = s.router {
-					iface.routes_mut().add_default_ipv4_route(r).unwrap();
+		let mut processed = false;
+		for (nic, iface) in nics.iter_mut().zip(ifaces.iter_mut()) {
+			let dhcp = iface.get_socket::<socket::Dhcpv4Socket>(nic.dhcp);
+			match dhcp.poll() {
+				Some(socket::Dhcpv4Event::Configured(s)) => {
+					iface.update_ip_addrs(|i| i[0] = s.address.into());
+					if let Some(r) = s.router {
+						iface.routes_mut().add_default_ipv4_route(r).unwrap();
+					}
+					nic.gateway = s.router;
+				}
+				Some(socket::Dhcpv4Event::Deconfigured) => {
+					iface.update_ip_addrs(|i| {
+						i[0] = wire::IpCidr::new(wire::Ipv4Address::UNSPECIFIED.into(), 0)
+					});
+					iface.routes_mut().remove_default_ipv4_route();
+					nic.gateway = None;
 				}
+				None => {}
 			}
-		}
 
-		if Pin::new(&mut poll_job).poll(&mut cx).is_ready() {
-			iface.device_mut().process();
-			poll_job = poll_handle.read(());
+			if driver_utils::task::poll(&mut nic.poll_job).is_ready() {
+				iface.device_mut().process();
+				nic.poll_job = nic.poll_handle.read(());
+				processed = true;
+			}
+		}
+		if processed {
 			continue;
 		}
-		if Pin::new(&mut table_notify).poll(&mut cx).is_ready() {
+		if driver_utils::task::poll(&mut table_notify).is_ready() {
 			table_notify = RefAsyncObject::from(table.table.notifier()).read(());
 		}
 		t = async_std::queue::poll();
-		if let Some(delay) = iface.poll_delay(time::Instant::from_micros(t.as_micros() as i64)) {
-			let delay = delay.into();
-			if delay != Duration::ZERO {
-				t = async_std::queue::wait(delay);
-			}
+		let now = time::Instant::from_micros(t.as_micros() as i64);
+		// If no interface needs to be polled at a specific time, wait for a request or packet.
+		let delay = ifaces
+			.iter_mut()
+			.filter_map(|iface| iface.poll_delay(now))
+			.min()
+			.map_or(Duration::MAX, Into::into);
+		if delay != Duration::ZERO {
+			(_, t) = async_std::queue::wait_any(&wait_handles, delay);
 		}
 
-		iface
-			.poll(time::Instant::from_micros(t.as_micros() as i64))
-			.unwrap();
+		let now = time::Instant::from_micros(t.as_micros() as i64);
+		for iface in ifaces.iter_mut() {
+			iface.poll(now).unwrap();
+		}
+	}
+}
+
+/// Find all virtio-net devices.
+fn find_devices(file_root: rt::RefObject<'_>) -> Vec<rt::Handle> {
+	let s = b" 1af4:1000";
+	let it = file_root.open(b"pci/info").unwrap();
+	let mut buf = [0; 64];
+	let mut devs = Vec::new();
+	loop {
+		let l = it.read(&mut buf).unwrap();
+		if l == 0 {
+			break devs;
+		}
+		let dev = &buf[..l];
+		if dev.ends_with(s) {
+			let mut path = Vec::from(*b"pci/");
+			path.extend(&dev[..7]);
+			devs.push(file_root.open(&path).unwrap().into_raw());
+		}
+	}
+}
+
+/// Set up a virtio-net device.
+fn init_device(dev_handle: rt::Handle) -> (virtio_net::Device<'static>, virtio_net::Mac) {
+	let pci_config = rt::io::map_object(dev_handle, None, rt::RWX::R, 0, usize::MAX).unwrap();
+
+	let pci = unsafe { pci::Pci::new(pci_config.0.cast(), 0, 0, &[]) };
+
+	let dev = pci.get(0, 0, 0).unwrap();
+	// FIXME figure out why InterfaceBuilder causes a 'static lifetime requirement
+	let dev = unsafe { core::mem::transmute::<&_, &'static _>(&dev) };
+
+	match dev {
+		pci::Header::H0(h) => {
+			let map_bar = |bar: u8| {
+				assert!(bar < 6);
+				let mut s = *b"bar0";
+				s[3] += bar;
+				rt::RefObject::from_raw(dev_handle)
+					.open(&s)
+					.unwrap()
+					.map_object(None, rt::io::RWX::RW, 0, usize::MAX)
+					.unwrap()
+					.0
+					.cast()
+			};
+			let dma_alloc = |size: usize, _align| -> Result<_, ()> {
+				let (d, a, _) = driver_utils::dma::alloc_dma(size.try_into().unwrap()).unwrap();
+				Ok((d.cast(), virtio::PhysAddr::new(a.try_into().unwrap())))
+			};
+
+			let msix = virtio_net::Msix {
+				receive_queue: Some(0),
+				transmit_queue: Some(1),
+			};
+
+			unsafe { virtio_net::Device::new(h, map_bar, dma_alloc, msix).unwrap() }
+		}
+		_ => unreachable!(),
+	}
+}
+
+fn new_iface(
+	dev: AnyDev<'static>,
+	mac: wire::EthernetAddress,
+	ip_addrs: Vec<wire::IpCidr>,
+) -> Interface {
+	let mut sockets = Vec::new();
+	sockets.resize_with(1024, || iface::SocketStorage::EMPTY);
+	iface::InterfaceBuilder::new(dev, sockets)
+		.ip_addrs(ip_addrs)
+		.hardware_addr(mac.into())
+		.neighbor_cache(iface::NeighborCache::new(BTreeMap::new()))
+		.routes(iface::Routes::new(BTreeMap::new()))
+		.finalize()
+}
+
+/// Find the interface that has the given address.
+fn find_iface(ifaces: &[Interface], addr: wire::IpAddress) -> Option<usize> {
+	ifaces.iter().position(|i| i.has_ip_addr(addr))
+}
+
+/// The interface used for `default` paths.
+///
+/// This is the first NIC with a default gateway. If there is none it is the first NIC or the
+/// loopback interface if there are no NICs at all.
+fn default_iface(nics: &[Nic]) -> usize {
+	nics.iter().position(|n| n.gateway.is_some()).unwrap_or(0)
+}
+
+/// The name of an interface, i.e. `eth<n>` for NICs and `lo` for the loopback interface.
+fn iface_name(nics: &[Nic], i: usize) -> String {
+	if i < nics.len() {
+		format!("eth{}", i)
+	} else {
+		"lo".into()
+	}
+}
+
+/// Find an interface by name.
+fn iface_by_name(nics: &[Nic], name: &str) -> Option<usize> {
+	if name == "lo" {
+		return Some(nics.len());
+	}
+	let i = name.strip_prefix("eth")?;
+	// Don't accept e.g. "eth01" so there is only one name per interface.
+	let valid = i.bytes().all(|c| c.is_ascii_digit()) && (i == "0" || !i.starts_with('0'));
+	valid
+		.then(|| i.parse().ok())
+		.flatten()
+		.filter(|&i| i < nics.len())
+}
+
+/// Determine which interface and source address to use to reach the given destination.
+fn route(
+	ifaces: &[Interface],
+	default: usize,
+	source: Source,
+	dest: wire::IpAddress,
+) -> Option<(usize, wire::IpAddress)> {
+	let on_link = |f: fn(wire::IpAddress) -> bool| {
+		ifaces.iter().enumerate().find_map(|(i, iface)| {
+			iface
+				.ip_addrs()
+				.iter()
+				.find(|a| !a.address().is_unspecified() && f(a.address()) && a.contains_addr(&dest))
+				.map(|a| (i, a.address()))
+		})
+	};
+	match source {
+		Source::Addr(addr) if !addr.is_unspecified() => find_iface(ifaces, addr).map(|i| (i, addr)),
+		Source::Iface(i) => Some((i, ifaces[i].ip_addrs()[0].address())),
+		_ if is_loopback(dest) => on_link(is_loopback),
+		// Prefer interfaces that are directly connected to the destination's network.
+		_ => on_link(|a| !is_loopback(a))
+			.or_else(|| Some((default, ifaces[default].ip_addrs()[0].address()))),
+	}
+}
+
+fn is_loopback(addr: wire::IpAddress) -> bool {
+	match addr {
+		wire::IpAddress::Ipv4(a) => a.is_loopback(),
+		wire::IpAddress::Ipv6(a) => a.is_loopback(),
+		_ => false,
+	}
+}
+
+/// Parse an IPv6 address, converting it to an IPv4 address if it is IPv4-mapped.
+fn parse_ip(addr: &str) -> wire::IpAddress {
+	let addr = Ipv6Addr::from_str(addr).unwrap();
+	// Don't use to_ipv4 as that also converts ::1 to 0.0.0.1
+	match addr.segments() {
+		[0, 0, 0, 0, 0, 0xffff, ..] => {
+			let [.., a, b, c, d] = addr.octets();
+			wire::IpAddress::Ipv4(wire::Ipv4Address([a, b, c, d]))
+		}
+		_ => wire::IpAddress::Ipv6(wire::Ipv6Address(addr.octets())),
 	}
 }
 
@@ -511,6 +795,7 @@ enum Query {
 enum QueryRoot {
 	Default,
 	Global,
+	Iface(usize),
 	IpAddr(usize),
 }
 
@@ -520,15 +805,18 @@ enum Object {
 }
 
 struct Table {
-	table: StreamTable,
+	/// The table lives for the entire lifetime of the driver. Making it `'static` allows keeping
+	/// buffers of pending requests around without copying them.
+	table: &'static StreamTable,
 	objects: driver_utils::Arena<Object>,
 	dirty: bool,
 }
 
 impl Table {
 	fn new(table_name: &[u8]) -> Self {
-		let (buf, _) = rt::Object::new(rt::NewObject::SharedMemory { size: 1 << 18 }).unwrap();
-		let table = StreamTable::new(&buf, rt::io::Pow2Size(9), (1 << 12) - 1);
+		let (buf, _) = rt::Object::new(rt::NewObject::SharedMemory { size: 1 << 20 }).unwrap();
+		let table = StreamTable::new(&buf, rt::io::Pow2Size(9), (1 << 16) - 1);
+		let table = alloc::boxed::Box::leak(alloc::boxed::Box::new(table));
 		rt::io::file_root()
 			.unwrap()
 			.create(table_name)
@@ -560,6 +848,11 @@ impl Table {
 		self.dirty = true;
 	}
 
+	fn send_data(&mut self, job_id: JobId, data: Data<'static>) {
+		self.table.enqueue(job_id, Response::Data(data));
+		self.dirty = true;
+	}
+
 	fn amount(&mut self, job_id: JobId, amount: usize) {
 		self.table
 			.enqueue(job_id, Response::Amount(amount.try_into().unwrap()));
@@ -1,4 +1,5 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
+use driver_utils::os::stream_table::{Data, StreamTable};
 use smoltcp::{
 	iface::{Interface, SocketHandle},
 	phy::Device,
@@ -6,40 +7,50 @@ use smoltcp::{
 	wire::IpEndpoint,
 };
 
-pub struct TcpListener<const PENDING_MAX: usize>
-where
-	[(); PENDING_MAX]: Sized,
-{
-	pending: [SocketHandle; PENDING_MAX],
+/// The size of the receive and transmit buffer of each socket.
+///
+/// Larger buffers allow more data to be in flight, which matters for bulk transfers.
+const BUFFER_SIZE: usize = 1 << 15;
+
+pub struct TcpListener<const PENDING_MAX: usize> {
+	/// Sockets waiting for a connection along with the interface each is bound to.
+	///
+	/// The backlog is shared by all interfaces so binding to more interfaces doesn't multiply
+	/// the memory used by the buffers of pending sockets.
+	pending: Vec<(usize, SocketHandle)>,
 	source: IpEndpoint,
 }
 
-impl<const PENDING_MAX: usize> TcpListener<PENDING_MAX>
-where
-	[SocketHandle; PENDING_MAX]: Sized + Default,
-{
+impl<const PENDING_MAX: usize> TcpListener<PENDING_MAX> {
 	pub fn new(
-		iface: &mut Interface<impl for<'d> Device<'d>>,
+		ifaces: &mut [Interface<impl for<'d> Device<'d>>],
+		bind: impl IntoIterator<Item = usize>,
 		source: impl Into<IpEndpoint>,
 	) -> Self {
 		let source = source.into();
-		let mut pending: [SocketHandle; PENDING_MAX] = Default::default();
-		pending
-			.iter_mut()
-			.for_each(|p| *p = new_socket(iface, |s| s.listen(source).unwrap()));
+		let bind = bind.into_iter().collect::<Vec<_>>();
+		// Every interface needs at least one socket to accept connections on.
+		let pending = bind
+			.iter()
+			.cycle()
+			.take(PENDING_MAX.max(bind.len()))
+			.map(|&i| (i, new_socket(&mut ifaces[i], |s| s.listen(source).unwrap())))
+			.collect();
 		Self { pending, source }
 	}
 
 	pub fn accept(
 		&mut self,
-		iface: &mut Interface<impl for<'d> Device<'d>>,
+		ifaces: &mut [Interface<impl for<'d> Device<'d>>],
 	) -> Option<TcpConnection> {
-		for p in self.pending.iter_mut() {
+		for (i, p) in self.pending.iter_mut() {
+			let iface = &mut ifaces[*i];
 			let sock = iface.get_socket::<TcpSocket>(*p);
 			if sock.is_active() {
 				let handle = *p;
+				// Replace the socket on the same interface to keep the backlog spread evenly.
 				*p = new_socket(iface, |s| s.listen(self.source).unwrap());
-				return Some(TcpConnection { handle });
+				return Some(TcpConnection { handle, iface: *i });
 			}
 		}
 		None
@@ -48,69 +59,106 @@ where
 
 pub struct TcpConnection {
 	handle: SocketHandle,
+	iface: usize,
 }
 
 impl TcpConnection {
 	pub fn new(
-		iface: &mut Interface<impl for<'d> Device<'d>>,
+		ifaces: &mut [Interface<impl for<'d> Device<'d>>],
+		iface: usize,
 		source: impl Into<IpEndpoint>,
 		destination: impl Into<IpEndpoint>,
 	) -> Self {
-		let handle = new_socket(iface, |_| ());
-		let (sock, cx) = iface.get_socket_and_context::<TcpSocket>(handle);
+		let handle = new_socket(&mut ifaces[iface], |_| ());
+		let (sock, cx) = ifaces[iface].get_socket_and_context::<TcpSocket>(handle);
 		sock.connect(cx, destination, source).unwrap();
-		Self { handle }
+		Self { handle, iface }
 	}
 
-	pub fn ready(&self, iface: &mut Interface<impl for<'d> Device<'d>>) -> bool {
-		let sock = iface.get_socket::<TcpSocket>(self.handle);
+	pub fn ready(&self, ifaces: &mut [Interface<impl for<'d> Device<'d>>]) -> bool {
+		let sock = ifaces[self.iface].get_socket::<TcpSocket>(self.handle);
 		sock.may_send() || sock.may_recv()
 	}
 
-	pub fn active(&self, iface: &mut Interface<impl for<'d> Device<'d>>) -> bool {
-		let sock = iface.get_socket::<TcpSocket>(self.handle);
+	pub fn active(&self, ifaces: &mut [Interface<impl for<'d> Device<'d>>]) -> bool {
+		let sock = ifaces[self.iface].get_socket::<TcpSocket>(self.handle);
 		sock.is_active()
 	}
 
-	pub fn read(
-		&mut self,
-		data: &mut [u8],
-		iface: &mut Interface<impl for<'d> Device<'d>>,
-	) -> smoltcp::Result<usize> {
-		iface.get_socket::<TcpSocket>(self.handle).recv_slice(data)
-	}
-
-	pub fn peek(
+	/// Copy received data directly into a table buffer, avoiding intermediate copies.
+	///
+	/// Returns `None` if no data is available yet or if no buffer could be allocated.
+	pub fn read_into<'t>(
 		&mut self,
-		data: &mut [u8],
-		iface: &mut Interface<impl for<'d> Device<'d>>,
-	) -> smoltcp::Result<usize> {
-		iface.get_socket::<TcpSocket>(self.handle).peek_slice(data)
+		table: &'t StreamTable,
+		max: usize,
+		peek: bool,
+		ifaces: &mut [Interface<impl for<'d> Device<'d>>],
+	) -> smoltcp::Result<Option<Data<'t>>> {
+		let sock = ifaces[self.iface].get_socket::<TcpSocket>(self.handle);
+		if peek {
+			// Only the contiguous part of the receive buffer can be peeked at.
+			let b = sock.peek(max)?;
+			return Ok((!b.is_empty())
+				.then(|| table.alloc(b.len()))
+				.flatten()
+				.map(|d| {
+					d.copy_from(0, b);
+					d
+				}));
+		}
+		// Zero-sized reads check whether the socket can still receive data.
+		sock.recv(|_| (0, ()))?;
+		let len = sock.recv_queue().min(max);
+		let data = match (len > 0).then(|| table.alloc(len)).flatten() {
+			Some(d) => d,
+			None => return Ok(None),
+		};
+		let mut offset = 0;
+		while offset < len {
+			// The receive buffer is a ring buffer, so this may take two iterations.
+			let n = sock.recv(|b| {
+				let n = b.len().min(len - offset);
+				data.copy_from(offset, &b[..n]);
+				(n, n)
+			})?;
+			offset += n;
+		}
+		Ok(Some(data))
 	}
 
-	pub fn write(
+	/// Copy data directly from a table buffer into the send buffer.
+	///
+	/// Returns the amount of bytes that will be sent.
+	pub fn write_from(
 		&mut self,
-		data: &[u8],
-		iface: &mut Interface<impl for<'d> Device<'d>>,
+		data: &Data<'_>,
+		ifaces: &mut [Interface<impl for<'d> Device<'d>>],
 	) -> smoltcp::Result<usize> {
-		iface.get_socket::<TcpSocket>(self.handle).send_slice(data)
-	}
-
-	pub fn write_all(
-		&mut self,
-		data: &[u8],
-		iface: &mut Interface<impl for<'d> Device<'d>>,
-	) -> Option<smoltcp::Result<()>> {
-		let s = iface.get_socket::<TcpSocket>(self.handle);
-		(s.send_capacity() - s.send_queue() >= data.len())
-			.then(|| s.send_slice(data).map(|l| debug_assert_eq!(l, data.len())))
+		let sock = ifaces[self.iface].get_socket::<TcpSocket>(self.handle);
+		let mut offset = 0;
+		loop {
+			// The send buffer is a ring buffer, so this may take two iterations.
+			let n = sock.send(|b| {
+				let n = b.len().min(data.len() - offset);
+				data.copy_to(offset, &mut b[..n]);
+				(n, n)
+			})?;
+			if n == 0 {
+				break Ok(offset);
+			}
+			offset += n;
+		}
 	}
 
-	pub fn close(&mut self, iface: &mut Interface<impl for<'d> Device<'d>>) {
-		iface.get_socket::<TcpSocket>(self.handle).close();
+	pub fn close(&mut self, ifaces: &mut [Interface<impl for<'d> Device<'d>>]) {
+		ifaces[self.iface]
+			.get_socket::<TcpSocket>(self.handle)
+			.close();
 	}
 
-	pub fn remove(&mut self, iface: &mut Interface<impl for<'d> Device<'d>>) -> bool {
+	pub fn remove(&mut self, ifaces: &mut [Interface<impl for<'d> Device<'d>>]) -> bool {
+		let iface = &mut ifaces[self.iface];
 		let sock = iface.get_socket::<TcpSocket>(self.handle);
 		let remove = sock.state() == TcpState::Closed;
 		if remove {
@@ -124,8 +172,11 @@ fn new_socket(
 	iface: &mut Interface<impl for<'d> Device<'d>>,
 	f: impl FnOnce(&mut TcpSocket),
 ) -> SocketHandle {
-	let rx = TcpSocketBuffer::new(Vec::from([0; 4096]));
-	let tx = TcpSocketBuffer::new(Vec::from([0; 4096]));
+	// The send buffer can't be skipped: data must be kept until it is acknowledged, which may
+	// be long after the table buffer has been freed. smoltcp builds packets directly in the
+	// virtqueue buffers, so there is no other copy.
+	let rx = TcpSocketBuffer::new(vec![0; BUFFER_SIZE]);
+	let tx = TcpSocketBuffer::new(vec![0; BUFFER_SIZE]);
 	let mut sock = TcpSocket::new(rx, tx);
 	f(&mut sock);
 	iface.add_socket(sock)
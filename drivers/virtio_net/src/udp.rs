@@ -7,10 +7,11 @@ use smoltcp::{
 
 pub struct UdpSocket {
 	handle: SocketHandle,
+	iface: usize,
 }
 
 impl UdpSocket {
-	pub fn new(iface: &mut Interface<impl for<'d> Device<'d>>) -> Self {
+	pub fn new(ifaces: &mut [Interface<impl for<'d> Device<'d>>], iface: usize) -> Self {
 		let rx = UdpSocketBuffer::new(
 			Vec::from([UdpPacketMetadata::EMPTY; 5]),
 			Vec::from([0; 1024]),
@@ -20,11 +21,11 @@ impl UdpSocket {
 			Vec::from([0; 1024]),
 		);
 		let sock = socket::UdpSocket::new(rx, tx);
-		let handle = iface.add_socket(sock);
-		Self { handle }
+		let handle = ifaces[iface].add_socket(sock);
+		Self { handle, iface }
 	}
 
-	pub fn close(self, iface: &mut Interface<impl for<'d> Device<'d>>) {
-		iface.remove_socket(self.handle);
+	pub fn close(self, ifaces: &mut [Interface<impl for<'d> Device<'d>>]) {
+		ifaces[self.iface].remove_socket(self.handle);
 	}
 }
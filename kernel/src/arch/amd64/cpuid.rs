@@ -66,7 +66,9 @@ impl Features {
 
 	// List stolen from https://sandpile.org/x86/cpuid.htm
 	flag!(basic osxsave = 0x1 | ecx[26]);
+	flag!(basic rdrand = 0x1 | ecx[30]);
 	flag!(basic fsgsbase = 0x7 | ebx[0]);
+	flag!(basic rdseed = 0x7 | ebx[18]);
 }
 
 struct Cpuid {
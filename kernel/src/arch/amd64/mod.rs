@@ -7,6 +7,7 @@ pub mod idt;
 mod float;
 pub mod msr;
 mod multiboot;
+pub mod random;
 pub mod scheduler;
 pub mod sync;
 mod syscall;
@@ -196,6 +197,7 @@ pub unsafe fn init() {
 
 		let features = cpuid::Features::new();
 		cpuid::try_enable_features(&features);
+		random::init(&features);
 
 		r#virtual::init();
 
@@ -207,6 +209,7 @@ extern "C" fn handle_timer() -> ! {
 	debug!("Timer interrupt!");
 	debug_assert!(!interrupts_enabled());
 	apic::local_apic::get().eoi.set(0);
+	crate::random::add_jitter();
 	unsafe { syscall::save_current_thread_state() };
 	cpuid::mark_task_switch();
 	// SAFETY: we just saved the thread's state.
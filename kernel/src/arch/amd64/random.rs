@@ -0,0 +1,59 @@
+//! Hardware sources of entropy.
+
+use super::cpuid::Features;
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Intel recommends retrying RDRAND up to 10 times before giving up.
+const RETRIES: usize = 10;
+
+static HAS_RDRAND: AtomicBool = AtomicBool::new(false);
+static HAS_RDSEED: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn init(features: &Features) {
+	HAS_RDRAND.store(features.rdrand(), Ordering::Relaxed);
+	HAS_RDSEED.store(features.rdseed(), Ordering::Relaxed);
+}
+
+macro_rules! rd {
+	($fn:ident, $insn:literal, $flag:ident) => {
+		/// Get a random value from the CPU's hardware generator, if it has one.
+		pub fn $fn() -> Option<u64> {
+			if !$flag.load(Ordering::Relaxed) {
+				return None;
+			}
+			for _ in 0..RETRIES {
+				let (value, ok): (u64, u8);
+				// SAFETY: the instruction is supported as reported by CPUID.
+				unsafe {
+					asm!(
+						concat!($insn, " {}"),
+						"setc {}",
+						out(reg) value,
+						out(reg_byte) ok,
+						options(nomem, nostack),
+					);
+				}
+				if ok != 0 {
+					return Some(value);
+				}
+			}
+			None
+		}
+	};
+}
+
+rd!(rdrand, "rdrand", HAS_RDRAND);
+rd!(rdseed, "rdseed", HAS_RDSEED);
+
+/// Get the value of the CPU's timestamp counter.
+///
+/// This is not a source of randomness by itself, but the exact moment at which events such
+/// as interrupts occur is hard to predict.
+pub fn cycle_counter() -> u64 {
+	let (high, low): (u32, u32);
+	unsafe {
+		asm!("rdtsc", out("edx") high, out("eax") low, options(nomem, nostack, preserves_flags));
+	}
+	u64::from(high) << 32 | u64::from(low)
+}
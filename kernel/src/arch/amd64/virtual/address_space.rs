@@ -125,6 +125,28 @@ impl AddressSpace {
 		Ok(())
 	}
 
+	/// Get the physical address a userspace address is mapped to.
+	///
+	/// Returns `None` if the address is not mapped or if it is mapped with a huge page, as
+	/// userspace mappings only use 4 KiB pages.
+	pub fn get_physical_address(&self, address: NonNull<()>) -> Option<u64> {
+		let address = address.as_ptr() as u64;
+		if address & 0xffff_8000_0000_0000 != 0 {
+			return None;
+		}
+		// SAFETY: the table is valid as long as this address space exists.
+		let mut table = unsafe { self.table() };
+		for level in (1..4).rev() {
+			let e = common::get_entry(table, address, level, 0)?;
+			if e.is_leaf() {
+				return None;
+			}
+			table = e.as_table()?;
+		}
+		let e = common::get_entry(table, address, 0, 0)?;
+		Some(e.page()? | (address & u64::try_from(Page::MASK).unwrap()))
+	}
+
 	pub unsafe fn activate(&self) {
 		unsafe {
 			asm!("mov cr3, {0}", in(reg) self.cr3);
@@ -52,7 +52,6 @@ impl Entry {
 		self.is_present() && self.0 & Self::GLOBAL > 0
 	}
 
-	#[allow(dead_code)]
 	pub fn page(&self) -> Option<u64> {
 		self.is_leaf().then(|| self.0 & !0xfff)
 	}
@@ -195,6 +194,16 @@ pub fn get_entry_mut(
 	}
 }
 
+pub fn get_entry(table: &[Entry; 512], address: u64, level: u8, depth: u8) -> Option<&Entry> {
+	let offt = usize::try_from((address >> (12 + u64::from(level + depth) * 9)) & 0x1ff).unwrap();
+	let entry = &table[offt];
+	if depth == 0 {
+		Some(entry)
+	} else {
+		get_entry(entry.as_table()?, address, level, depth - 1)
+	}
+}
+
 pub fn get_current<'a>() -> &'a mut [Entry; 512] {
 	unsafe {
 		let phys: u64;
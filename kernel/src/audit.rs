@@ -0,0 +1,376 @@
+//! Audit log of object operations.
+//!
+//! Opening, creating, destroying and sharing objects and spawning processes can be recorded in a
+//! bounded ring, which is exposed as `system/audit`. If the ring is full the oldest entry is
+//! discarded. Reading the log returns one line per entry and removes the entries that were read:
+//!
+//! ```text
+//! <time> <process> <operation> <handle> <path> <result>
+//! ```
+//!
+//! Nothing is recorded by default. The operations to record are set with the `audit/ops`
+//! property, e.g. `open create destroy`, and `audit/process` limits recording to a single process.
+//! These can only be changed through `system/audit_filter`, so processes that may read the log
+//! can't stop their own operations from being recorded.
+
+use crate::object_table::{Error, Handle, Object, Ticket, TinySlice};
+use crate::scheduler::process::Process;
+use crate::sync::SpinLock;
+use crate::time::Monotonic;
+use crate::util::ByteStr;
+use alloc::{
+	boxed::Box,
+	string::{String, ToString},
+	sync::Arc,
+	vec::Vec,
+};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Paths longer than this are truncated.
+const MAX_PATH_LEN: usize = 128;
+
+/// The operations that are recorded.
+static OPERATIONS: AtomicU8 = AtomicU8::new(0);
+/// The process whose operations are recorded. [`ANY_PROCESS`] records all processes.
+static PROCESS: AtomicU64 = AtomicU64::new(ANY_PROCESS);
+const ANY_PROCESS: u64 = u64::MAX;
+
+static LOG: SpinLock<Ring> = SpinLock::new(Ring {
+	push: 0,
+	pop: 0,
+	lost: 0,
+	data: [const { None }; 128],
+});
+
+#[derive(Clone, Copy)]
+pub enum Operation {
+	Open = 1 << 0,
+	Create = 1 << 1,
+	Destroy = 1 << 2,
+	Share = 1 << 3,
+	Spawn = 1 << 4,
+}
+
+impl Operation {
+	const ALL: [Self; 5] = [
+		Self::Open,
+		Self::Create,
+		Self::Destroy,
+		Self::Share,
+		Self::Spawn,
+	];
+
+	fn name(self) -> &'static str {
+		match self {
+			Self::Open => "open",
+			Self::Create => "create",
+			Self::Destroy => "destroy",
+			Self::Share => "share",
+			Self::Spawn => "spawn",
+		}
+	}
+}
+
+/// An operation that is in progress.
+///
+/// This is empty if the operation is not recorded.
+#[derive(Default)]
+pub struct Record(Option<Pending>);
+
+struct Pending {
+	time: Monotonic,
+	process: u64,
+	operation: Operation,
+	handle: Option<Handle>,
+	path: Box<[u8]>,
+}
+
+impl Record {
+	/// Add the operation with its result to the log.
+	pub fn finish(self, result: Result<u64, Error>) {
+		if let Some(record) = self.0 {
+			LOG.auto_lock().push(Entry { record, result });
+		}
+	}
+}
+
+/// Begin recording an operation by the current process.
+pub fn begin(operation: Operation, handle: Option<Handle>, path: &[u8]) -> Record {
+	let ops = OPERATIONS.load(Ordering::Relaxed);
+	if ops & operation as u8 == 0 {
+		return Record(None);
+	}
+	// Operations done by the kernel itself are attributed to process 0.
+	let process = Process::current().map_or(0, |p| p.id());
+	if !is_recorded(ops, PROCESS.load(Ordering::Relaxed), operation, process) {
+		return Record(None);
+	}
+	Record(Some(Pending {
+		time: Monotonic::now(),
+		process,
+		operation,
+		handle,
+		path: path[..path.len().min(MAX_PATH_LEN)].into(),
+	}))
+}
+
+/// Whether an operation passes the filters.
+fn is_recorded(ops: u8, filter: u64, operation: Operation, process: u64) -> bool {
+	ops & operation as u8 != 0 && (filter == ANY_PROCESS || filter == process)
+}
+
+/// Parse a space-separated list of operations.
+fn parse_operations(s: &[u8]) -> Option<u8> {
+	s.split(|c| *c == b' ')
+		.filter(|s| !s.is_empty())
+		.try_fold(0, |ops, s| {
+			Operation::ALL
+				.into_iter()
+				.find(|o| o.name().as_bytes() == s)
+				.map(|o| ops | o as u8)
+		})
+}
+
+/// Parse the ID of a process. Empty means any process.
+fn parse_process(s: &[u8]) -> Option<u64> {
+	match s {
+		b"" => Some(ANY_PROCESS),
+		s => core::str::from_utf8(s)
+			.ok()
+			.and_then(|s| s.parse().ok())
+			.filter(|p| *p != ANY_PROCESS),
+	}
+}
+
+/// Record an operation that already completed.
+pub fn record(
+	operation: Operation,
+	handle: Option<Handle>,
+	path: &[u8],
+	result: Result<u64, Error>,
+) {
+	begin(operation, handle, path).finish(result)
+}
+
+struct Entry {
+	record: Pending,
+	result: Result<u64, Error>,
+}
+
+impl Entry {
+	fn format(&self, out: &mut String) {
+		let r = &self.record;
+		let _ = write!(
+			out,
+			"{} {} {} ",
+			r.time.as_nanos(),
+			r.process,
+			r.operation.name()
+		);
+		let _ = match r.handle {
+			Some(h) => write!(out, "{}", h),
+			None => write!(out, "-"),
+		};
+		let _ = write!(out, " {:?} ", ByteStr::new(&r.path));
+		let _ = match &self.result {
+			Ok(v) => writeln!(out, "ok {}", v),
+			Err(e) => writeln!(out, "{:?}", e),
+		};
+	}
+}
+
+struct Ring {
+	push: u8,
+	pop: u8,
+	/// The amount of entries that have been discarded.
+	lost: u64,
+	data: [Option<Entry>; 128],
+}
+
+impl Ring {
+	fn push(&mut self, entry: Entry) {
+		if self.push ^ 128 == self.pop {
+			self.pop = self.pop.wrapping_add(1);
+			self.lost += 1;
+		}
+		self.data[usize::from(self.push & 0x7f)] = Some(entry);
+		self.push = self.push.wrapping_add(1);
+	}
+
+	fn get(&self, i: u8) -> Option<&Entry> {
+		let i = self.pop.wrapping_add(i);
+		(i != self.push)
+			.then(|| self.data[usize::from(i & 0x7f)].as_ref())
+			.flatten()
+	}
+
+	fn remove(&mut self, count: u8) {
+		for _ in 0..count {
+			self.data[usize::from(self.pop & 0x7f)] = None;
+			self.pop = self.pop.wrapping_add(1);
+		}
+	}
+}
+
+struct AuditLog;
+
+impl Object for AuditLog {
+	/// Read as many whole entries as fit. Entries that are too long on their own are truncated.
+	fn read(self: Arc<Self>, length: usize, peek: bool) -> Ticket<Box<[u8]>> {
+		let mut log = LOG.auto_lock();
+		let (mut out, mut line) = (String::new(), String::new());
+		let mut count = 0;
+		while let Some(e) = log.get(count) {
+			line.clear();
+			e.format(&mut line);
+			if out.len() + line.len() > length {
+				if count == 0 {
+					out.push_str(&line);
+					out.truncate(length);
+					count += 1;
+				}
+				break;
+			}
+			out.push_str(&line);
+			count += 1;
+		}
+		if !peek {
+			log.remove(count);
+		}
+		Ticket::new_complete(Ok(out.into_bytes().into()))
+	}
+
+	fn get_meta(self: Arc<Self>, property: &TinySlice<u8>) -> Ticket<Box<[u8]>> {
+		Ticket::new_complete(match property.as_ref() {
+			b"audit/lost" => Ok(LOG.auto_lock().lost.to_string().into_bytes().into()),
+			p => get_filter(p),
+		})
+	}
+}
+
+/// Changes the operations that are recorded.
+struct AuditFilter;
+
+impl Object for AuditFilter {
+	fn get_meta(self: Arc<Self>, property: &TinySlice<u8>) -> Ticket<Box<[u8]>> {
+		Ticket::new_complete(get_filter(property.as_ref()))
+	}
+
+	fn set_meta(self: Arc<Self>, property: &TinySlice<u8>, value: &TinySlice<u8>) -> Ticket<u64> {
+		Ticket::new_complete(match property.as_ref() {
+			// A space-separated list of operations. Empty disables recording.
+			b"audit/ops" => parse_operations(value.as_ref())
+				.map(|ops| {
+					OPERATIONS.store(ops, Ordering::Relaxed);
+					0
+				})
+				.ok_or(Error::InvalidData),
+			// The ID of a process. Empty records all processes.
+			b"audit/process" => parse_process(value.as_ref())
+				.map(|p| {
+					PROCESS.store(p, Ordering::Relaxed);
+					0
+				})
+				.ok_or(Error::InvalidData),
+			_ => Err(Error::DoesNotExist),
+		})
+	}
+}
+
+fn get_filter(property: &[u8]) -> Result<Box<[u8]>, Error> {
+	match property {
+		b"audit/ops" => {
+			let ops = OPERATIONS.load(Ordering::Relaxed);
+			let ops = Operation::ALL
+				.into_iter()
+				.filter(|o| ops & *o as u8 != 0)
+				.map(|o| o.name())
+				.collect::<Vec<_>>();
+			Ok(ops.join(" ").into_bytes().into())
+		}
+		b"audit/process" => match PROCESS.load(Ordering::Relaxed) {
+			ANY_PROCESS => Ok([].into()),
+			p => Ok(p.to_string().into_bytes().into()),
+		},
+		_ => Err(Error::DoesNotExist),
+	}
+}
+
+/// Open the audit log.
+pub fn open() -> Arc<dyn Object> {
+	Arc::new(AuditLog)
+}
+
+/// Open the object that changes which operations are recorded.
+pub fn open_filter() -> Arc<dyn Object> {
+	Arc::new(AuditFilter)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn entry(process: u64, path: &[u8], result: Result<u64, Error>) -> Entry {
+		Entry {
+			record: Pending {
+				time: Monotonic::from_nanos(1234),
+				process,
+				operation: Operation::Open,
+				handle: Some(3),
+				path: path.into(),
+			},
+			result,
+		}
+	}
+
+	#[test]
+	fn ring_full() {
+		let mut ring = Ring {
+			push: 0,
+			pop: 0,
+			lost: 0,
+			data: [const { None }; 128],
+		};
+		for i in 0..130 {
+			ring.push(entry(i, b"", Ok(0)));
+		}
+		assert_eq!(ring.lost, 2);
+		assert_eq!(ring.get(0).unwrap().record.process, 2);
+		assert_eq!(ring.get(127).unwrap().record.process, 129);
+		assert!(ring.get(128).is_none());
+		ring.remove(127);
+		assert_eq!(ring.get(0).unwrap().record.process, 129);
+		assert!(ring.get(1).is_none());
+	}
+
+	#[test]
+	fn filter() {
+		let ops = parse_operations(b"open  share").unwrap();
+		assert_eq!(ops, Operation::Open as u8 | Operation::Share as u8);
+		assert!(parse_operations(b"open read").is_none());
+		assert_eq!(parse_operations(b""), Some(0));
+
+		assert!(is_recorded(ops, ANY_PROCESS, Operation::Share, 5));
+		assert!(!is_recorded(ops, ANY_PROCESS, Operation::Create, 5));
+		assert!(is_recorded(ops, 5, Operation::Open, 5));
+		assert!(!is_recorded(ops, 5, Operation::Open, 6));
+
+		assert_eq!(parse_process(b""), Some(ANY_PROCESS));
+		assert_eq!(parse_process(b"42"), Some(42));
+		assert!(parse_process(b"-1").is_none());
+		assert!(parse_process(ANY_PROCESS.to_string().as_bytes()).is_none());
+	}
+
+	#[test]
+	fn format() {
+		let mut s = String::new();
+		entry(7, b"drivers/\"fs\"", Ok(9)).format(&mut s);
+		assert_eq!(s, "1234 7 open 3 \"drivers/\\\"fs\\\"\" ok 9\n");
+		s.clear();
+		let mut e = entry(7, b"", Err(Error::DoesNotExist));
+		e.record.handle = None;
+		e.format(&mut s);
+		assert_eq!(s, "1234 7 open - \"\" DoesNotExist\n");
+	}
+}
@@ -54,6 +54,14 @@ impl Object for File {
 		Ticket::new_complete(Ok(self.data[bottom..top].into()))
 	}
 
+	fn read_at(self: Arc<Self>, offset: u64, length: usize) -> Ticket<Box<[u8]>> {
+		let bottom = usize::try_from(offset)
+			.unwrap_or(usize::MAX)
+			.min(self.data.len());
+		let top = bottom.saturating_add(length).min(self.data.len());
+		Ticket::new_complete(Ok(self.data[bottom..top].into()))
+	}
+
 	fn seek(&self, from: SeekFrom) -> Ticket<u64> {
 		let mut pos = None;
 		self.position
@@ -9,11 +9,12 @@ struct SystemTable;
 
 impl Object for SystemTable {
 	fn open(self: Arc<Self>, path: &[u8]) -> Ticket<Arc<dyn Object>> {
-		if path == b"log" {
-			Ticket::new_complete(Ok(Arc::new(SystemLogRef)))
-		} else {
-			Ticket::new_complete(Err(Error::DoesNotExist))
-		}
+		Ticket::new_complete(match path {
+			b"log" => Ok(Arc::new(SystemLogRef)),
+			b"audit" => Ok(crate::audit::open()),
+			b"audit_filter" => Ok(crate::audit::open_filter()),
+			_ => Err(Error::DoesNotExist),
+		})
 	}
 }
 
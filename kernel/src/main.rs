@@ -38,11 +38,13 @@ use core::panic::PanicInfo;
 mod log;
 
 mod arch;
+mod audit;
 mod boot;
 mod driver;
 mod initfs;
 mod memory;
 mod object_table;
+mod random;
 mod scheduler;
 mod sync;
 mod time;
@@ -79,6 +81,7 @@ extern "C" fn post_init(boot_info: usize) -> ! {
 	driver::post_init(&root);
 	scheduler::post_init(&root);
 	log::post_init(&root);
+	random::post_init(&root);
 	let fs = initfs::post_init(boot_info);
 
 	let init = fs.find(b"init").expect("no init has been specified");
@@ -171,6 +171,11 @@ impl AddressSpace {
 		Ok(())
 	}
 
+	/// Get the physical address a userspace address is mapped to.
+	pub fn get_physical_address(&self, address: NonNull<()>) -> Option<u64> {
+		self.mmu_address_space.get_physical_address(address)
+	}
+
 	/// # Safety
 	///
 	/// The memory region may no longer be used after this call.
@@ -0,0 +1,170 @@
+use super::{Error, MemoryObject, Object, SeekFrom, Ticket, TinySlice};
+use alloc::{boxed::Box, sync::Arc};
+use norostb_kernel::object::Operations;
+
+/// A wrapper around an object that only forwards the allowed operations.
+///
+/// If a prefix is set only paths that are equal to or under the prefix can be accessed. Paths are
+/// not rewritten. Objects that are opened or created through the wrapper are restricted in the
+/// same way, except that any path below them can be accessed.
+pub struct Attenuate {
+	object: Arc<dyn Object>,
+	allow: Operations,
+	/// If `None`, paths are not restricted.
+	prefix: Option<Box<[u8]>>,
+}
+
+impl Attenuate {
+	/// Create a new wrapper. An empty prefix does not restrict paths.
+	pub fn new(object: Arc<dyn Object>, allow: Operations, prefix: &[u8]) -> Arc<Self> {
+		let prefix = match prefix {
+			[] => None,
+			p => Some(p.strip_suffix(b"/").unwrap_or(p).into()),
+		};
+		Arc::new(Self {
+			object,
+			allow,
+			prefix,
+		})
+	}
+
+	fn is_allowed(&self, op: Operations) -> bool {
+		self.allow.contains(op)
+	}
+
+	fn is_path_allowed(&self, path: &[u8]) -> bool {
+		let Some(prefix) = self.prefix.as_deref() else { return true };
+		// Servers may interpret these, which would allow escaping the prefix.
+		if path.split(|c| *c == b'/').any(|c| c == b"..") {
+			return false;
+		}
+		prefix.is_empty()
+			|| path
+				.strip_prefix(prefix)
+				.map_or(false, |p| p.is_empty() || p[0] == b'/')
+	}
+
+	/// Restrict an object that was opened or created through this wrapper.
+	fn wrap(&self, ticket: Ticket<Arc<dyn Object>>) -> Ticket<Arc<dyn Object>> {
+		let allow = self.allow;
+		// Keep preventing escapes with "..".
+		let prefix = self.prefix.as_ref().map(|_| Box::default());
+		ticket.map(move |object| {
+			Arc::new(Self {
+				object,
+				allow,
+				prefix,
+			})
+		})
+	}
+}
+
+impl Object for Attenuate {
+	fn memory_object(self: Arc<Self>) -> Option<Arc<dyn MemoryObject>> {
+		self.is_allowed(Operations::MAP)
+			.then(|| self.object.clone().memory_object())
+			.flatten()
+	}
+
+	fn open(self: Arc<Self>, path: &[u8]) -> Ticket<Arc<dyn Object>> {
+		if !self.is_allowed(Operations::OPEN) {
+			denied()
+		} else if !self.is_path_allowed(path) {
+			not_found()
+		} else {
+			self.wrap(self.object.clone().open(path))
+		}
+	}
+
+	fn create(self: Arc<Self>, path: &[u8]) -> Ticket<Arc<dyn Object>> {
+		if !self.is_allowed(Operations::CREATE) {
+			denied()
+		} else if !self.is_path_allowed(path) {
+			not_found()
+		} else {
+			self.wrap(self.object.clone().create(path))
+		}
+	}
+
+	fn read(self: Arc<Self>, length: usize, peek: bool) -> Ticket<Box<[u8]>> {
+		if !self.is_allowed(Operations::READ) {
+			return denied();
+		}
+		self.object.clone().read(length, peek)
+	}
+
+	fn write(self: Arc<Self>, data: &[u8]) -> Ticket<u64> {
+		if !self.is_allowed(Operations::WRITE) {
+			return denied();
+		}
+		self.object.clone().write(data)
+	}
+
+	fn write_vectored(self: Arc<Self>, data: &[&[u8]]) -> Ticket<u64> {
+		if !self.is_allowed(Operations::WRITE) {
+			return denied();
+		}
+		self.object.clone().write_vectored(data)
+	}
+
+	fn read_at(self: Arc<Self>, offset: u64, length: usize) -> Ticket<Box<[u8]>> {
+		if !self.is_allowed(Operations::READ) {
+			return denied();
+		}
+		self.object.clone().read_at(offset, length)
+	}
+
+	fn write_at(self: Arc<Self>, offset: u64, data: &[u8]) -> Ticket<u64> {
+		if !self.is_allowed(Operations::WRITE) {
+			return denied();
+		}
+		self.object.clone().write_at(offset, data)
+	}
+
+	fn seek(&self, from: SeekFrom) -> Ticket<u64> {
+		if !self.is_allowed(Operations::SEEK) {
+			return denied();
+		}
+		self.object.seek(from)
+	}
+
+	fn share(&self, object: &Arc<dyn Object>) -> Ticket<u64> {
+		if !self.is_allowed(Operations::SHARE) {
+			return denied();
+		}
+		self.object.share(object)
+	}
+
+	fn destroy(&self, path: &[u8]) -> Ticket<u64> {
+		if !self.is_allowed(Operations::DESTROY) {
+			denied()
+		} else if !self.is_path_allowed(path) {
+			not_found()
+		} else {
+			self.object.destroy(path)
+		}
+	}
+
+	fn get_meta(self: Arc<Self>, property: &TinySlice<u8>) -> Ticket<Box<[u8]>> {
+		if !self.is_allowed(Operations::GET_META) {
+			return denied();
+		}
+		self.object.clone().get_meta(property)
+	}
+
+	fn set_meta(self: Arc<Self>, property: &TinySlice<u8>, value: &TinySlice<u8>) -> Ticket<u64> {
+		if !self.is_allowed(Operations::SET_META) {
+			return denied();
+		}
+		self.object.clone().set_meta(property, value)
+	}
+}
+
+/// Denied operations fail the same way as unsupported ones.
+fn denied<T>() -> Ticket<T> {
+	Ticket::new_complete(Err(Error::InvalidOperation))
+}
+
+fn not_found<T>() -> Ticket<T> {
+	Ticket::new_complete(Err(Error::DoesNotExist))
+}
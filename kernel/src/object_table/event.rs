@@ -0,0 +1,76 @@
+//! # Events
+//!
+//! An event is a counter that can be used to signal other processes.
+//!
+//! Writing a little-endian `u64` adds it to the counter. Reading blocks until the counter is not
+//! zero, then returns it as a little-endian `u64` and resets it to zero. Peeking returns the
+//! counter immediately without resetting it.
+
+use super::{Error, Object, Ticket, TicketWaker};
+use crate::sync::Mutex;
+use alloc::{
+	boxed::Box,
+	collections::VecDeque,
+	sync::{Arc, Weak},
+};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The ID of the next read, used to find reads that are cancelled.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Create a new event with a counter of zero.
+pub fn new() -> Arc<dyn Object> {
+	Arc::new(Event(Default::default()))
+}
+
+struct Event(Mutex<EventInner>);
+
+#[derive(Default)]
+struct EventInner {
+	counter: u64,
+	/// Pending reads along with their ID and the length of the read.
+	wake: VecDeque<(u64, usize, TicketWaker<Box<[u8]>>)>,
+}
+
+impl Object for Event {
+	fn read(self: Arc<Self>, length: usize, peek: bool) -> Ticket<Box<[u8]>> {
+		let mut e = self.0.lock();
+		if peek {
+			return Ticket::new_complete(Ok(encode(e.counter, length)));
+		}
+		if e.counter > 0 {
+			let n = core::mem::take(&mut e.counter);
+			return Ticket::new_complete(Ok(encode(n, length)));
+		}
+		let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+		let (ticket, waker) = Ticket::new();
+		let event = Arc::downgrade(&self);
+		waker.on_cancel(move || {
+			if let Some(event) = Weak::upgrade(&event) {
+				event.0.lock().wake.retain(|(i, _, _)| *i != id);
+			}
+		});
+		e.wake.push_back((id, length, waker));
+		ticket
+	}
+
+	fn write(self: Arc<Self>, data: &[u8]) -> Ticket<u64> {
+		let Ok(n) = <[u8; 8]>::try_from(data) else {
+			return Ticket::new_complete(Err(Error::InvalidData));
+		};
+		let mut e = self.0.lock();
+		e.counter = e.counter.saturating_add(u64::from_le_bytes(n));
+		if e.counter > 0 {
+			if let Some((_, length, w)) = e.wake.pop_front() {
+				let n = core::mem::take(&mut e.counter);
+				w.complete(Ok(encode(n, length)));
+			}
+		}
+		Ticket::new_complete(Ok(data.len() as _))
+	}
+}
+
+/// Encode the counter, truncated to the length of the read.
+fn encode(n: u64, length: usize) -> Box<[u8]> {
+	n.to_le_bytes()[..length.min(8)].into()
+}
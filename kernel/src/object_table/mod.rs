@@ -5,8 +5,12 @@
 //! Objects can be searched/filtered with tags. Individual objects are addressed by unique
 //! integer IDs.
 
+pub mod event;
 pub mod pipe;
+pub mod pty;
+pub mod timer;
 
+mod attenuate;
 mod object;
 mod query;
 mod root;
@@ -22,6 +26,7 @@ pub use norostb_kernel::{
 	syscall::Handle,
 };
 
+pub use attenuate::Attenuate;
 pub use object::*;
 pub use query::*;
 pub use root::Root;
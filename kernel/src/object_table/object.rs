@@ -29,6 +29,27 @@ pub trait Object {
 		not_implemented()
 	}
 
+	/// Write data from multiple buffers as if they were a single buffer.
+	fn write_vectored(self: Arc<Self>, data: &[&[u8]]) -> Ticket<u64> {
+		match data {
+			[] => self.write(&[]),
+			[d] => self.write(d),
+			d => self.write(&d.concat()),
+		}
+	}
+
+	/// Read data at the given offset without using or advancing the seek head.
+	fn read_at(self: Arc<Self>, offset: u64, length: usize) -> Ticket<Box<[u8]>> {
+		let _ = (offset, length);
+		not_implemented()
+	}
+
+	/// Write data at the given offset without using or advancing the seek head.
+	fn write_at(self: Arc<Self>, offset: u64, data: &[u8]) -> Ticket<u64> {
+		let _ = (offset, data);
+		not_implemented()
+	}
+
 	fn seek(&self, _from: norostb_kernel::io::SeekFrom) -> Ticket<u64> {
 		not_implemented()
 	}
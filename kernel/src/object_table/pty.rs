@@ -0,0 +1,370 @@
+//! Pseudo-terminals.
+//!
+//! A PTY consists of a master and a slave object. Data written to the master is passed through a
+//! line discipline and can then be read from the slave. Data written to the slave can be read
+//! from the master.
+//!
+//! Both objects have the following properties:
+//!
+//! * `pty/size`: the window size as 2 little-endian `u16`s: rows and columns.
+//! * `pty/mode`: either `cooked` or `raw`. In cooked mode input is buffered per line and can be
+//!   edited, `^C` raises an interrupt and newlines written to the slave are converted to `\r\n`.
+//! * `pty/echo`: `1` if input is echoed back to the master, `0` otherwise.
+//!
+//! Getting `pty/interrupt` blocks until the next interrupt. Interrupts are not delivered as
+//! signals: no process is stopped when `^C` is pressed. Instead, the foreground process is
+//! expected to wait on `pty/interrupt` alongside any blocking read from the slave and to cancel
+//! the read once the interrupt completes. Processes that don't wait on it can't be interrupted.
+
+use super::{Error, Object, Ticket, TicketWaker, TinySlice};
+use crate::sync::Mutex;
+use alloc::{
+	boxed::Box,
+	collections::VecDeque,
+	sync::{Arc, Weak},
+	vec::Vec,
+};
+use core::{
+	mem,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+// Same as pipe.
+const MAX_SIZE: usize = 1 << 16;
+
+const INTERRUPT: u8 = 0x03;
+const EOF: u8 = 0x04;
+const BACKSPACE: u8 = 0x08;
+const KILL_LINE: u8 = 0x15;
+const DELETE: u8 = 0x7f;
+
+/// The ID of the next blocking request, used to find requests that are cancelled.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Create a new PTY. The first object is the master, the second the slave.
+pub fn new() -> [Arc<dyn Object>; 2] {
+	let inner = Arc::new(Mutex::new(PtyInner {
+		input: Default::default(),
+		line: Default::default(),
+		output: Default::default(),
+		wake_slave: Default::default(),
+		wake_master: Default::default(),
+		wake_interrupt: Default::default(),
+		finished: Default::default(),
+		rows: 24,
+		cols: 80,
+		raw: false,
+		echo: true,
+		eof: 0,
+		flags: 0,
+	}));
+	[Arc::new(Master(inner.clone())), Arc::new(Slave(inner))]
+}
+
+struct PtyInner {
+	/// Input that is ready to be read by the slave.
+	input: VecDeque<u8>,
+	/// The line that is being edited in cooked mode.
+	line: Vec<u8>,
+	/// Output that is ready to be read by the master.
+	output: VecDeque<u8>,
+	/// Pending reads along with their ID and the length of the read.
+	wake_slave: VecDeque<(u64, usize, TicketWaker<Box<[u8]>>)>,
+	wake_master: VecDeque<(u64, usize, TicketWaker<Box<[u8]>>)>,
+	/// Pending `pty/interrupt` requests along with their ID.
+	wake_interrupt: Vec<(u64, TicketWaker<Box<[u8]>>)>,
+	/// Tickets to complete once the lock is released.
+	finished: Vec<(TicketWaker<Box<[u8]>>, Result<Box<[u8]>, Error>)>,
+	rows: u16,
+	cols: u16,
+	raw: bool,
+	echo: bool,
+	/// The amount of pending end-of-file markers.
+	eof: usize,
+	flags: u8,
+}
+
+const MASTER_CLOSED: u8 = 1;
+const SLAVE_CLOSED: u8 = 2;
+
+struct Master(Arc<Mutex<PtyInner>>);
+
+struct Slave(Arc<Mutex<PtyInner>>);
+
+/// Lock the PTY and complete any tickets finished by `f` after unlocking.
+fn with<R>(pty: &Mutex<PtyInner>, f: impl FnOnce(&mut PtyInner) -> R) -> R {
+	let mut pty = pty.lock();
+	let r = f(&mut pty);
+	let finished = mem::take(&mut pty.finished);
+	drop(pty);
+	finished.into_iter().for_each(|(w, r)| w.complete(r));
+	r
+}
+
+/// Create a ticket for a blocking request.
+///
+/// If the ticket is cancelled the request is removed from the PTY, so it doesn't consume data
+/// or linger until the next interrupt.
+fn wait(pty: &Arc<Mutex<PtyInner>>) -> (u64, Ticket<Box<[u8]>>, TicketWaker<Box<[u8]>>) {
+	let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+	let (ticket, waker) = Ticket::new();
+	let pty = Arc::downgrade(pty);
+	waker.on_cancel(move || {
+		if let Some(pty) = Weak::upgrade(&pty) {
+			let mut pty = pty.lock();
+			pty.wake_slave.retain(|(i, ..)| *i != id);
+			pty.wake_master.retain(|(i, ..)| *i != id);
+			pty.wake_interrupt.retain(|(i, _)| *i != id);
+		}
+	});
+	(id, ticket, waker)
+}
+
+fn get_meta(pty: &Arc<Mutex<PtyInner>>, property: &[u8]) -> Ticket<Box<[u8]>> {
+	if property == b"pty/interrupt" {
+		let (id, ticket, waker) = wait(pty);
+		pty.lock().wake_interrupt.push((id, waker));
+		return ticket;
+	}
+	pty.lock().get_meta(property)
+}
+
+impl PtyInner {
+	/// Pass input from the master through the line discipline.
+	fn push_input(&mut self, data: &[u8]) {
+		if self.raw {
+			self.input.extend(data);
+			self.echo(data);
+			return;
+		}
+		for &c in data {
+			match c {
+				INTERRUPT => {
+					self.line.clear();
+					self.input.clear();
+					self.echo(b"^C\r\n");
+					let w = self
+						.wake_interrupt
+						.drain(..)
+						.map(|(_, w)| (w, Ok([].into())));
+					self.finished.extend(w);
+				}
+				EOF if self.line.is_empty() => self.eof += 1,
+				EOF => self.input.extend(self.line.drain(..)),
+				BACKSPACE | DELETE => {
+					if self.line.pop().is_some() {
+						self.echo(b"\x08 \x08");
+					}
+				}
+				KILL_LINE => {
+					for _ in 0..self.line.len() {
+						self.echo(b"\x08 \x08");
+					}
+					self.line.clear();
+				}
+				b'\r' | b'\n' => {
+					self.line.push(b'\n');
+					self.input.extend(self.line.drain(..));
+					self.echo(b"\r\n");
+				}
+				c if self.line.len() < MAX_SIZE => {
+					self.line.push(c);
+					self.echo(&[c]);
+				}
+				// Drop input if the line is too long.
+				_ => {}
+			}
+		}
+		self.wake_slave();
+	}
+
+	/// Push output from the slave.
+	fn push_output(&mut self, data: &[u8]) {
+		if self.raw {
+			self.output.extend(data);
+		} else {
+			for &c in data {
+				if c == b'\n' {
+					self.output.push_back(b'\r');
+				}
+				self.output.push_back(c);
+			}
+		}
+		self.wake_master();
+	}
+
+	fn echo(&mut self, data: &[u8]) {
+		if self.echo {
+			self.output.extend(data);
+			self.wake_master();
+		}
+	}
+
+	fn wake_slave(&mut self) {
+		while !self.input.is_empty() || self.eof > 0 {
+			let Some((_, len, w)) = self.wake_slave.pop_front() else { break };
+			let data = self.read_input(len);
+			self.finished.push((w, Ok(data)));
+		}
+	}
+
+	fn wake_master(&mut self) {
+		while !self.output.is_empty() {
+			let Some((_, len, w)) = self.wake_master.pop_front() else { break };
+			let data = take(&mut self.output, len);
+			self.finished.push((w, Ok(data)));
+		}
+	}
+
+	fn read_input(&mut self, length: usize) -> Box<[u8]> {
+		if self.input.is_empty() {
+			// An end-of-file marker results in a single empty read.
+			self.eof -= 1;
+			return [].into();
+		}
+		take(&mut self.input, length)
+	}
+
+	fn get_meta(&self, property: &[u8]) -> Ticket<Box<[u8]>> {
+		Ticket::new_complete(match property {
+			b"pty/size" => {
+				let mut b = [0; 4];
+				b[..2].copy_from_slice(&self.rows.to_le_bytes());
+				b[2..].copy_from_slice(&self.cols.to_le_bytes());
+				Ok(b.into())
+			}
+			b"pty/mode" => {
+				let mode: &[u8] = if self.raw { b"raw" } else { b"cooked" };
+				Ok(mode.into())
+			}
+			b"pty/echo" => Ok([u8::from(self.echo)].into()),
+			_ => Err(Error::DoesNotExist),
+		})
+	}
+
+	fn set_meta(&mut self, property: &[u8], value: &[u8]) -> Ticket<u64> {
+		Ticket::new_complete(match (property, value) {
+			(b"pty/size", &[r0, r1, c0, c1]) => {
+				self.rows = u16::from_le_bytes([r0, r1]);
+				self.cols = u16::from_le_bytes([c0, c1]);
+				Ok(0)
+			}
+			(b"pty/mode", b"raw") => {
+				self.raw = true;
+				// Don't lose whatever is being edited.
+				self.input.extend(self.line.drain(..));
+				self.wake_slave();
+				Ok(0)
+			}
+			(b"pty/mode", b"cooked") => {
+				self.raw = false;
+				Ok(0)
+			}
+			(b"pty/echo", &[e @ (0 | 1)]) => {
+				self.echo = e != 0;
+				Ok(0)
+			}
+			(b"pty/size" | b"pty/mode" | b"pty/echo", _) => Err(Error::InvalidData),
+			_ => Err(Error::DoesNotExist),
+		})
+	}
+}
+
+fn take(buf: &mut VecDeque<u8>, length: usize) -> Box<[u8]> {
+	let len = length.min(buf.len());
+	buf.drain(..len).collect()
+}
+
+impl Object for Master {
+	fn read(self: Arc<Self>, length: usize, _peek: bool) -> Ticket<Box<[u8]>> {
+		let mut pty = self.0.lock();
+		Ticket::new_complete(if !pty.output.is_empty() {
+			Ok(take(&mut pty.output, length))
+		} else if pty.flags & SLAVE_CLOSED != 0 {
+			Ok([].into())
+		} else {
+			let (id, t, w) = wait(&self.0);
+			pty.wake_master.push_back((id, length, w));
+			return t;
+		})
+	}
+
+	fn write(self: Arc<Self>, data: &[u8]) -> Ticket<u64> {
+		with(&self.0, |pty| {
+			if pty.flags & SLAVE_CLOSED != 0 {
+				return Ticket::new_complete(Ok(0));
+			}
+			let len = data.len().min(MAX_SIZE.saturating_sub(pty.input.len()));
+			pty.push_input(&data[..len]);
+			Ticket::new_complete(Ok(len as _))
+		})
+	}
+
+	fn get_meta(self: Arc<Self>, property: &TinySlice<u8>) -> Ticket<Box<[u8]>> {
+		get_meta(&self.0, property)
+	}
+
+	fn set_meta(self: Arc<Self>, property: &TinySlice<u8>, value: &TinySlice<u8>) -> Ticket<u64> {
+		with(&self.0, |pty| pty.set_meta(property, value))
+	}
+}
+
+impl Object for Slave {
+	fn read(self: Arc<Self>, length: usize, _peek: bool) -> Ticket<Box<[u8]>> {
+		let mut pty = self.0.lock();
+		Ticket::new_complete(if !pty.input.is_empty() || pty.eof > 0 {
+			Ok(pty.read_input(length))
+		} else if pty.flags & MASTER_CLOSED != 0 {
+			Ok([].into())
+		} else {
+			let (id, t, w) = wait(&self.0);
+			pty.wake_slave.push_back((id, length, w));
+			return t;
+		})
+	}
+
+	fn write(self: Arc<Self>, data: &[u8]) -> Ticket<u64> {
+		with(&self.0, |pty| {
+			if pty.flags & MASTER_CLOSED != 0 {
+				return Ticket::new_complete(Ok(0));
+			}
+			let len = data.len().min(MAX_SIZE.saturating_sub(pty.output.len()));
+			pty.push_output(&data[..len]);
+			Ticket::new_complete(Ok(len as _))
+		})
+	}
+
+	fn get_meta(self: Arc<Self>, property: &TinySlice<u8>) -> Ticket<Box<[u8]>> {
+		get_meta(&self.0, property)
+	}
+
+	fn set_meta(self: Arc<Self>, property: &TinySlice<u8>, value: &TinySlice<u8>) -> Ticket<u64> {
+		with(&self.0, |pty| pty.set_meta(property, value))
+	}
+}
+
+impl Drop for Master {
+	fn drop(&mut self) {
+		with(&self.0, |pty| {
+			pty.flags |= MASTER_CLOSED;
+			let w = pty.wake_slave.drain(..).map(|(_, _, w)| (w, Ok([].into())));
+			pty.finished.extend(w);
+			let w = pty.wake_interrupt.drain(..);
+			pty.finished
+				.extend(w.map(|(_, w)| (w, Err(Error::Cancelled))));
+		})
+	}
+}
+
+impl Drop for Slave {
+	fn drop(&mut self) {
+		with(&self.0, |pty| {
+			pty.flags |= SLAVE_CLOSED;
+			let w = pty
+				.wake_master
+				.drain(..)
+				.map(|(_, _, w)| (w, Ok([].into())));
+			pty.finished.extend(w);
+		})
+	}
+}
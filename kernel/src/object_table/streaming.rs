@@ -10,6 +10,7 @@ use crate::{
 };
 use alloc::{
 	boxed::Box,
+	collections::VecDeque,
 	sync::{Arc, Weak},
 	vec::Vec,
 };
@@ -26,6 +27,8 @@ pub struct StreamingTable {
 	shared: Option<Mutex<Arena<Arc<dyn Object>, ()>>>,
 	/// The queue shared with the server.
 	queue: Mutex<ClientQueue>,
+	/// Cancellations that didn't fit in the queue yet.
+	pending_cancels: Mutex<VecDeque<(Handle, JobId)>>,
 	/// The shared memory containing the queue.
 	queue_mem: Arc<OwnedPageFrames>,
 	/// The memory region with buffers that is being shared with the server.
@@ -72,6 +75,7 @@ impl StreamingTable {
 			jobs: Default::default(),
 			shared: allow_sharing.then(Default::default),
 			queue: Mutex::new(queue),
+			pending_cancels: Default::default(),
 			queue_mem,
 			buffer_mem: unsafe { Buffers::new(buffer_mem.cast(), buffer_mem_size, block_size) },
 			notify_singleton: Arc::new(Notify {
@@ -82,7 +86,7 @@ impl StreamingTable {
 		}))
 	}
 
-	fn submit_job<T, F>(&self, handle: Handle, f: F) -> Ticket<T>
+	fn submit_job<T, F>(self: &Arc<Self>, handle: Handle, f: F) -> Ticket<T>
 	where
 		F: FnOnce(&mut ClientQueue, JobId) -> Request,
 		AnyTicketWaker: From<TicketWaker<T>>,
@@ -90,15 +94,46 @@ impl StreamingTable {
 		let (ticket, ticket_waker) = Ticket::new();
 		self.jobs.lock().insert_with(move |h| {
 			let mut q = self.queue.lock();
-			let r = f(&mut q, JobId::new(h.into_raw().0.try_into().unwrap()));
+			let job_id = JobId::new(h.into_raw().0.try_into().unwrap());
+			let r = f(&mut q, job_id);
 			q.try_enqueue(handle, r)
 				.unwrap_or_else(|e| todo!("{:?}", e));
+			// The job stays around until the server responds to it.
+			let table = Arc::downgrade(self);
+			ticket_waker.on_cancel(move || {
+				if let Some(table) = table.upgrade() {
+					let mut q = table.queue.lock();
+					table.flush_cancels(&mut q);
+					// If the queue is full, send it once the server made room.
+					if q.try_enqueue(handle, Request::Cancel { job_id }).is_err() {
+						table.pending_cancels.lock().push_back((handle, job_id));
+					}
+					drop(q);
+					table.notify_singleton.wake_readers();
+				}
+			});
 			ticket_waker.into()
 		});
 		self.notify_singleton.wake_readers();
 		ticket.into()
 	}
 
+	/// Enqueue cancellations that didn't fit in the queue earlier.
+	///
+	/// Returns `true` if any were enqueued.
+	fn flush_cancels(&self, queue: &mut ClientQueue) -> bool {
+		let mut cancels = self.pending_cancels.lock();
+		let n = cancels.len();
+		while let Some(&(handle, job_id)) = cancels.front() {
+			let r = queue.try_enqueue(handle, Request::Cancel { job_id });
+			if r.is_err() {
+				break;
+			}
+			cancels.pop_front();
+		}
+		cancels.len() != n
+	}
+
 	fn copy_data_from(&self, queue: &mut ClientQueue, data: &[u8]) -> Slice {
 		self.copy_data_from_scatter(queue, &[data])
 	}
@@ -133,14 +168,21 @@ impl StreamingTable {
 		let mut q = self.queue.lock();
 		let mut j = self.jobs.lock();
 		while let Some((job_id, resp)) = q.dequeue() {
+			// The job ID may be reused, so don't cancel a later job by accident.
+			self.pending_cancels
+				.lock()
+				.retain(|(_, id)| id.get() != job_id.get());
 			let job_id = arena::Handle::from_raw(job_id.get() as _, ());
 			let job = j.remove(job_id).unwrap_or_else(|| todo!("invalid job id"));
 			match resp.get() {
 				Ok(v) => match job {
-					AnyTicketWaker::Object(w) => w.complete(Ok(Arc::new(StreamObject {
-						table: Arc::downgrade(self),
-						handle: v as _,
-					}))),
+					AnyTicketWaker::Object(w) => w.complete(match resp.as_object() {
+						Some(h) => self.take_shared(h).ok_or(Error::InvalidObject),
+						None => Ok(Arc::new(StreamObject {
+							table: Arc::downgrade(self),
+							handle: v as _,
+						})),
+					}),
 					AnyTicketWaker::Data(w) => {
 						let s = resp.as_slice().unwrap();
 						let buf = self.buffer_mem.get(s);
@@ -154,6 +196,18 @@ impl StreamingTable {
 				Err(e) => job.complete_err(Error::from(e)),
 			}
 		}
+		let flushed = self.flush_cancels(&mut q);
+		drop(j);
+		drop(q);
+		if flushed {
+			self.notify_singleton.wake_readers();
+		}
+	}
+
+	/// Take an object that was shared with this table.
+	fn take_shared(&self, handle: Handle) -> Option<Arc<dyn Object>> {
+		let h = arena::Handle::from_raw(handle as _, ());
+		self.shared.as_ref().and_then(|s| s.lock().remove(h))
 	}
 
 	fn requests_enqueued(&self) -> u32 {
@@ -169,17 +223,21 @@ impl Object for StreamingTable {
 				table: Arc::downgrade(&self),
 				handle: Handle::MAX,
 			})),
-			&[a, b, c, d] => {
-				let h = arena::Handle::from_raw(Handle::from_le_bytes([a, b, c, d]) as _, ());
-				self.shared
-					.as_ref()
-					.and_then(|s| s.lock().remove(h))
-					.ok_or(Error::DoesNotExist)
-			}
+			&[a, b, c, d] => self
+				.take_shared(Handle::from_le_bytes([a, b, c, d]))
+				.ok_or(Error::DoesNotExist),
 			_ => Err(Error::InvalidData),
 		})
 	}
 
+	/// Share an object so it can be returned to a client with a response.
+	fn share(&self, object: &Arc<dyn Object>) -> Ticket<u64> {
+		Ticket::new_complete(match self.shared.as_ref() {
+			Some(shared) => Ok(shared.lock().insert(object.clone()).into_raw().0 as _),
+			None => Err(Error::InvalidOperation),
+		})
+	}
+
 	fn memory_object(self: Arc<Self>) -> Option<Arc<dyn MemoryObject>> {
 		Some(self)
 	}
@@ -277,6 +335,55 @@ impl Object for StreamObject {
 		})
 	}
 
+	/// Each buffer is passed to the server as a separate slice.
+	fn write_vectored(self: Arc<Self>, data: &[&[u8]]) -> Ticket<u64> {
+		self.with_table(|tbl| {
+			tbl.submit_job(self.handle, |q, job_id| {
+				// The list counts towards the memory limit of the request too.
+				let mut left = (tbl.max_request_mem as usize + 1)
+					.saturating_sub(data.len() * Slice::ENCODED_SIZE);
+				let mut list = Vec::with_capacity(data.len() * Slice::ENCODED_SIZE);
+				for d in data.iter().filter(|d| !d.is_empty()) {
+					let d = &d[..d.len().min(left)];
+					if d.is_empty() {
+						break;
+					}
+					left -= d.len();
+					list.extend_from_slice(&tbl.copy_data_from(q, d).to_le_bytes());
+				}
+				Request::WriteVectored {
+					job_id,
+					slices: tbl.copy_data_from(q, &list),
+				}
+			})
+		})
+	}
+
+	fn read_at(self: Arc<Self>, offset: u64, length: usize) -> Ticket<Box<[u8]>> {
+		let amount = length.try_into().unwrap_or(u32::MAX);
+		self.with_table(|tbl| {
+			tbl.submit_job(self.handle, |q, job_id| Request::ReadAt {
+				job_id,
+				offset_amount: tbl
+					.copy_data_from_scatter(q, &[&offset.to_le_bytes(), &amount.to_le_bytes()]),
+			})
+		})
+	}
+
+	fn write_at(self: Arc<Self>, offset: u64, data: &[u8]) -> Ticket<u64> {
+		self.with_table(|tbl| {
+			// Make sure the offset isn't cut off. If the data is truncated, keep it a multiple of
+			// the block size as servers may only accept whole blocks, e.g. sectors.
+			let block_mask = tbl.buffer_mem.block_size() as usize - 1;
+			let max = (tbl.max_request_mem as usize + 1).saturating_sub(8) & !block_mask;
+			let data = &data[..data.len().min(max)];
+			tbl.submit_job(self.handle, |q, job_id| Request::WriteAt {
+				job_id,
+				data_offset: tbl.copy_data_from_scatter(q, &[data, &offset.to_le_bytes()]),
+			})
+		})
+	}
+
 	fn get_meta(self: Arc<Self>, property: &TinySlice<u8>) -> Ticket<Box<[u8]>> {
 		self.with_table(|tbl| {
 			tbl.submit_job(self.handle, |q, job_id| Request::GetMeta {
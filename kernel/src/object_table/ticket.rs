@@ -12,6 +12,8 @@ use core::{
 #[derive(Default)]
 pub struct Ticket<T> {
 	inner: Arc<SpinLock<TicketInner<T>>>,
+	/// Function to apply to the value once the job is completed.
+	map: Option<Box<dyn FnOnce(T) -> T>>,
 }
 
 impl<T> Ticket<T> {
@@ -19,23 +21,51 @@ impl<T> Ticket<T> {
 		let inner = SpinLock::new(TicketInner {
 			waker: None,
 			status: Some(status),
+			on_cancel: None,
 		})
 		.into();
-		Self { inner }
+		Self { inner, map: None }
 	}
 
 	pub fn new() -> (Self, TicketWaker<T>) {
 		let inner = Arc::new(SpinLock::new(TicketInner {
 			waker: None,
 			status: None,
+			on_cancel: None,
 		}));
 		(
 			Self {
 				inner: inner.clone(),
+				map: None,
 			},
 			TicketWaker { inner },
 		)
 	}
+
+	/// Apply a function to the value once the job is completed successfully.
+	pub fn map(mut self, f: impl FnOnce(T) -> T + 'static) -> Self
+	where
+		T: 'static,
+	{
+		self.map = Some(match self.map.take() {
+			Some(g) => Box::new(move |v| f(g(v))),
+			None => Box::new(f),
+		});
+		self
+	}
+
+	/// Cancel the job.
+	///
+	/// If the job hasn't completed yet the object that is handling it is notified. The job may
+	/// still complete but the result will be discarded.
+	pub fn cancel(self) {
+		let mut l = self.inner.lock();
+		let f = l.status.is_none().then(|| l.on_cancel.take()).flatten();
+		drop(l);
+		if let Some(f) = f {
+			f()
+		}
+	}
 }
 
 pub struct TicketWaker<T> {
@@ -55,6 +85,11 @@ impl<T> TicketWaker<T> {
 		l.waker.take().map(|w| w.wake());
 		l.status = Some(status);
 	}
+
+	/// Set a function to call if the ticket is cancelled before the job completes.
+	pub fn on_cancel(&self, f: impl FnOnce() + 'static) {
+		self.inner.lock().on_cancel = Some(Box::new(f));
+	}
 }
 
 impl<T: fmt::Debug> fmt::Debug for TicketWaker<T> {
@@ -63,20 +98,34 @@ impl<T: fmt::Debug> fmt::Debug for TicketWaker<T> {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct TicketInner<T> {
 	waker: Option<Waker>,
 	/// The completion status of this job.
 	status: Option<Result<T, Error>>,
+	/// Function to notify the object handling the job if it is cancelled.
+	on_cancel: Option<Box<dyn FnOnce()>>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for TicketInner<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct(stringify!(TicketInner))
+			.field("waker", &self.waker)
+			.field("status", &self.status)
+			.finish_non_exhaustive()
+	}
 }
 
 impl<T> Future for Ticket<T> {
 	type Output = Result<T, Error>;
 
 	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-		let mut t = self.inner.lock();
+		let this = self.get_mut();
+		let mut t = this.inner.lock();
 		if let Some(s) = t.status.take() {
-			return Poll::Ready(s);
+			drop(t);
+			let map = this.map.take();
+			return Poll::Ready(s.map(|v| map.map_or(v, |f| f(v))));
 		}
 		t.waker = Some(cx.waker().clone());
 		Poll::Pending
@@ -139,6 +188,16 @@ any_ticket!(Arc<dyn Object> => Object, into_object);
 any_ticket!(u64 => U64, into_u64);
 any_ticket!(Box<[u8]> => Data, into_data);
 
+impl AnyTicket {
+	pub fn cancel(self) {
+		match self {
+			Self::Object(t) => t.cancel(),
+			Self::U64(t) => t.cancel(),
+			Self::Data(t) => t.cancel(),
+		}
+	}
+}
+
 impl AnyTicketWaker {
 	pub fn complete_err(self, err: Error) {
 		match self {
@@ -0,0 +1,204 @@
+//! # Timers
+//!
+//! A timer expires at a given time and, if it is periodic, every interval afterwards.
+//!
+//! Reading from a timer blocks until it has expired at least once. The data is the amount of
+//! times it expired since the last read as a little-endian `u64`. Once a one-shot timer has been
+//! read all subsequent reads return no data.
+//!
+//! Peeking returns the amount of times it expired without resetting it. It doesn't block, so the
+//! amount may be zero.
+
+use super::{Object, Ticket, TicketWaker};
+use crate::{sync::SpinLock, time::Monotonic};
+use alloc::{
+	boxed::Box,
+	collections::VecDeque,
+	sync::{Arc, Weak},
+	vec::Vec,
+};
+use core::{
+	sync::atomic::{AtomicU64, Ordering},
+	time::Duration,
+};
+
+/// Timers with pending reads along with the time they expire.
+///
+/// A timer is listed once, as long as it has pending reads. This lock is taken before the lock of
+/// a timer.
+static PENDING: SpinLock<Vec<(Monotonic, Arc<Timer>)>> = SpinLock::new(Vec::new());
+
+/// The ID of the next read, used to find reads that are cancelled.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Create a new timer that expires at the given deadline.
+///
+/// If `interval` is `None` the timer only expires once.
+pub fn new(deadline: Monotonic, interval: Option<Duration>) -> Arc<dyn Object> {
+	Arc::new(Timer(SpinLock::new(TimerInner {
+		deadline,
+		interval: interval.filter(|i| !i.is_zero()),
+		wake: Default::default(),
+	})))
+}
+
+/// Complete pending reads of expired timers.
+///
+/// Returns the time at which the next timer expires.
+pub fn expire(now: Monotonic) -> Monotonic {
+	let mut pending = PENDING.auto_lock();
+	let mut rearm = Vec::new();
+	pending.retain(|(deadline, timer)| {
+		if *deadline > now {
+			return true;
+		}
+		let mut t = timer.0.auto_lock();
+		if !t.wake.is_empty() {
+			if let Some(n) = t.expirations(now) {
+				let (_, length, w) = t.wake.pop_front().unwrap();
+				w.isr_complete(Ok(encode(n, length)));
+			}
+		}
+		if t.deadline == Monotonic::MAX {
+			// One-shot timers won't expire again.
+			t.wake
+				.drain(..)
+				.for_each(|(_, _, w)| w.isr_complete(Ok([].into())));
+		} else if !t.wake.is_empty() {
+			rearm.push((t.deadline, timer.clone()));
+		}
+		false
+	});
+	pending.extend(rearm);
+	pending
+		.iter()
+		.map(|(d, _)| *d)
+		.min()
+		.unwrap_or(Monotonic::MAX)
+}
+
+struct Timer(SpinLock<TimerInner>);
+
+struct TimerInner {
+	/// When the timer expires next. This is [`Monotonic::MAX`] if it won't expire anymore.
+	deadline: Monotonic,
+	/// The period of the timer if it is periodic.
+	interval: Option<Duration>,
+	/// Pending reads along with their ID and the length of the read.
+	wake: VecDeque<(u64, usize, TicketWaker<Box<[u8]>>)>,
+}
+
+impl TimerInner {
+	/// Take the amount of times the timer expired up to the given time.
+	///
+	/// Returns `None` if it hasn't expired.
+	fn expirations(&mut self, now: Monotonic) -> Option<u64> {
+		let (n, deadline) = self.count(now)?;
+		self.deadline = deadline;
+		Some(n)
+	}
+
+	/// Count the amount of times the timer expired up to the given time along with the time it
+	/// expires next.
+	///
+	/// Returns `None` if it hasn't expired.
+	fn count(&self, now: Monotonic) -> Option<(u64, Monotonic)> {
+		let elapsed = self.deadline.duration_until(now)?;
+		Some(match self.interval {
+			Some(interval) => {
+				let n = elapsed.as_nanos() / interval.as_nanos() + 1;
+				let ns = u128::from(self.deadline.as_nanos()) + n * interval.as_nanos();
+				let deadline = Monotonic::from_nanos(ns.try_into().unwrap_or(u64::MAX));
+				(n.try_into().unwrap_or(u64::MAX), deadline)
+			}
+			None => (1, Monotonic::MAX),
+		})
+	}
+}
+
+impl Object for Timer {
+	fn read(self: Arc<Self>, length: usize, peek: bool) -> Ticket<Box<[u8]>> {
+		if peek {
+			let t = self.0.auto_lock();
+			if t.deadline == Monotonic::MAX {
+				return Ticket::new_complete(Ok([].into()));
+			}
+			let n = t.count(Monotonic::now()).map_or(0, |(n, _)| n);
+			return Ticket::new_complete(Ok(encode(n, length)));
+		}
+		let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+		let (ticket, waker) = Ticket::new();
+		let timer = Arc::downgrade(&self);
+		waker.on_cancel(move || {
+			if let Some(timer) = Weak::upgrade(&timer) {
+				let mut pending = PENDING.auto_lock();
+				let mut t = timer.0.auto_lock();
+				t.wake.retain(|(i, _, _)| *i != id);
+				if t.wake.is_empty() {
+					pending.retain(|(_, p)| !Arc::ptr_eq(p, &timer));
+				}
+			}
+		});
+
+		let mut pending = PENDING.auto_lock();
+		let mut t = self.0.auto_lock();
+		if let Some(n) = t.expirations(Monotonic::now()) {
+			drop((t, pending));
+			waker.complete(Ok(encode(n, length)));
+		} else if t.deadline == Monotonic::MAX {
+			drop((t, pending));
+			waker.complete(Ok([].into()));
+		} else {
+			if t.wake.is_empty() {
+				pending.push((t.deadline, self.clone()));
+			}
+			t.wake.push_back((id, length, waker));
+		}
+		ticket
+	}
+}
+
+/// Encode the amount of expirations, truncated to the length of the read.
+fn encode(n: u64, length: usize) -> Box<[u8]> {
+	n.to_le_bytes()[..length.min(8)].into()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn timer(deadline: u64, interval: Option<u64>) -> TimerInner {
+		TimerInner {
+			deadline: Monotonic::from_nanos(deadline),
+			interval: interval.map(Duration::from_nanos),
+			wake: Default::default(),
+		}
+	}
+
+	#[test]
+	fn periodic() {
+		let mut t = timer(100, Some(10));
+		assert_eq!(t.expirations(Monotonic::from_nanos(99)), None);
+		assert_eq!(
+			t.count(Monotonic::from_nanos(125)),
+			Some((3, Monotonic::from_nanos(130)))
+		);
+		// Counting doesn't reset the timer.
+		assert_eq!(t.count(Monotonic::from_nanos(125)).map(|(n, _)| n), Some(3));
+		assert_eq!(t.expirations(Monotonic::from_nanos(125)), Some(3));
+		assert_eq!(t.expirations(Monotonic::from_nanos(125)), None);
+		assert_eq!(t.expirations(Monotonic::from_nanos(130)), Some(1));
+	}
+
+	#[test]
+	fn one_shot() {
+		let mut t = timer(100, None);
+		assert_eq!(
+			t.count(Monotonic::from_nanos(200)),
+			Some((1, Monotonic::MAX))
+		);
+		assert_eq!(t.expirations(Monotonic::from_nanos(200)), Some(1));
+		assert_eq!(t.deadline, Monotonic::MAX);
+		assert_eq!(t.expirations(Monotonic::from_nanos(300)), None);
+	}
+}
@@ -0,0 +1,142 @@
+//! Kernel entropy source.
+//!
+//! Entropy is gathered from the CPU's hardware generator (RDSEED/RDRAND) if present and from
+//! the timing of interrupts. It is mixed into the key of a ChaCha20-based CSPRNG, which is
+//! exposed to userspace as the `random` object.
+
+use crate::arch::random as hw;
+use crate::object_table::{Object, Root, Ticket};
+use crate::sync::SpinLock;
+use crate::time::Monotonic;
+use alloc::{boxed::Box, sync::Arc, vec};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The maximum amount of bytes that can be read at once.
+const MAX_READ: usize = 1 << 16;
+
+/// Jitter accumulated from interrupts since the last reseed.
+static JITTER: AtomicU64 = AtomicU64::new(0);
+
+static RNG: SpinLock<ChaCha> = SpinLock::new(ChaCha {
+	key: [0; 8],
+	counter: 0,
+});
+
+/// Mix the current value of the cycle counter into the entropy pool.
+///
+/// This is meant to be called from interrupt handlers and hence never blocks.
+pub fn add_jitter() {
+	let t = hw::cycle_counter();
+	// fetch_update can't fail since the closure always returns Some.
+	let _ = JITTER.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |j| {
+		Some(j.rotate_left(7) ^ t)
+	});
+}
+
+/// Fill a buffer with cryptographically secure random bytes.
+pub fn fill(buf: &mut [u8]) {
+	let mut rng = RNG.auto_lock();
+	rng.reseed();
+	rng.fill(buf);
+}
+
+struct ChaCha {
+	key: [u32; 8],
+	counter: u64,
+}
+
+impl ChaCha {
+	/// Mix fresh entropy into the key.
+	fn reseed(&mut self) {
+		let mut seed = [
+			JITTER.swap(0, Ordering::Relaxed),
+			hw::cycle_counter(),
+			Monotonic::now().as_nanos(),
+			0,
+		];
+		for s in seed[3..].iter_mut() {
+			*s = hw::rdseed().or_else(hw::rdrand).unwrap_or(0);
+		}
+		for (k, s) in self.key.chunks_exact_mut(2).zip(seed) {
+			k[0] ^= s as u32;
+			k[1] ^= (s >> 32) as u32;
+		}
+		self.rekey();
+	}
+
+	/// Replace the key with fresh output so earlier output can't be recovered from it.
+	fn rekey(&mut self) {
+		let b = self.next_block();
+		self.key.copy_from_slice(&b[..8]);
+	}
+
+	fn fill(&mut self, buf: &mut [u8]) {
+		for c in buf.chunks_mut(64) {
+			let b = self.next_block();
+			for (w, c) in b.iter().zip(c.chunks_mut(4)) {
+				c.copy_from_slice(&w.to_le_bytes()[..c.len()]);
+			}
+		}
+		self.rekey();
+	}
+
+	fn next_block(&mut self) -> [u32; 16] {
+		let b = block(&self.key, self.counter);
+		self.counter = self.counter.wrapping_add(1);
+		b
+	}
+}
+
+/// Compute a single ChaCha20 block with an all-zero nonce.
+fn block(key: &[u32; 8], counter: u64) -> [u32; 16] {
+	#[rustfmt::skip]
+	let mut s = [
+		0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574,
+		key[0], key[1], key[2], key[3],
+		key[4], key[5], key[6], key[7],
+		counter as u32, (counter >> 32) as u32, 0, 0,
+	];
+	let init = s;
+	fn qr(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+		s[a] = s[a].wrapping_add(s[b]);
+		s[d] = (s[d] ^ s[a]).rotate_left(16);
+		s[c] = s[c].wrapping_add(s[d]);
+		s[b] = (s[b] ^ s[c]).rotate_left(12);
+		s[a] = s[a].wrapping_add(s[b]);
+		s[d] = (s[d] ^ s[a]).rotate_left(8);
+		s[c] = s[c].wrapping_add(s[d]);
+		s[b] = (s[b] ^ s[c]).rotate_left(7);
+	}
+	for _ in 0..10 {
+		qr(&mut s, 0, 4, 8, 12);
+		qr(&mut s, 1, 5, 9, 13);
+		qr(&mut s, 2, 6, 10, 14);
+		qr(&mut s, 3, 7, 11, 15);
+		qr(&mut s, 0, 5, 10, 15);
+		qr(&mut s, 1, 6, 11, 12);
+		qr(&mut s, 2, 7, 8, 13);
+		qr(&mut s, 3, 4, 9, 14);
+	}
+	for (s, i) in s.iter_mut().zip(init) {
+		*s = s.wrapping_add(i);
+	}
+	s
+}
+
+struct RandomObject;
+
+impl Object for RandomObject {
+	fn read(self: Arc<Self>, length: usize, _peek: bool) -> Ticket<Box<[u8]>> {
+		let mut buf = vec![0; length.min(MAX_READ)].into_boxed_slice();
+		fill(&mut buf);
+		Ticket::new_complete(Ok(buf))
+	}
+}
+
+pub fn post_init(root: &Root) {
+	// Make sure the key doesn't start out all-zero even if interrupts haven't fired yet.
+	RNG.auto_lock().reseed();
+	let obj = Arc::new(RandomObject) as Arc<dyn Object>;
+	root.add(*b"random", Arc::downgrade(&obj));
+	let _ = Arc::into_raw(obj); // Intentionally leak the object.
+}
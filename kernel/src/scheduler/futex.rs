@@ -0,0 +1,105 @@
+//! # Futexes
+//!
+//! Threads wait on a 32-bit word in memory until another thread wakes them. Waiters are keyed by
+//! the physical address of the word so that futexes also work on memory shared between
+//! processes.
+
+use super::{process::Process, Thread};
+use crate::{memory::r#virtual::phys_to_virt, sync::SpinLock, time::Monotonic};
+use alloc::{
+	collections::{BTreeMap, VecDeque},
+	sync::{Arc, Weak},
+	vec::Vec,
+};
+use core::{
+	ptr::NonNull,
+	sync::atomic::{AtomicU32, AtomicU64, Ordering},
+	time::Duration,
+};
+
+/// Waiting threads along with an ID per wait, keyed by physical address.
+static WAITERS: SpinLock<BTreeMap<u64, VecDeque<(u64, Weak<Thread>)>>> =
+	SpinLock::new(BTreeMap::new());
+
+/// The ID of the next wait, used to find waits that timed out.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Wait until another thread wakes us or the timeout expires.
+///
+/// Returns immediately if the word at `address` in the address space of `process` is not equal
+/// to `expected`. `address` must be aligned to 4 bytes.
+pub fn wait(
+	process: &Process,
+	address: NonNull<()>,
+	expected: u32,
+	timeout: Duration,
+) -> Result<(), WaitError> {
+	let thread = Thread::current().unwrap();
+	let deadline = Monotonic::now().saturating_add(timeout);
+	let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+	// The address space is locked while the word is loaded so it can't be unmapped and freed
+	// in the meantime.
+	let address = process
+		.with_physical_address(address, |address| {
+			let mut waiters = WAITERS.lock();
+			// SAFETY: the address is mapped and aligned.
+			let word = unsafe { &*phys_to_virt(address).cast::<AtomicU32>() };
+			if word.load(Ordering::SeqCst) != expected {
+				return Err(WaitError::Mismatch);
+			}
+			waiters
+				.entry(address)
+				.or_default()
+				.push_back((id, Arc::downgrade(&thread)));
+			Ok(address)
+		})
+		.ok_or(WaitError::Unmapped)??;
+	loop {
+		// The thread may be woken for other reasons, so check if we're still waiting.
+		let mut waiters = WAITERS.lock();
+		let Some(queue) = waiters.get_mut(&address) else { return Ok(()) };
+		let Some(i) = queue.iter().position(|(i, _)| *i == id) else { return Ok(()) };
+		let timeout = Monotonic::now()
+			.duration_until(deadline)
+			.unwrap_or_default();
+		if timeout.is_zero() {
+			queue.remove(i);
+			if queue.is_empty() {
+				waiters.remove(&address);
+			}
+			return Err(WaitError::TimedOut);
+		}
+		drop(waiters);
+		thread.sleep(timeout);
+	}
+}
+
+/// Wake up to `count` threads waiting on the given address.
+///
+/// Waiters whose thread no longer exists are removed without counting towards `count`.
+///
+/// Returns the amount of threads that were woken.
+pub fn wake(address: u64, count: usize) -> usize {
+	let threads = {
+		let mut waiters = WAITERS.lock();
+		let Some(queue) = waiters.get_mut(&address) else { return 0 };
+		let mut threads = Vec::new();
+		while threads.len() < count {
+			let Some((_, t)) = queue.pop_front() else { break };
+			threads.extend(t.upgrade());
+		}
+		if queue.is_empty() {
+			waiters.remove(&address);
+		}
+		threads
+	};
+	threads.iter().for_each(|t| t.wake());
+	threads.len()
+}
+
+#[derive(Debug)]
+pub enum WaitError {
+	Unmapped,
+	Mismatch,
+	TimedOut,
+}
@@ -1,3 +1,4 @@
+mod futex;
 mod memory_object;
 pub mod process;
 mod round_robin;
@@ -6,7 +7,11 @@ mod thread;
 mod waker;
 
 use crate::{
-	arch, driver::apic, memory::frame::AllocateError, object_table::Root, time::Monotonic,
+	arch,
+	driver::apic,
+	memory::frame::AllocateError,
+	object_table::{timer, Root},
+	time::Monotonic,
 };
 use alloc::sync::Arc;
 use core::future::Future;
@@ -35,12 +40,17 @@ unsafe fn try_next_thread() -> Result<!, Monotonic> {
 	let mut thr = next();
 	let first = Arc::as_ptr(&thr);
 	let now = Monotonic::now();
-	let mut t = Monotonic::MAX;
+	// Expire timers first as it may wake threads.
+	let mut t = timer::expire(now);
+	// Interrupt the thread in time for the next timer to expire.
+	let time_slice = now
+		.duration_until(t)
+		.map_or(TIME_SLICE, |d| d.min(TIME_SLICE));
 	loop {
 		let sleep_until = thr.sleep_until();
 		if sleep_until <= now {
 			// Be very careful _not_ to clone here, as otherwise we'll start leaking references.
-			apic::set_timer_oneshot(TIME_SLICE);
+			apic::set_timer_oneshot(time_slice);
 			let _ = thr.resume();
 		}
 		t = t.min(sleep_until);
@@ -1,20 +1,22 @@
 //! # I/O with user processes
 
 use super::{super::poll, erase_handle, unerase_handle, MemoryObject, PendingTicket};
-use crate::memory::frame::OwnedPageFrames;
-use crate::memory::r#virtual::{MapError, UnmapError, RWX};
+use crate::audit::{self, Operation};
+use crate::memory::frame::{AllocateError, OwnedPageFrames};
+use crate::memory::r#virtual::{AddressSpace, MapError, UnmapError, RWX};
 use crate::memory::Page;
-use crate::object_table::{AnyTicketValue, Error, Handle, Object, TinySlice};
+use crate::object_table::{AnyTicket, AnyTicketValue, Error, Handle, Object, TinySlice};
+use crate::sync::mutex::Guard;
+use crate::time::Monotonic;
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
-use core::{
-	ptr::{self, NonNull},
-	task::Poll,
-	time::Duration,
+use core::{num::NonZeroUsize, ptr::NonNull, task::Poll, time::Duration};
+use norostb_kernel::io::{
+	self as k_io, IoSlice, IoSliceMut, Request, Response, SeekFrom, IO_SLICES_MAX,
 };
-use norostb_kernel::io::{self as k_io, Request, Response, SeekFrom};
 
 pub enum CreateQueueError {
 	TooLarge,
+	AllocateError(AllocateError),
 	MapError(MapError),
 }
 
@@ -26,11 +28,21 @@ pub enum WaitQueueError {
 	InvalidAddress,
 }
 
+pub enum WaitAnyError {
+	NoPendingRequests,
+	TimedOut,
+}
+
 const MAX_SIZE_P2: u8 = 15;
 
 pub(super) struct Queue {
 	user_ptr: NonNull<Page>,
-	frames: Arc<OwnedPageFrames>,
+	/// The queue mapped in kernel space.
+	///
+	/// The frames of the queue may not be contiguous, so they are mapped separately to get a
+	/// contiguous view of the queue. This also ensures the frames stay alive if the user unmaps
+	/// the queue.
+	kernel_ptr: NonNull<Page>,
 	requests_mask: u32,
 	responses_mask: u32,
 	pending: Vec<PendingTicket>,
@@ -38,18 +50,25 @@ pub(super) struct Queue {
 
 impl Queue {
 	fn kernel_io_queue(&self) -> k_io::Queue {
-		let mut frame = None;
-		self.frames.physical_pages(&mut |f| {
-			assert!(frame.is_none() && f.len() == 1, "TODO");
-			frame = Some(f[0]);
-			true
-		});
 		k_io::Queue {
-			base: NonNull::new(frame.unwrap().as_ptr()).unwrap().cast(),
+			base: self.kernel_ptr.cast(),
 			requests_mask: self.requests_mask,
 			responses_mask: self.responses_mask,
 		}
 	}
+
+	/// The amount of pages the queue spans.
+	fn page_count(&self) -> NonZeroUsize {
+		let size = k_io::Queue::total_size(self.requests_mask, self.responses_mask);
+		Page::min_pages_for_bytes(size).try_into().unwrap()
+	}
+}
+
+impl Drop for Queue {
+	fn drop(&mut self) {
+		// SAFETY: the kernel mapping is only used by this queue.
+		unsafe { AddressSpace::kernel_unmap_object(self.kernel_ptr, self.page_count()).unwrap() };
+	}
 }
 
 impl super::Process {
@@ -67,33 +86,29 @@ impl super::Process {
 		let size = k_io::Queue::total_size(requests_mask, responses_mask);
 		let count = Page::min_pages_for_bytes(size);
 
-		// FIXME the user can manually unmap the queue, leading to very bad things.
-		// An easy work-around for now is to allow only one page, which is guaranteed to be
-		// contiguous and hence we can just use a pointer in identity-mapped space.
-		assert_eq!(count, 1, "TODO");
-		let frames = Arc::new(
-			OwnedPageFrames::new(count.try_into().unwrap(), self.allocate_hints(0 as _)).unwrap(),
-		);
+		let mut frames =
+			OwnedPageFrames::new(count.try_into().unwrap(), self.allocate_hints(0 as _))
+				.map_err(CreateQueueError::AllocateError)?;
+		unsafe { frames.clear() };
+		let frames = Arc::new(frames);
 
-		let (user_ptr, _) = self
-			.address_space
-			.lock()
-			.map_object(
-				base,
-				frames.clone(),
-				RWX::RW,
-				0,
-				usize::MAX,
-				self.hint_color,
-			)
+		let (kernel_ptr, _) = AddressSpace::kernel_map_object(None, frames.clone(), RWX::RW)
 			.map_err(CreateQueueError::MapError)?;
-		self.io_queues.lock().push(Queue {
-			user_ptr,
-			frames,
+		let mut queue = Queue {
+			user_ptr: NonNull::dangling(),
+			kernel_ptr,
 			requests_mask,
 			responses_mask,
 			pending: Default::default(),
-		});
+		};
+
+		let (user_ptr, _) = self
+			.address_space
+			.lock()
+			.map_object(base, frames, RWX::RW, 0, usize::MAX, self.hint_color)
+			.map_err(CreateQueueError::MapError)?;
+		queue.user_ptr = user_ptr;
+		self.io_queues.lock().push(queue);
 		Ok(user_ptr)
 	}
 
@@ -107,11 +122,9 @@ impl super::Process {
 			queues.remove(i)
 		};
 
-		let size = k_io::Queue::total_size(queue.requests_mask, queue.responses_mask);
-		let count = Page::min_pages_for_bytes(size).try_into().unwrap();
 		self.address_space
 			.lock()
-			.unmap_object(base, count)
+			.unmap_object(base, queue.page_count())
 			.map_err(RemoveQueueError::UnmapError)
 	}
 
@@ -127,143 +140,29 @@ impl super::Process {
 		// Poll tickets first as it may shrink the ticket Vec.
 		poll_tickets(queue, &mut objects);
 
-		let k_io_queue = queue.kernel_io_queue();
-		let tickets = &mut queue.pending;
-		let mut queue = k_io_queue;
-
-		while let Ok(e) = unsafe { queue.dequeue_request() } {
-			let mut push_resp = |value| {
-				// It is the responsibility of the user process to ensure no more requests are in
-				// flight than there is space for responses.
-				let _ = unsafe {
-					queue.enqueue_response(Response {
-						user_data: e.user_data,
-						value,
-					})
-				};
-			};
-			let mut push_pending = |data_ptr, data_len, ticket| {
-				tickets.push(PendingTicket {
-					user_data: e.user_data,
-					data_ptr,
-					data_len,
-					ticket,
-				})
-			};
-			let handle = unerase_handle(e.handle);
-			let Some(object) = objects.get(handle) else {
-				push_resp(Error::InvalidObject as i64);
-				continue;
-			};
-			match e.ty {
-				Request::READ | Request::PEEK => {
-					let data_ptr = e.arguments_64[0] as *mut u8;
-					let data_len = e.arguments_64[1] as usize;
-					let mut ticket = object
-						.clone()
-						.read(data_len.try_into().unwrap(), e.ty == Request::PEEK);
-					match poll(&mut ticket) {
-						Poll::Pending => push_pending(data_ptr, data_len, ticket.into()),
-						Poll::Ready(Ok(b)) => push_resp(copy_data_to(data_ptr, data_len, b)),
-						Poll::Ready(Err(e)) => push_resp(e as i64),
-					}
-				}
-				Request::WRITE => {
-					let data_ptr = e.arguments_64[0] as *const u8;
-					let data_len = e.arguments_64[1] as usize;
-					let data = unsafe { core::slice::from_raw_parts(data_ptr, data_len) };
-					// TODO crappy workaround for Stream table
-					// We should instead pass a reference to the objects list or some Context
-					// object.
-					let object = object.clone();
-					drop(objects);
-					let mut ticket = object.write(data);
-					objects = self.objects.lock();
-					match poll(&mut ticket) {
-						Poll::Pending => push_pending(ptr::null_mut(), 0, ticket.into()),
-						Poll::Ready(Ok(b)) => push_resp(b.try_into().unwrap()),
-						Poll::Ready(Err(e)) => push_resp(e as i64),
-					}
-				}
-				Request::OPEN => {
-					let path_ptr = e.arguments_64[0] as *const u8;
-					let path_len = e.arguments_64[1] as usize;
-					let path = unsafe { core::slice::from_raw_parts(path_ptr, path_len) };
-					let mut ticket = object.clone().open(path);
-					match poll(&mut ticket) {
-						Poll::Pending => push_pending(ptr::null_mut(), 0, ticket.into()),
-						Poll::Ready(Ok(o)) => {
-							push_resp(erase_handle(objects.insert(o)).try_into().unwrap())
-						}
-						Poll::Ready(Err(e)) => push_resp(e as i64),
-					}
-				}
-				Request::CREATE => {
-					let path_ptr = e.arguments_64[0] as *const u8;
-					let path_len = e.arguments_64[1] as usize;
-					let path = unsafe { core::slice::from_raw_parts(path_ptr, path_len) };
-					let mut ticket = object.clone().create(path);
-					match poll(&mut ticket) {
-						Poll::Pending => push_pending(ptr::null_mut(), 0, ticket.into()),
-						Poll::Ready(Ok(o)) => {
-							push_resp(erase_handle(objects.insert(o)).try_into().unwrap())
-						}
-						Poll::Ready(Err(e)) => push_resp(e as i64),
-					}
-				}
-				Request::SEEK => {
-					let direction = e.arguments_8[0];
-					let offset = e.arguments_64[0];
-
-					let Ok(from) = SeekFrom::try_from_raw(direction, offset) else {
-						warn!("Invalid offset ({}, {})", direction, offset);
-						push_resp(-1);
-						continue;
-					};
-					let mut ticket = object.seek(from);
-					match poll(&mut ticket) {
-						Poll::Pending => push_pending(ptr::null_mut(), 0, ticket.into()),
-						Poll::Ready(Ok(n)) => push_resp(n as i64),
-						Poll::Ready(Err(e)) => push_resp(e as i64),
-					}
-				}
-				Request::CLOSE => {
-					// We are not supposed to return a response under any circumstances.
-					let _ = objects.remove(handle);
-				}
-				Request::SHARE => {
-					let share = unerase_handle(e.arguments_64[0] as Handle);
-					if let Some(shr) = objects.get(share) {
-						let mut ticket = object.clone().share(shr);
-						match poll(&mut ticket) {
-							Poll::Pending => push_pending(ptr::null_mut(), 0, ticket.into()),
-							Poll::Ready(Ok(n)) => push_resp(n as i64),
-							Poll::Ready(Err(e)) => push_resp(e as i64),
-						}
-					} else {
-						push_resp(Error::InvalidObject as i64)
-					}
-				}
-				Request::GET_META => {
-					let [prop_len, val_len, _] = e.arguments_8;
-					let [prop_ptr, val_ptr] = e.arguments_64;
-					let prop =
-						unsafe { TinySlice::from_raw_parts(prop_ptr as *const u8, prop_len) };
-					let mut ticket = object.clone().get_meta(prop);
-					let (val_ptr, val_len) = (val_ptr as *mut u8, val_len.into());
-					match poll(&mut ticket) {
-						Poll::Pending => push_pending(val_ptr, val_len, ticket.into()),
-						Poll::Ready(Ok(b)) => push_resp(copy_data_to(val_ptr, val_len, b)),
-						Poll::Ready(Err(e)) => push_resp(e as i64),
+		let mut k_io_queue = queue.kernel_io_queue();
+		let mut next = unsafe { k_io_queue.dequeue_request() }.ok();
+		while let Some(e) = next.take() {
+			if !control(queue, &mut objects, &e) {
+				// Gather the remaining requests of the chain, if any.
+				//
+				// Cancel and timeout requests can't be linked as they don't have a response. They
+				// end the chain instead and are handled after it starts.
+				let mut chain = Chain::default();
+				let mut flags = e.flags;
+				while flags & Request::LINK != 0 {
+					let Ok(e) = (unsafe { k_io_queue.dequeue_request() }) else { break };
+					if matches!(e.ty, Request::CANCEL | Request::TIMEOUT) {
+						next = Some(e);
+						break;
 					}
+					flags = e.flags;
+					chain.requests.push(e);
 				}
-				Request::SET_META => todo!(),
-				Request::DESTROY => todo!(),
-				op => {
-					warn!("Unknown I/O queue operation {}", op);
-					push_resp(Error::InvalidOperation as i64);
-				}
+				chain.requests.reverse();
+				run_chain(queue, &mut objects, e, chain);
 			}
+			next = next.or_else(|| unsafe { k_io_queue.dequeue_request() }.ok());
 		}
 		Ok(())
 	}
@@ -292,6 +191,14 @@ impl super::Process {
 				}
 			}
 
+			// Wake up in time to cancel requests that exceed their deadline.
+			let now = Monotonic::now();
+			let timeout = queue
+				.pending
+				.iter()
+				.map(|t| now.duration_until(t.deadline).unwrap_or_default())
+				.fold(timeout, Duration::min);
+
 			// Prevent blocking other threads.
 			drop(io_queues);
 
@@ -301,30 +208,408 @@ impl super::Process {
 		}
 		Ok(())
 	}
+
+	/// Wait until a pending request on any of the given objects finishes or the timeout
+	/// expires.
+	///
+	/// Returns the index of the handle of the object.
+	pub fn wait_any(&self, handles: &[Handle], timeout: Duration) -> Result<usize, WaitAnyError> {
+		let deadline = Monotonic::now().saturating_add(timeout);
+		loop {
+			let mut io_queues = self.io_queues.lock();
+			let mut objects = self.objects.lock();
+			let mut index = None;
+			for queue in io_queues.iter_mut() {
+				poll_tickets_with(queue, &mut objects, |h| {
+					index = index.or_else(|| handles.iter().position(|e| *e == h));
+				});
+			}
+			if let Some(i) = index {
+				return Ok(i);
+			}
+
+			let mut pending = io_queues
+				.iter()
+				.flat_map(|q| q.pending.iter())
+				.filter(|t| handles.contains(&t.handle))
+				.peekable();
+			if pending.peek().is_none() {
+				return Err(WaitAnyError::NoPendingRequests);
+			}
+			let now = Monotonic::now();
+			if now >= deadline {
+				return Err(WaitAnyError::TimedOut);
+			}
+			// Wake up in time to cancel requests that exceed their deadline.
+			let wake_at = pending.map(|t| t.deadline).fold(deadline, Monotonic::min);
+
+			// Prevent blocking other threads.
+			drop(objects);
+			drop(io_queues);
+
+			let timeout = now.duration_until(wake_at).unwrap_or_default();
+			super::super::Thread::current().unwrap().sleep(timeout);
+		}
+	}
+}
+
+type Objects<'a> = Guard<'a, arena::Arena<Arc<dyn Object>, u8>>;
+
+/// The state of a request after submitting it.
+enum Submit {
+	/// The request finished with the given value.
+	Done(i64),
+	/// The request finished but doesn't have a response.
+	NoResponse,
+	/// The request has not finished yet.
+	Pending(UserBuffer, AnyTicket, audit::Record),
+}
+
+/// Requests linked to a request that has not finished yet.
+#[derive(Default)]
+pub(super) struct Chain {
+	/// The remaining requests in reverse order.
+	requests: Vec<Request>,
+	/// The handle returned by the last open or create request.
+	handle: Option<Handle>,
+}
+
+/// Handle a request that affects other requests.
+///
+/// Requests that already finished or don't exist are ignored. Returns `false` if it isn't such a
+/// request.
+fn control(queue: &mut Queue, objects: &mut Objects<'_>, e: &Request) -> bool {
+	match e.ty {
+		Request::CANCEL => {
+			if let Some(i) = queue
+				.pending
+				.iter()
+				.position(|t| t.user_data == e.user_data)
+			{
+				cancel(queue, objects, i, Error::Cancelled);
+			} else {
+				cancel_linked(queue, e.user_data);
+			}
+		}
+		Request::TIMEOUT => {
+			let deadline = Monotonic::from_nanos(e.arguments_64[0]);
+			if let Some(t) = queue
+				.pending
+				.iter_mut()
+				.find(|t| t.user_data == e.user_data)
+			{
+				t.deadline = t.deadline.min(deadline);
+			}
+		}
+		_ => return false,
+	}
+	true
+}
+
+/// Cancel a request that is waiting in the chain of a pending request.
+///
+/// The requests linked after it are cancelled too, except [`Request::CLOSE`].
+fn cancel_linked(queue: &mut Queue, user_data: u64) {
+	for i in 0..queue.pending.len() {
+		let requests = &mut queue.pending[i].chain.requests;
+		// The requests are stored in reverse order, so everything before it runs after it.
+		let Some(n) = requests
+			.iter()
+			.position(|e| e.user_data == user_data && e.ty != Request::CLOSE)
+		else {
+			continue;
+		};
+		let mut cancelled = Vec::new();
+		let mut k = 0;
+		requests.retain(|e| {
+			k += 1;
+			let keep = k > n + 1 || e.ty == Request::CLOSE;
+			if !keep {
+				cancelled.push(e.user_data);
+			}
+			keep
+		});
+		for user_data in cancelled.into_iter().rev() {
+			push_resp(queue, user_data, Error::Cancelled as i64);
+		}
+		return;
+	}
 }
 
-fn poll_tickets(queue: &mut Queue, objects: &mut arena::Arena<Arc<dyn Object>, u8>) -> usize {
+/// Run the requests of a chain in order until one fails or has to wait.
+fn run_chain(queue: &mut Queue, objects: &mut Objects<'_>, mut e: Request, mut chain: Chain) {
+	loop {
+		let state = if e.flags & Request::LINK_HANDLE == 0 {
+			submit(objects, &e)
+		} else if let Some(handle) = chain.handle {
+			e.handle = handle;
+			submit(objects, &e)
+		} else if e.ty == Request::CLOSE {
+			Submit::NoResponse
+		} else {
+			Submit::Done(Error::InvalidObject as i64)
+		};
+		match state {
+			Submit::Done(value) => {
+				push_resp(queue, e.user_data, value);
+				if norostb_kernel::error::result(value).is_err() {
+					return cancel_chain(queue, objects, chain);
+				}
+				if matches!(e.ty, Request::OPEN | Request::CREATE) {
+					chain.handle = Some(value as Handle);
+				}
+			}
+			Submit::NoResponse => {}
+			Submit::Pending(buffer, ticket, audit) => {
+				return queue.pending.push(PendingTicket {
+					user_data: e.user_data,
+					handle: e.handle,
+					buffer,
+					ticket,
+					audit,
+					deadline: Monotonic::MAX,
+					chain,
+				});
+			}
+		}
+		let Some(next) = chain.requests.pop() else { return };
+		e = next;
+	}
+}
+
+/// Begin processing a single request.
+fn submit(objects: &mut Objects<'_>, e: &Request) -> Submit {
+	let handle = unerase_handle(e.handle);
+	let Some(object) = objects.get(handle) else {
+		return Submit::Done(Error::InvalidObject as i64);
+	};
+	match e.ty {
+		Request::READ | Request::PEEK => {
+			let data_ptr = e.arguments_64[0] as *mut u8;
+			let data_len = e.arguments_64[1] as usize;
+			let buf = UserBuffer::Slice(data_ptr, data_len);
+			let mut ticket = object
+				.clone()
+				.read(data_len.try_into().unwrap(), e.ty == Request::PEEK);
+			match poll(&mut ticket) {
+				Poll::Pending => Submit::Pending(buf, ticket.into(), Default::default()),
+				Poll::Ready(Ok(b)) => Submit::Done(buf.copy_from(b)),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::WRITE => {
+			let data_ptr = e.arguments_64[0] as *const u8;
+			let data_len = e.arguments_64[1] as usize;
+			let data = unsafe { core::slice::from_raw_parts(data_ptr, data_len) };
+			// Unlock the object table as the Stream table may need it.
+			// TODO we should instead pass a reference to the objects list or some Context object.
+			let object = object.clone();
+			let mut ticket = Guard::unlocked(objects, || object.write(data));
+			match poll(&mut ticket) {
+				Poll::Pending => {
+					Submit::Pending(UserBuffer::None, ticket.into(), Default::default())
+				}
+				Poll::Ready(Ok(b)) => Submit::Done(b.try_into().unwrap()),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::READ_AT => {
+			let [data_ptr, data_len, offset] = e.arguments_64;
+			let buf = UserBuffer::Slice(data_ptr as *mut u8, data_len as usize);
+			let mut ticket = object.clone().read_at(offset, data_len as usize);
+			match poll(&mut ticket) {
+				Poll::Pending => Submit::Pending(buf, ticket.into(), Default::default()),
+				Poll::Ready(Ok(b)) => Submit::Done(buf.copy_from(b)),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::WRITE_AT => {
+			let [data_ptr, data_len, offset] = e.arguments_64;
+			let data = unsafe { core::slice::from_raw_parts(data_ptr as *const u8, data_len as _) };
+			// TODO ditto
+			let object = object.clone();
+			let mut ticket = Guard::unlocked(objects, || object.write_at(offset, data));
+			match poll(&mut ticket) {
+				Poll::Pending => {
+					Submit::Pending(UserBuffer::None, ticket.into(), Default::default())
+				}
+				Poll::Ready(Ok(b)) => Submit::Done(b.try_into().unwrap()),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::READ_VECTORED => {
+			let [bufs_ptr, count, _] = e.arguments_64;
+			let count = count as usize;
+			if count > IO_SLICES_MAX {
+				return Submit::Done(Error::InvalidData as i64);
+			}
+			let bufs_ptr = bufs_ptr as *const IoSliceMut<'static>;
+			let bufs = unsafe { core::slice::from_raw_parts(bufs_ptr, count) };
+			// Copy the descriptors so they can't be changed while the request is in flight.
+			let bufs = bufs
+				.iter()
+				.map(|b| (b.as_slice().as_ptr() as *mut u8, b.as_slice().len()))
+				.collect::<Box<[_]>>();
+			let len = bufs.iter().try_fold(0usize, |s, &(_, l)| s.checked_add(l));
+			let Some(len) = len else { return Submit::Done(Error::InvalidData as i64) };
+			let buf = UserBuffer::Vectored(bufs);
+			let mut ticket = object.clone().read(len, false);
+			match poll(&mut ticket) {
+				Poll::Pending => Submit::Pending(buf, ticket.into(), Default::default()),
+				Poll::Ready(Ok(b)) => Submit::Done(buf.copy_from(b)),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::WRITE_VECTORED => {
+			let [bufs_ptr, count, _] = e.arguments_64;
+			let count = count as usize;
+			if count > IO_SLICES_MAX {
+				return Submit::Done(Error::InvalidData as i64);
+			}
+			let bufs = unsafe { core::slice::from_raw_parts(bufs_ptr as *const IoSlice, count) };
+			let data = bufs.iter().map(|b| b.as_slice()).collect::<Vec<_>>();
+			// TODO ditto
+			let object = object.clone();
+			let mut ticket = Guard::unlocked(objects, || object.write_vectored(&data));
+			match poll(&mut ticket) {
+				Poll::Pending => {
+					Submit::Pending(UserBuffer::None, ticket.into(), Default::default())
+				}
+				Poll::Ready(Ok(b)) => Submit::Done(b.try_into().unwrap()),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::OPEN => {
+			let path_ptr = e.arguments_64[0] as *const u8;
+			let path_len = e.arguments_64[1] as usize;
+			let path = unsafe { core::slice::from_raw_parts(path_ptr, path_len) };
+			let rec = audit::begin(Operation::Open, Some(e.handle), path);
+			let mut ticket = object.clone().open(path);
+			match poll(&mut ticket) {
+				Poll::Pending => Submit::Pending(UserBuffer::None, ticket.into(), rec),
+				Poll::Ready(r) => {
+					let r = r.map(|o| erase_handle(objects.insert(o)));
+					rec.finish(r.map(u64::from));
+					Submit::Done(r.map_or_else(|e| e as i64, i64::from))
+				}
+			}
+		}
+		Request::CREATE => {
+			let path_ptr = e.arguments_64[0] as *const u8;
+			let path_len = e.arguments_64[1] as usize;
+			let path = unsafe { core::slice::from_raw_parts(path_ptr, path_len) };
+			let rec = audit::begin(Operation::Create, Some(e.handle), path);
+			let mut ticket = object.clone().create(path);
+			match poll(&mut ticket) {
+				Poll::Pending => Submit::Pending(UserBuffer::None, ticket.into(), rec),
+				Poll::Ready(r) => {
+					let r = r.map(|o| erase_handle(objects.insert(o)));
+					rec.finish(r.map(u64::from));
+					Submit::Done(r.map_or_else(|e| e as i64, i64::from))
+				}
+			}
+		}
+		Request::SEEK => {
+			let direction = e.arguments_8[0];
+			let offset = e.arguments_64[0];
+
+			let Ok(from) = SeekFrom::try_from_raw(direction, offset) else {
+				warn!("Invalid offset ({}, {})", direction, offset);
+				return Submit::Done(-1);
+			};
+			let mut ticket = object.seek(from);
+			match poll(&mut ticket) {
+				Poll::Pending => {
+					Submit::Pending(UserBuffer::None, ticket.into(), Default::default())
+				}
+				Poll::Ready(Ok(n)) => Submit::Done(n as i64),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::CLOSE => {
+			// We are not supposed to return a response under any circumstances.
+			let _ = objects.remove(handle);
+			Submit::NoResponse
+		}
+		Request::SHARE => {
+			let share = unerase_handle(e.arguments_64[0] as Handle);
+			if let Some(shr) = objects.get(share) {
+				let rec = audit::begin(Operation::Share, Some(e.handle), &[]);
+				let mut ticket = object.clone().share(shr);
+				match poll(&mut ticket) {
+					Poll::Pending => Submit::Pending(UserBuffer::None, ticket.into(), rec),
+					Poll::Ready(r) => {
+						rec.finish(r);
+						Submit::Done(r.map_or_else(|e| e as i64, |n| n as i64))
+					}
+				}
+			} else {
+				Submit::Done(Error::InvalidObject as i64)
+			}
+		}
+		Request::GET_META => {
+			let [prop_len, val_len] = e.arguments_8;
+			let [prop_ptr, val_ptr, _] = e.arguments_64;
+			let prop = unsafe { TinySlice::from_raw_parts(prop_ptr as *const u8, prop_len) };
+			let mut ticket = object.clone().get_meta(prop);
+			let buf = UserBuffer::Slice(val_ptr as *mut u8, val_len.into());
+			match poll(&mut ticket) {
+				Poll::Pending => Submit::Pending(buf, ticket.into(), Default::default()),
+				Poll::Ready(Ok(b)) => Submit::Done(buf.copy_from(b)),
+				Poll::Ready(Err(e)) => Submit::Done(e as i64),
+			}
+		}
+		Request::SET_META => todo!(),
+		Request::DESTROY => todo!(),
+		op => {
+			warn!("Unknown I/O queue operation {}", op);
+			Submit::Done(Error::InvalidOperation as i64)
+		}
+	}
+}
+
+fn poll_tickets(queue: &mut Queue, objects: &mut Objects<'_>) -> usize {
+	poll_tickets_with(queue, objects, |_| ())
+}
+
+/// Poll pending tickets, calling `finished` with the handle of each request that finished.
+fn poll_tickets_with(
+	queue: &mut Queue,
+	objects: &mut Objects<'_>,
+	mut finished: impl FnMut(Handle),
+) -> usize {
 	let mut polls = 0;
+	let now = Monotonic::now();
 	for i in (0..queue.pending.len()).rev() {
 		match poll(&mut queue.pending[i].ticket) {
+			Poll::Pending if queue.pending[i].deadline <= now => {
+				polls += 1;
+				finished(queue.pending[i].handle);
+				cancel(queue, objects, i, Error::TimedOut);
+			}
 			Poll::Pending => {}
 			Poll::Ready(r) => {
 				polls += 1;
 				let tk = queue.pending.swap_remove(i);
-				let mut push_resp = |value| push_resp(queue, tk.user_data, value);
-				match r {
+				finished(tk.handle);
+				let mut chain = tk.chain;
+				let value = match r {
 					Ok(AnyTicketValue::Object(o)) => {
-						push_resp(erase_handle(objects.insert(o)).try_into().unwrap())
+						let handle = erase_handle(objects.insert(o));
+						chain.handle = Some(handle);
+						handle.into()
 					}
-					Ok(AnyTicketValue::U64(n)) => push_resp(n as i64),
-					Ok(AnyTicketValue::Data(b)) => {
-						let data =
-							unsafe { core::slice::from_raw_parts_mut(tk.data_ptr, tk.data_len) };
-						let len = b.len().min(data.len());
-						data[..len].copy_from_slice(&b[..len]);
-						push_resp(len.try_into().unwrap())
-					}
-					Err(e) => push_resp(e as i64),
+					Ok(AnyTicketValue::U64(n)) => n as i64,
+					Ok(AnyTicketValue::Data(b)) => tk.buffer.copy_from(b),
+					Err(e) => e as i64,
+				};
+				tk.audit
+					.finish(norostb_kernel::error::result(value).map(|v| v as u64));
+				push_resp(queue, tk.user_data, value);
+				if norostb_kernel::error::result(value).is_err() {
+					cancel_chain(queue, objects, chain);
+				} else if let Some(e) = chain.requests.pop() {
+					run_chain(queue, objects, e, chain);
 				}
 			}
 		}
@@ -332,6 +617,30 @@ fn poll_tickets(queue: &mut Queue, objects: &mut arena::Arena<Arc<dyn Object>, u
 	polls
 }
 
+/// Cancel a pending ticket and the requests linked to it.
+fn cancel(queue: &mut Queue, objects: &mut Objects<'_>, index: usize, error: Error) {
+	let tk = queue.pending.swap_remove(index);
+	tk.ticket.cancel();
+	tk.audit.finish(Err(error));
+	push_resp(queue, tk.user_data, error as i64);
+	cancel_chain(queue, objects, tk.chain);
+}
+
+/// Cancel requests that will not run due to a preceding request failing.
+///
+/// Close requests are still run to avoid leaking objects opened in the chain.
+fn cancel_chain(queue: &mut Queue, objects: &mut Objects<'_>, chain: Chain) {
+	for e in chain.requests.into_iter().rev() {
+		if e.ty != Request::CLOSE {
+			push_resp(queue, e.user_data, Error::Cancelled as i64);
+		} else if e.flags & Request::LINK_HANDLE == 0 {
+			let _ = objects.remove(unerase_handle(e.handle));
+		} else if let Some(handle) = chain.handle {
+			let _ = objects.remove(unerase_handle(handle));
+		}
+	}
+}
+
 fn push_resp(queue: &mut Queue, user_data: u64, value: i64) {
 	// It is the responsibility of the user process to ensure no more requests are in
 	// flight than there is space for responses.
@@ -342,11 +651,32 @@ fn push_resp(queue: &mut Queue, user_data: u64, value: i64) {
 	};
 }
 
-fn copy_data_to(to_ptr: *mut u8, to_len: usize, from: Box<[u8]>) -> i64 {
-	let data = unsafe { core::slice::from_raw_parts_mut(to_ptr, to_len) };
-	let len = from.len().min(data.len());
-	data[..len].copy_from_slice(&from[..len]);
-	len.try_into().unwrap()
+/// A buffer in user memory.
+pub(super) enum UserBuffer {
+	None,
+	Slice(*mut u8, usize),
+	/// Copies of the [`IoSliceMut`]s of a request, which are filled in order.
+	Vectored(Box<[(*mut u8, usize)]>),
+}
+
+impl UserBuffer {
+	/// Copy as much data as fits, returning the amount of bytes copied.
+	fn copy_from(self, from: Box<[u8]>) -> i64 {
+		let mut from = &from[..];
+		let mut copy = |ptr, len| {
+			let data = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
+			let len = from.len().min(data.len());
+			data[..len].copy_from_slice(&from[..len]);
+			from = &from[len..];
+			len
+		};
+		let len = match self {
+			Self::None => 0,
+			Self::Slice(ptr, len) => copy(ptr, len),
+			Self::Vectored(bufs) => bufs.iter().map(|&(ptr, len)| copy(ptr, len)).sum(),
+		};
+		len.try_into().unwrap()
+	}
 }
 
 pub enum RemoveQueueError {
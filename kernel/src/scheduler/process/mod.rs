@@ -4,14 +4,15 @@ mod table;
 
 use super::{MemoryObject, Thread};
 use crate::{
-	arch,
+	arch, audit,
 	memory::{
 		frame::{self, AllocateHints},
-		r#virtual::{AddressSpace, MapError, UnmapError, RWX},
+		r#virtual::{phys_to_virt, AddressSpace, MapError, UnmapError, RWX},
 		Page,
 	},
 	object_table::{AnyTicket, Error, Object, Ticket, TicketWaker, TinySlice},
 	sync::{Mutex, SpinLock},
+	time::Monotonic,
 	util::{erase_handle, unerase_handle},
 };
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
@@ -19,13 +20,18 @@ use arena::Arena;
 use core::{
 	num::NonZeroUsize,
 	ptr::NonNull,
-	sync::atomic::{AtomicU8, Ordering},
+	sync::atomic::{AtomicU64, AtomicU8, Ordering},
 };
 use norostb_kernel::Handle;
 
+pub use io::WaitAnyError;
 pub use table::post_init;
 
+/// The ID of the next process. `0` is reserved for the kernel.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
 pub struct Process {
+	id: u64,
 	address_space: SpinLock<AddressSpace>,
 	hint_color: u8,
 	threads: SpinLock<Arena<Arc<Thread>, u8>>,
@@ -37,14 +43,22 @@ pub struct Process {
 
 struct PendingTicket {
 	user_data: u64,
-	data_ptr: *mut u8,
-	data_len: usize,
+	/// The object the request was submitted to.
+	handle: Handle,
+	/// Where to copy data to when the ticket finishes.
+	buffer: io::UserBuffer,
 	ticket: AnyTicket,
+	audit: audit::Record,
+	/// The ticket is cancelled if it hasn't finished by this time.
+	deadline: Monotonic,
+	/// Requests linked to this request.
+	chain: io::Chain,
 }
 
 impl Process {
 	fn new() -> Result<Self, frame::AllocateError> {
 		Ok(Self {
+			id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
 			address_space: SpinLock::new(AddressSpace::new()?),
 			hint_color: 0,
 			threads: Default::default(),
@@ -55,6 +69,11 @@ impl Process {
 		})
 	}
 
+	/// The unique ID of this process.
+	pub fn id(&self) -> u64 {
+		self.id
+	}
+
 	pub unsafe fn activate_address_space(&self) {
 		unsafe { self.address_space.isr_lock().activate() };
 	}
@@ -118,6 +137,45 @@ impl Process {
 		self.address_space.lock().unmap_object(base, count)
 	}
 
+	/// Get the physical address an address in this process' address space is mapped to.
+	pub fn get_physical_address(&self, address: NonNull<()>) -> Option<u64> {
+		self.address_space.lock().get_physical_address(address)
+	}
+
+	/// Call `f` with the physical address an address in this process' address space is mapped
+	/// to.
+	///
+	/// The address space stays locked while `f` runs, so the address remains mapped and the
+	/// backing frame can't be freed. `f` must not sleep.
+	pub fn with_physical_address<R>(
+		&self,
+		address: NonNull<()>,
+		f: impl FnOnce(u64) -> R,
+	) -> Option<R> {
+		let address_space = self.address_space.lock();
+		address_space.get_physical_address(address).map(f)
+	}
+
+	/// Copy a range of memory in this process' address space.
+	///
+	/// Returns `None` if any part of the range isn't mapped user memory.
+	pub fn copy_from_user(&self, address: usize, len: usize) -> Option<Box<[u8]>> {
+		let end = address.checked_add(len)?;
+		let mut buf = Vec::with_capacity(len);
+		let address_space = self.address_space.lock();
+		let mut a = address;
+		while a < end {
+			let n = (Page::SIZE - (a & Page::MASK)).min(end - a);
+			let phys = address_space.get_physical_address(NonNull::new(a as *mut ())?)?;
+			// SAFETY: the range is mapped and doesn't cross a page boundary. The address space is
+			// locked, so the frame can't be freed while copying.
+			let s = unsafe { core::slice::from_raw_parts(phys_to_virt(phys), n) };
+			buf.extend_from_slice(s);
+			a += n;
+		}
+		Some(buf.into())
+	}
+
 	/// Duplicate a reference to an object.
 	pub fn duplicate_object_handle(&self, handle: Handle) -> Option<Handle> {
 		let mut objects = self.objects.lock();
@@ -1,4 +1,5 @@
 use crate::{
+	audit::{self, Operation},
 	memory::frame::{AllocateHints, OwnedPageFrames},
 	object_table::{Error, Object, Ticket},
 	scheduler::MemoryObject,
@@ -66,15 +67,15 @@ impl Object for ProcessBuilder {
 
 	fn create(self: Arc<Self>, path: &[u8]) -> Ticket<Arc<dyn Object>> {
 		Ticket::new_complete(if path == b"spawn" {
-			Ok({
-				super::Process::from_elf(
-					self.bin.take().unwrap(),
-					self.stack.take().take(),
-					0,
-					self.objects.take(),
-				)
-				.unwrap()
-			})
+			let process = super::Process::from_elf(
+				self.bin.take().unwrap(),
+				self.stack.take().take(),
+				0,
+				self.objects.take(),
+			)
+			.unwrap();
+			audit::record(Operation::Spawn, None, b"", Ok(process.id()));
+			Ok(process)
 		} else {
 			Err(Error::CantCreateObject)
 		})
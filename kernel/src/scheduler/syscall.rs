@@ -1,6 +1,7 @@
 use crate::time::Monotonic;
 use crate::{
 	arch,
+	audit::{self, Operation},
 	memory::{
 		frame,
 		frame::OwnedPageFrames,
@@ -8,18 +9,27 @@ use crate::{
 		Page,
 	},
 	object_table::{
-		pipe, Handle, NewStreamingTableError, Object, Root, SeekFrom, StreamingTable, SubRange,
-		TinySlice,
+		event, pipe, pty, timer, Attenuate, Handle, NewStreamingTableError, Object, Root, SeekFrom,
+		StreamingTable, SubRange, TinySlice,
+	},
+	scheduler::{
+		self, futex,
+		process::{Process, WaitAnyError},
+		Thread,
 	},
-	scheduler::{self, process::Process, Thread},
 	util::{erase_handle, unerase_handle},
 };
-use alloc::{boxed::Box, sync::Arc};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use core::mem;
 use core::num::NonZeroUsize;
 use core::ptr::NonNull;
 use core::time::Duration;
-use norostb_kernel::{error::Error, io::Request, object::NewObject};
+use norostb_kernel::{
+	error::Error,
+	io::{IoSlice, IoSliceMut, Request, IO_SLICES_MAX},
+	object::{NewObject, ATTENUATE_PREFIX_MAX},
+	syscall::WAIT_ANY_MAX,
+};
 
 #[derive(Clone, Copy)]
 #[repr(C)]
@@ -50,7 +60,7 @@ impl Return {
 
 type Syscall = extern "C" fn(usize, usize, usize, usize, usize, usize) -> Return;
 
-pub const SYSCALLS_LEN: usize = 15;
+pub const SYSCALLS_LEN: usize = 18;
 
 /// Helper type to ensure the syscall table is aligned to a cache boundary, which
 /// improves efficiency when using the first 8 syscalls (which all fit inside a single
@@ -76,6 +86,9 @@ static SYSCALLS: SyscallTable = SyscallTable([
 	exit_thread,
 	create_io_queue,
 	destroy_io_queue,
+	futex_wait,
+	futex_wake,
+	wait_any,
 ]);
 
 fn raw_to_rwx(rwx: usize) -> Option<RWX> {
@@ -180,7 +193,6 @@ extern "C" fn do_io(ty: usize, handle: usize, a: usize, b: usize, c: usize, _: u
 			status: 0,
 			value: r as _,
 		};
-		let ins = |l: &mut arena::Arena<_, _>, o| Return::handle(erase_handle(l.insert(o)));
 		match ty {
 			Request::READ | Request::PEEK => block_on(o.clone().read(b, ty == Request::PEEK))
 				.map_or_else(Return::error, |r| {
@@ -198,6 +210,57 @@ extern "C" fn do_io(ty: usize, handle: usize, a: usize, b: usize, c: usize, _: u
 					value: r.try_into().unwrap(),
 				})
 			}
+			Request::READ_AT => {
+				block_on(o.clone().read_at(c as _, b)).map_or_else(Return::error, |r| {
+					assert!(r.len() <= b, "object returned too much data");
+					unsafe { (a as *mut u8).copy_from_nonoverlapping(r.as_ptr(), r.len()) }
+					Return {
+						status: 0,
+						value: r.len(),
+					}
+				})
+			}
+			Request::WRITE_AT => {
+				let r = unsafe { core::slice::from_raw_parts(a as *const u8, b) };
+				block_on(o.clone().write_at(c as _, r)).map_or_else(Return::error, |r| Return {
+					status: 0,
+					value: r.try_into().unwrap(),
+				})
+			}
+			Request::READ_VECTORED | Request::WRITE_VECTORED if b > IO_SLICES_MAX => {
+				Return::INVALID_DATA
+			}
+			Request::READ_VECTORED => {
+				let bufs = unsafe { core::slice::from_raw_parts(a as *const IoSliceMut, b) };
+				let Some(len) = bufs
+					.iter()
+					.try_fold(0usize, |n, b| n.checked_add(b.as_slice().len()))
+				else {
+					return Return::INVALID_DATA;
+				};
+				block_on(o.clone().read(len, false)).map_or_else(Return::error, |r| {
+					assert!(r.len() <= len, "object returned too much data");
+					let (total, mut r) = (r.len(), &r[..]);
+					for b in bufs {
+						let l = r.len().min(b.as_slice().len());
+						let d = b.as_slice().as_ptr() as *mut u8;
+						unsafe { d.copy_from_nonoverlapping(r.as_ptr(), l) }
+						r = &r[l..];
+					}
+					Return {
+						status: 0,
+						value: total - r.len(),
+					}
+				})
+			}
+			Request::WRITE_VECTORED => {
+				let bufs = unsafe { core::slice::from_raw_parts(a as *const IoSlice, b) };
+				let data = bufs.iter().map(|b| b.as_slice()).collect::<Vec<_>>();
+				block_on(o.clone().write_vectored(&data)).map_or_else(Return::error, |r| Return {
+					status: 0,
+					value: r.try_into().unwrap(),
+				})
+			}
 			Request::GET_META => {
 				let (prop_len, value_len) = (c as u8, (c >> 8) as u8);
 				let prop = unsafe { TinySlice::from_raw_parts(a as *const u8, prop_len) };
@@ -225,16 +288,22 @@ extern "C" fn do_io(ty: usize, handle: usize, a: usize, b: usize, c: usize, _: u
 			Request::OPEN | Request::CREATE => {
 				let r = unsafe { core::slice::from_raw_parts(a as *const u8, b) };
 				let o = o.clone();
-				block_on(if ty == Request::OPEN {
-					o.open(r)
+				let (op, t) = if ty == Request::OPEN {
+					(Operation::Open, o.open(r))
 				} else {
-					o.create(r)
-				})
-				.map_or_else(Return::error, |o| ins(objects, o))
+					(Operation::Create, o.create(r))
+				};
+				let rec = audit::begin(op, Some(erase_handle(handle)), r);
+				let r = block_on(t).map(|o| erase_handle(objects.insert(o)));
+				rec.finish(r.map(u64::from));
+				r.map_or_else(Return::error, Return::handle)
 			}
 			Request::DESTROY => {
 				let r = unsafe { core::slice::from_raw_parts(a as *const u8, b) };
-				block_on(o.clone().destroy(r)).map_or_else(Return::error, return_u64)
+				let rec = audit::begin(Operation::Destroy, Some(erase_handle(handle)), r);
+				let r = block_on(o.clone().destroy(r));
+				rec.finish(r);
+				r.map_or_else(Return::error, return_u64)
 			}
 			Request::SEEK => a
 				.try_into()
@@ -249,11 +318,16 @@ extern "C" fn do_io(ty: usize, handle: usize, a: usize, b: usize, c: usize, _: u
 					.map_or(Error::InvalidObject as _, |_| 0),
 				value: 0,
 			},
-			Request::SHARE => objects
-				.get(unerase_handle(a as _))
-				.map_or(Return::INVALID_OBJECT, |s| {
-					block_on(o.share(s)).map_or_else(Return::error, return_u64)
-				}),
+			Request::SHARE => {
+				objects
+					.get(unerase_handle(a as _))
+					.map_or(Return::INVALID_OBJECT, |s| {
+						let rec = audit::begin(Operation::Share, Some(erase_handle(handle)), &[]);
+						let r = block_on(o.share(s));
+						rec.finish(r);
+						r.map_or_else(Return::error, return_u64)
+					})
+			}
 			_ => Return::INVALID_OPERATION,
 		}
 	})
@@ -333,6 +407,33 @@ extern "C" fn new_object(ty: usize, a: usize, b: usize, c: usize, _: usize, _: u
 			.unwrap_or(Err(Error::InvalidObject))
 			.map(|o| [o, u32::MAX]),
 		NewObject::Pipe => proc.add_objects(pipe::new()).map_err(|e| match e {}),
+		NewObject::Pty => proc.add_objects(pty::new()).map_err(|e| match e {}),
+		NewObject::Attenuate {
+			handle,
+			allow,
+			prefix,
+		} => (prefix.len <= ATTENUATE_PREFIX_MAX)
+			.then(|| proc.copy_from_user(prefix.ptr, prefix.len))
+			.flatten()
+			.ok_or(Error::InvalidData)
+			.and_then(|prefix| {
+				proc.object_transform_new(handle, |o| {
+					Ok::<_, Error>(Attenuate::new(o.clone(), allow, &prefix) as Arc<dyn Object>)
+				})
+				.unwrap_or(Err(Error::InvalidObject))
+			})
+			.map(|o| [o, u32::MAX]),
+		NewObject::Timer { deadline, interval } => proc
+			.add_object(timer::new(
+				Monotonic::from_nanos(deadline.as_nanos()),
+				interval,
+			))
+			.map_err(|e| match e {})
+			.map(|o| [o, u32::MAX]),
+		NewObject::Event => proc
+			.add_object(event::new())
+			.map_err(|e| match e {})
+			.map(|o| [o, u32::MAX]),
 	}
 	.map_or_else(
 		|e| Return {
@@ -621,6 +722,83 @@ extern "C" fn exit(code: usize, _: usize, _: usize, _: usize, _: usize, _: usize
 	}
 }
 
+extern "C" fn futex_wait(
+	address: usize,
+	expected: usize,
+	timeout_l: usize,
+	timeout_h: usize,
+	_: usize,
+	_: usize,
+) -> Return {
+	debug!("futex_wait {:#x} {}", address, expected);
+	let Some(address) = futex_address(address) else {
+		return Return::INVALID_DATA;
+	};
+	let timeout = merge_u64(timeout_l, timeout_h);
+	let timeout = Duration::from_nanos(timeout.into());
+	let process = Process::current().unwrap();
+	match futex::wait(&process, address, expected as u32, timeout) {
+		Ok(()) | Err(futex::WaitError::Mismatch) => Return {
+			status: 0,
+			value: 0,
+		},
+		Err(futex::WaitError::Unmapped) => Return::INVALID_DATA,
+		Err(futex::WaitError::TimedOut) => Return::error(Error::TimedOut),
+	}
+}
+
+extern "C" fn futex_wake(
+	address: usize,
+	count: usize,
+	_: usize,
+	_: usize,
+	_: usize,
+	_: usize,
+) -> Return {
+	debug!("futex_wake {:#x} {}", address, count);
+	let process = Process::current().unwrap();
+	let Some(address) = futex_address(address).and_then(|a| process.get_physical_address(a)) else {
+		return Return::INVALID_DATA;
+	};
+	Return {
+		status: 0,
+		value: futex::wake(address, count),
+	}
+}
+
+extern "C" fn wait_any(
+	handles: usize,
+	len: usize,
+	timeout_l: usize,
+	timeout_h: usize,
+	_: usize,
+	_: usize,
+) -> Return {
+	debug!("wait_any {:#x} {}", handles, len);
+	if len > WAIT_ANY_MAX {
+		return Return::INVALID_DATA;
+	}
+	// Copy the handles as the thread may sleep and the user could free the buffer meanwhile.
+	let handles = unsafe { core::slice::from_raw_parts(handles as *const Handle, len) }.to_vec();
+	let timeout = merge_u64(timeout_l, timeout_h);
+	let timeout = Duration::from_nanos(timeout.into());
+	match Process::current().unwrap().wait_any(&handles, timeout) {
+		Ok(i) => Return {
+			status: 0,
+			value: i,
+		},
+		Err(WaitAnyError::NoPendingRequests) => Return::error(Error::InvalidOperation),
+		Err(WaitAnyError::TimedOut) => Return::error(Error::TimedOut),
+	}
+}
+
+/// Check the address of a futex, which must be aligned to 4 bytes.
+fn futex_address(address: usize) -> Option<NonNull<()>> {
+	(address % 4 == 0)
+		.then(|| NonNull::new(address as *mut ()))
+		.flatten()
+}
+
 fn merge_u64(l: usize, h: usize) -> u64 {
 	match mem::size_of_val(&l) {
 		4 => (h as u64) << 32 | l as u64,
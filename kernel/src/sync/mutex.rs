@@ -58,6 +58,19 @@ pub struct Guard<'a, T> {
 	lock: &'a Mutex<T>,
 }
 
+impl<T> Guard<'_, T> {
+	/// Unlock the mutex while running the given closure and lock it again afterwards.
+	#[cfg_attr(debug_assertions, track_caller)]
+	pub fn unlocked<R>(guard: &mut Self, f: impl FnOnce() -> R) -> R {
+		guard.lock.lock.unlock();
+		let r = f();
+		while !guard.lock.lock.try_lock() {
+			Thread::yield_current()
+		}
+		r
+	}
+}
+
 impl<T> Deref for Guard<'_, T> {
 	type Target = T;
 
@@ -1,7 +1,7 @@
 use crate::{
 	io::{Buf, Read},
 	object::{file_root, RefAsyncObject},
-	AsyncObject,
+	queue, AsyncObject,
 };
 use alloc::vec::Vec;
 use rt::io;
@@ -10,6 +10,7 @@ pub struct File(AsyncObject);
 
 impl_wrap!(File read);
 impl_wrap!(File write);
+impl_wrap!(File seek);
 
 impl File {
 	pub async fn open<B: Buf>(&self, path: B) -> (io::Result<File>, B) {
@@ -24,12 +25,23 @@ impl File {
 }
 
 pub async fn read<B: Buf>(path: B) -> (io::Result<Vec<u8>>, B) {
-	let (f, path) = file_root().open(path).await;
+	// Open the file and read the first chunk without waiting for the open request to finish.
+	let root = file_root().as_raw();
+	let (f, r, path, mut v) = queue::submit2(
+		|q, p, b| q.submit_open_read(root, p, b),
+		path,
+		Vec::with_capacity(2048),
+	)
+	.await;
 	let f = match f {
-		Ok(f) => f,
+		Ok(f) => AsyncObject::from_raw(f),
 		Err(e) => return (Err(e), path),
 	};
-	let mut v = Vec::new();
+	match r {
+		Ok(0) => return (Ok(v), path),
+		Ok(_) => {}
+		Err(e) => return (Err(e), path),
+	}
 	loop {
 		v.reserve(2048);
 		let l = v.len();
@@ -17,6 +17,7 @@ pub mod net;
 pub mod process;
 pub mod queue;
 pub mod task;
+pub mod time;
 #[macro_use]
 mod macros;
 
@@ -35,7 +35,7 @@ macro_rules! eprintln {
 	($($arg:tt)*) => {
 		(async {
 			$crate::io::__eprint(format_args!($($arg)*)).await;
-			$crate::print!("\n").await;
+			$crate::eprint!("\n").await;
 		})
 	};
 }
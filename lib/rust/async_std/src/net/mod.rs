@@ -10,10 +10,7 @@ impl TcpListener {
 	pub async fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
 		let root = rt::io::net_root().expect("no net root");
 		let mut last_err = io::Error::InvalidData;
-		for a in addr
-			.to_socket_addrs()
-			.unwrap_or_else(|_| todo!("convert error"))
-		{
+		for a in addr.to_socket_addrs().map_err(|_| io::Error::InvalidData)? {
 			let a = into_ip6(a);
 			let path = format!("{}/tcp/listen/{}", a.ip(), a.port());
 			match root.create(path.as_bytes()) {
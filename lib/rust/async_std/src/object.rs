@@ -30,6 +30,10 @@ impl AsyncObject {
 		(res.map(Self), b)
 	}
 
+	pub async fn share(&self, share: &AsyncObject) -> io::Result<u64> {
+		queue::submit(|q, ()| q.submit_share(self.0, share.0), ()).await
+	}
+
 	pub async fn get_meta<B, Bm>(&self, property: B, value: Bm) -> (io::Result<u8>, B, Bm)
 	where
 		B: Buf,
@@ -75,6 +79,14 @@ impl<B: io::Buf> io::Write<B> for AsyncObject {
 	}
 }
 
+impl io::Seek for AsyncObject {
+	type Future = io_queue_rt::Seek<'static>;
+
+	fn seek(&self, from: io::SeekFrom) -> Self::Future {
+		queue::submit(|q, ()| q.submit_seek(self.0, from), ())
+	}
+}
+
 macro_rules! impl_wrap {
 	($ty:ident read) => {
 		impl<B: crate::io::BufMut> crate::io::Read<B> for $ty {
@@ -94,6 +106,15 @@ macro_rules! impl_wrap {
 			}
 		}
 	};
+	($ty:ident seek) => {
+		impl crate::io::Seek for $ty {
+			type Future = <$crate::object::AsyncObject as crate::io::Seek>::Future;
+
+			fn seek(&self, from: crate::io::SeekFrom) -> Self::Future {
+				self.0.seek(from)
+			}
+		}
+	};
 }
 
 impl Drop for AsyncObject {
@@ -154,6 +175,10 @@ pub fn file_root() -> RefAsyncObject<'static> {
 	RefAsyncObject::from(io::file_root().expect("no file root"))
 }
 
+pub fn net_root() -> RefAsyncObject<'static> {
+	RefAsyncObject::from(io::net_root().expect("no net root"))
+}
+
 pub fn process_root() -> RefAsyncObject<'static> {
 	RefAsyncObject::from(io::process_root().expect("no process root"))
 }
@@ -14,6 +14,8 @@ pub struct Command {
 	stdin: Stdio,
 	stdout: Stdio,
 	stderr: Stdio,
+	/// Root handles to pass instead of the inherited ones. `None` passes no handle.
+	roots: Vec<(u32, Option<AsyncObject>)>,
 }
 
 // Make everything async just in case we need it in the future (hah)
@@ -26,6 +28,7 @@ impl Command {
 			stdin: Stdio(StdioTy::Inherit),
 			stdout: Stdio(StdioTy::Inherit),
 			stderr: Stdio(StdioTy::Inherit),
+			roots: Default::default(),
 		}
 	}
 
@@ -55,17 +58,43 @@ impl Command {
 		self
 	}
 
+	pub async fn file_root(&mut self, root: Option<AsyncObject>) -> &mut Self {
+		self.root(rt::args::ID_FILE_ROOT, root)
+	}
+
+	pub async fn net_root(&mut self, root: Option<AsyncObject>) -> &mut Self {
+		self.root(rt::args::ID_NET_ROOT, root)
+	}
+
+	pub async fn process_root(&mut self, root: Option<AsyncObject>) -> &mut Self {
+		self.root(rt::args::ID_PROCESS_ROOT, root)
+	}
+
+	fn root(&mut self, ty: u32, root: Option<AsyncObject>) -> &mut Self {
+		self.roots.retain(|(t, _)| *t != ty);
+		self.roots.push((ty, root));
+		self
+	}
+
 	pub async fn spawn(&mut self) -> io::Result<Child> {
 		let mut stdin = None;
 		let mut stdout = None;
 		let mut stderr = None;
 
+		// All standard streams that use a PTY share the same one.
+		let pty = [&self.stdin, &self.stdout, &self.stderr]
+			.iter()
+			.any(|s| matches!(s.0, StdioTy::Pty))
+			.then(|| rt::Object::new(rt::NewObject::Pty))
+			.transpose()?;
+
 		fn io<'a>(
 			n: u32,
 			io: &mut Stdio,
 			og: Option<rt::RefObject<'a>>,
 			sto: &'a mut Option<(rt::Object, rt::Object)>,
 			dir: bool,
+			pty: Option<&(rt::Object, rt::Object)>,
 		) -> io::Result<impl Iterator<Item = (u32, rt::RefObject<'a>)>> {
 			let io = mem::replace(&mut io.0, StdioTy::Inherit);
 			Ok(match io {
@@ -77,6 +106,11 @@ impl Command {
 					*sto = Some((a, b));
 					sto.as_ref().map(|(_, o)| rt::RefObject::from(o))
 				}
+				StdioTy::Pty => {
+					let (master, slave) = pty.expect("no PTY was created");
+					*sto = Some((master.duplicate()?, slave.duplicate()?));
+					sto.as_ref().map(|(_, o)| rt::RefObject::from(o))
+				}
 			}
 			.into_iter()
 			.map(move |o| (n, o)))
@@ -89,22 +123,39 @@ impl Command {
 		let proc = rt::process::Process::new(
 			rt::io::process_root().unwrap(),
 			&res?,
-			io(ID_STDIN, &mut self.stdin, io::stdin(), &mut stdin, false)?
-				.chain(io(
-					ID_STDOUT,
-					&mut self.stdout,
-					io::stdout(),
-					&mut stdout,
-					true,
-				)?)
-				.chain(io(
-					ID_STDERR,
-					&mut self.stderr,
-					io::stderr(),
-					&mut stderr,
-					true,
-				)?)
-				.chain(rt::process::Process::default_root_handles()),
+			io(
+				ID_STDIN,
+				&mut self.stdin,
+				io::stdin(),
+				&mut stdin,
+				false,
+				pty.as_ref(),
+			)?
+			.chain(io(
+				ID_STDOUT,
+				&mut self.stdout,
+				io::stdout(),
+				&mut stdout,
+				true,
+				pty.as_ref(),
+			)?)
+			.chain(io(
+				ID_STDERR,
+				&mut self.stderr,
+				io::stderr(),
+				&mut stderr,
+				true,
+				pty.as_ref(),
+			)?)
+			.chain(
+				rt::process::Process::default_root_handles()
+					.filter(|(ty, _)| self.roots.iter().all(|(t, _)| t != ty)),
+			)
+			.chain(
+				self.roots
+					.iter()
+					.flat_map(|(ty, r)| r.as_ref().map(|r| (*ty, r.into()))),
+			),
 			self.args.iter(),
 			self.env.iter().map(|(a, b)| (a, b)),
 		)?;
@@ -11,6 +11,7 @@ enum StdioTy {
 	Piped,
 	Inherit,
 	Null,
+	Pty,
 }
 
 impl Stdio {
@@ -25,4 +26,11 @@ impl Stdio {
 	pub fn null() -> Self {
 		Self(StdioTy::Null)
 	}
+
+	/// Connect the stream to the slave side of a PTY.
+	///
+	/// All streams of a process that use this share the same PTY.
+	pub fn pty() -> Self {
+		Self(StdioTy::Pty)
+	}
 }
@@ -57,6 +57,20 @@ pub fn wait(timeout: Duration) -> rt::time::Monotonic {
 	t
 }
 
+/// Wait until a request on any of the given objects finishes or the timeout expires.
+///
+/// Returns the index of the handle of the object, if any.
+pub fn wait_any(handles: &[rt::Handle], timeout: Duration) -> (Option<usize>, rt::time::Monotonic) {
+	let q = get();
+	// Process responses of requests that finished immediately so we don't block on them.
+	q.poll();
+	q.process();
+	let i = q.wait_any(handles, timeout);
+	let t = q.poll();
+	q.process();
+	(i, t)
+}
+
 pub fn get() -> &'static Queue {
 	// Get or allocate key
 	let mut key = IO_QUEUE_KEY.load(Ordering::Relaxed);
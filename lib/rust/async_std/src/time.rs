@@ -0,0 +1,67 @@
+//! # Timers
+//!
+//! Timers are kernel objects, so waiting on them is an ordinary I/O queue request which can be
+//! polled alongside any other request.
+
+use crate::{io, queue, AsyncObject};
+use core::{future::Future, time::Duration};
+pub use rt::time::Monotonic;
+
+/// Sleep for at least the given duration.
+pub async fn sleep(duration: Duration) {
+	sleep_until(Monotonic::now().saturating_add(duration)).await
+}
+
+/// Sleep until the given deadline has passed.
+pub async fn sleep_until(deadline: Monotonic) {
+	let timer = new_timer(deadline, None);
+	queue::submit(|q, ()| q.submit_wait_timer(timer.as_raw()), ())
+		.await
+		.expect("failed to wait for timer");
+}
+
+/// Run a future, failing with [`io::Error::TimedOut`] if it doesn't finish in time.
+pub async fn timeout<F: Future>(duration: Duration, fut: F) -> io::Result<F::Output> {
+	futures_lite::future::or(async { Ok(fut.await) }, async {
+		sleep(duration).await;
+		Err(io::Error::TimedOut)
+	})
+	.await
+}
+
+/// A timer that expires periodically.
+pub struct Interval {
+	timer: AsyncObject,
+}
+
+impl Interval {
+	/// Create a timer that expires every `period`, starting one period from now.
+	pub fn new(period: Duration) -> Self {
+		let start = Monotonic::now().saturating_add(period);
+		Self {
+			timer: new_timer(start, Some(period)),
+		}
+	}
+
+	/// Wait for the next period, returning the amount of periods that passed since the last
+	/// tick.
+	pub async fn tick(&mut self) -> u64 {
+		queue::submit(|q, ()| q.submit_wait_timer(self.timer.as_raw()), ())
+			.await
+			.expect("failed to wait for timer")
+	}
+
+	/// Get the amount of periods that passed since the last tick without waiting or resetting
+	/// it.
+	pub async fn peek(&self) -> u64 {
+		queue::submit(|q, ()| q.submit_peek_timer(self.timer.as_raw()), ())
+			.await
+			.expect("failed to peek timer")
+	}
+}
+
+fn new_timer(deadline: Monotonic, interval: Option<Duration>) -> AsyncObject {
+	rt::Object::new(rt::NewObject::Timer { deadline, interval })
+		.map(|(t, _)| t.into())
+		.expect("failed to create timer")
+}
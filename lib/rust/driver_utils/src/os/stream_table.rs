@@ -1,5 +1,9 @@
 use crate::Handle;
-use core::{cell::RefCell, ops::Deref};
+use alloc::vec::Vec;
+use core::{
+	cell::{Cell, RefCell},
+	ops::Deref,
+};
 use nora_stream_table::{Buffers, ServerQueue, Slice};
 use norostb_rt::{
 	self as rt,
@@ -15,6 +19,7 @@ pub struct StreamTable {
 	table: rt::Object,
 	// Keep a handle around as Root objects use weak references
 	public: rt::Object,
+	vectored_writes: Cell<bool>,
 }
 
 impl StreamTable {
@@ -52,9 +57,18 @@ impl StreamTable {
 			notify,
 			table: tbl,
 			public,
+			vectored_writes: Cell::new(false),
 		}
 	}
 
+	/// Receive writes from multiple buffers as [`Request::WriteVectored`].
+	///
+	/// If disabled, which is the default, the buffers are gathered into a single
+	/// [`Request::Write`] first.
+	pub fn set_vectored_writes(&self, enable: bool) {
+		self.vectored_writes.set(enable)
+	}
+
 	pub fn public(&self) -> &rt::Object {
 		&self.public
 	}
@@ -112,6 +126,56 @@ impl StreamTable {
 				job_id,
 				share: self.table.open(&share.to_le_bytes()).unwrap(),
 			},
+			R::Cancel { job_id } => Request::Cancel { job_id },
+			R::ReadAt {
+				job_id,
+				offset_amount,
+			} => {
+				let data = self.get_owned_buf(offset_amount);
+				let mut buf = [0; 12];
+				let l = data.len().min(buf.len());
+				data.copy_to_untrusted(0, &mut buf[..l]);
+				data.manual_drop();
+				let (offset, amount) = buf.split_at(8);
+				Request::ReadAt {
+					job_id,
+					offset: u64::from_le_bytes(offset.try_into().unwrap()),
+					amount: u32::from_le_bytes(amount.try_into().unwrap()),
+				}
+			}
+			R::WriteAt {
+				job_id,
+				data_offset,
+			} => {
+				let data = self.get_owned_buf(data_offset);
+				let len = data.len().saturating_sub(8);
+				let mut offset = [0; 8];
+				data.copy_to_untrusted(len, &mut offset[..data.len() - len]);
+				Request::WriteAt {
+					job_id,
+					offset: u64::from_le_bytes(offset),
+					data: DataAt { data, len },
+				}
+			}
+			R::WriteVectored { job_id, slices } => {
+				let list = self.get_owned_buf(slices);
+				let data = (0..list.len() / Slice::ENCODED_SIZE)
+					.map(|i| {
+						let mut b = [0; Slice::ENCODED_SIZE];
+						list.copy_to(i * Slice::ENCODED_SIZE, &mut b);
+						self.get_owned_buf(Slice::from_le_bytes(b))
+					})
+					.collect::<Vec<_>>();
+				list.manual_drop();
+				if self.vectored_writes.get() {
+					Request::WriteVectored { job_id, data }
+				} else {
+					Request::Write {
+						job_id,
+						data: self.gather(data),
+					}
+				}
+			}
 		};
 		Some((h, r))
 	}
@@ -122,11 +186,16 @@ impl StreamTable {
 			Response::Error(e) => R::Error(e as _),
 			Response::Amount(n) => R::Amount(n),
 			Response::Position(n) => R::Position(n),
+			Response::Share(n) => R::Raw(n),
 			Response::Data(d) => R::Slice(Slice {
 				offset: d.offset().try_into().unwrap(),
 				length: d.len().try_into().unwrap(),
 			}),
 			Response::Handle(h) => R::Handle(h),
+			Response::Object(o) => match self.table.share(&o) {
+				Ok(h) => R::Object(h as _),
+				Err(e) => R::Error(e as _),
+			},
 		};
 		self.queue.borrow_mut().try_enqueue(job_id, r).unwrap();
 	}
@@ -156,6 +225,21 @@ impl StreamTable {
 			data: self.buffers.get(slice),
 		}
 	}
+
+	/// Copy the buffers of a vectored write into a single buffer.
+	fn gather<'a>(&'a self, data: Vec<Data<'a>>) -> Data<'a> {
+		// Free the buffers before allocating the new one so we don't run out.
+		let mut v = Vec::new();
+		for d in data {
+			let l = v.len();
+			v.resize(l + d.len(), 0);
+			d.copy_to(0, &mut v[l..]);
+			d.manual_drop();
+		}
+		let data = self.alloc(v.len()).expect("out of buffers");
+		data.copy_from(0, &v);
+		data
+	}
 }
 
 pub enum Request<'a> {
@@ -193,18 +277,55 @@ pub enum Request<'a> {
 		job_id: JobId,
 		from: SeekFrom,
 	},
+	/// An object the client shares with this server.
 	Share {
 		job_id: JobId,
 		share: rt::Object,
 	},
+	/// The client is no longer interested in the result of a job.
+	///
+	/// There is no response to this request. A job that is still in progress should be
+	/// finished early with [`rt::Error::Cancelled`] if possible. Cancellation of jobs that
+	/// already finished must be ignored, so servers that finish every job immediately can
+	/// ignore this request entirely.
+	Cancel {
+		job_id: JobId,
+	},
+	/// Read data at an offset. The seek head is neither used nor advanced.
+	ReadAt {
+		job_id: JobId,
+		offset: u64,
+		amount: u32,
+	},
+	/// Write data at an offset. The seek head is neither used nor advanced.
+	WriteAt {
+		job_id: JobId,
+		offset: u64,
+		data: DataAt<'a>,
+	},
+	/// Write data from multiple buffers as if they were a single buffer.
+	///
+	/// This is only used if enabled with [`StreamTable::set_vectored_writes`]. Each buffer must
+	/// be dropped with [`Data::manual_drop`].
+	WriteVectored {
+		job_id: JobId,
+		data: Vec<Data<'a>>,
+	},
 }
 
 pub enum Response<'a> {
 	Error(rt::Error),
 	Amount(u32),
 	Position(u64),
+	/// The value returned by a share request, which is passed on to the client unchanged.
+	Share(u64),
 	Data(Data<'a>),
 	Handle(Handle),
+	/// Give an object directly to the client in response to an open or create request.
+	///
+	/// This can be any object, e.g. shared memory or a pipe. Further requests on it won't pass
+	/// through this server.
+	Object(rt::Object),
 }
 
 pub struct Data<'a> {
@@ -287,3 +408,42 @@ impl<'a> PropertyValue<'a> {
 
 #[derive(Debug)]
 pub struct InvalidPropertyValue;
+
+/// Data of a [`Request::WriteAt`].
+///
+/// The buffer also holds the offset after the data, which is excluded from [`DataAt::len`].
+pub struct DataAt<'a> {
+	data: Data<'a>,
+	len: usize,
+}
+
+impl<'a> DataAt<'a> {
+	#[inline(always)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	#[inline]
+	pub fn copy_to(&self, offset: usize, buf: &mut [u8]) {
+		assert!(offset + buf.len() <= self.len, "out of bounds");
+		self.data.copy_to(offset, buf)
+	}
+
+	#[inline]
+	pub fn copy_to_untrusted(&self, offset: usize, buf: &mut [u8]) {
+		assert!(offset + buf.len() <= self.len, "out of bounds");
+		self.data.copy_to_untrusted(offset, buf)
+	}
+
+	/// The blocks of the buffer. The data is at the start of the first block.
+	///
+	/// The last blocks may contain (part of) the offset instead of data.
+	#[inline(always)]
+	pub fn blocks(&self) -> nora_stream_table::DataIter<'_, 'a> {
+		self.data.blocks()
+	}
+
+	pub fn manual_drop(self) {
+		self.data.manual_drop()
+	}
+}
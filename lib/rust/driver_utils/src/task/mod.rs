@@ -1 +1,16 @@
 pub mod waker;
+
+use core::{
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+/// Poll a future once without registering a waker.
+///
+/// This is intended for drivers that block until any of their objects have a finished request
+/// and then poll the futures of those objects, e.g. with `async_std::queue::wait_any`.
+pub fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+	let w = waker::dummy();
+	Pin::new(future).poll(&mut Context::from_waker(&w))
+}
@@ -0,0 +1,63 @@
+//! # Helpers for programs that serve files from the file root to remote clients.
+
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom};
+
+pub struct Metadata {
+	pub size: u64,
+	pub dir: bool,
+}
+
+/// Normalize a path sent by a client to one relative to the file root.
+///
+/// The file root is the root directory, so `..` can't be used to escape from it.
+pub fn normalize(path: &str) -> String {
+	let mut p = Vec::new();
+	for s in path.split('/') {
+		match s {
+			"" | "." => {}
+			".." => {
+				p.pop();
+			}
+			s => p.push(s),
+		}
+	}
+	p.join("/")
+}
+
+/// Get the size of a file and whether it is a directory.
+pub fn metadata(path: &str) -> io::Result<Metadata> {
+	if let Ok(m) = fs::metadata(path) {
+		return Ok(Metadata {
+			size: m.len(),
+			dir: m.is_dir(),
+		});
+	}
+	// Not every file root supports metadata. Files can be seeked in but directories can't.
+	let mut f = File::open(path)?;
+	Ok(match file_size(&mut f) {
+		Ok(size) => Metadata { size, dir: false },
+		Err(_) => Metadata { size: 0, dir: true },
+	})
+}
+
+/// Get the size of a file without changing the position.
+pub fn file_size(f: &mut File) -> io::Result<u64> {
+	let pos = f.stream_position()?;
+	let size = f.seek(SeekFrom::End(0))?;
+	f.seek(SeekFrom::Start(pos))?;
+	Ok(size)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn normalize_path() {
+		assert_eq!(normalize("/"), "");
+		assert_eq!(normalize("."), "");
+		assert_eq!(normalize("/a/./b//c/"), "a/b/c");
+		assert_eq!(normalize("../../a/../b"), "b");
+	}
+}
@@ -7,7 +7,7 @@ use norostb_kernel::{io, syscall};
 
 pub use norostb_kernel::{
 	error,
-	io::{Handle, Response, SeekFrom, TinySlice},
+	io::{Handle, IoSlice, IoSliceMut, Response, SeekFrom, TinySlice},
 	time::Monotonic,
 };
 
@@ -117,40 +117,88 @@ impl Queue {
 		Pow2Size::approx_mask(self.inner.responses_mask)
 	}
 
+	/// Check if the given amount of requests, of which `responses` expect a response, can be
+	/// submitted without any of them failing.
+	///
+	/// This is useful to ensure a chain of linked requests isn't submitted partially.
+	pub fn can_submit(&self, requests: u32, responses: u32) -> bool {
+		self.inner.requests_free() >= requests
+			&& self.requests_in_flight + responses <= self.inner.responses_mask + 1
+	}
+
 	pub fn submit(
 		&mut self,
 		user_data: u64,
 		handle: Handle,
 		request: Request,
+	) -> Result<bool, Full> {
+		self.submit_with_flags(user_data, handle, request, Default::default())
+	}
+
+	pub fn submit_with_flags(
+		&mut self,
+		user_data: u64,
+		handle: Handle,
+		request: Request,
+		flags: Flags,
 	) -> Result<bool, Full> {
 		// responses_mask + 1 = responses_len
-		if self.inner.responses_mask < self.requests_in_flight {
+		let no_response = matches!(
+			request,
+			Request::Close | Request::Cancel | Request::Timeout { .. }
+		);
+		if !no_response && self.inner.responses_mask < self.requests_in_flight {
 			return Err(Full);
 		}
 		// SAFETY: requests_mask is not bogus.
 		unsafe {
 			let mut expect_response = true;
-			self.inner
-				.enqueue_request(match request {
-					Request::Read { buffer } => io::Request::read_uninit(user_data, handle, buffer),
-					Request::Write { buffer } => io::Request::write(user_data, handle, buffer),
-					Request::GetMeta { property, value } => {
-						io::Request::get_meta_uninit(user_data, handle, property, value)
-					}
-					Request::SetMeta { property, value } => {
-						io::Request::set_meta(user_data, handle, property, value)
-					}
-					Request::Open { path } => io::Request::open(user_data, handle, path),
-					Request::Create { path } => io::Request::create(user_data, handle, path),
-					Request::Seek { from } => io::Request::seek(user_data, handle, from),
-					Request::Close => {
-						expect_response = false;
-						io::Request::close(user_data, handle)
-					}
-					Request::Peek { buffer } => io::Request::peek_uninit(user_data, handle, buffer),
-					Request::Share { share } => io::Request::share(user_data, handle, share),
-				})
-				.map_err(|_| Full)?;
+			let mut request = match request {
+				Request::Read { buffer } => io::Request::read_uninit(user_data, handle, buffer),
+				Request::Write { buffer } => io::Request::write(user_data, handle, buffer),
+				Request::ReadAt { buffer, offset } => {
+					io::Request::read_at_uninit(user_data, handle, buffer, offset)
+				}
+				Request::WriteAt { buffer, offset } => {
+					io::Request::write_at(user_data, handle, buffer, offset)
+				}
+				Request::ReadVectored { buffers } => {
+					io::Request::read_vectored(user_data, handle, buffers)
+				}
+				Request::WriteVectored { buffers } => {
+					io::Request::write_vectored(user_data, handle, buffers)
+				}
+				Request::GetMeta { property, value } => {
+					io::Request::get_meta_uninit(user_data, handle, property, value)
+				}
+				Request::SetMeta { property, value } => {
+					io::Request::set_meta(user_data, handle, property, value)
+				}
+				Request::Open { path } => io::Request::open(user_data, handle, path),
+				Request::Create { path } => io::Request::create(user_data, handle, path),
+				Request::Seek { from } => io::Request::seek(user_data, handle, from),
+				Request::Close => {
+					expect_response = false;
+					io::Request::close(user_data, handle)
+				}
+				Request::Peek { buffer } => io::Request::peek_uninit(user_data, handle, buffer),
+				Request::Share { share } => io::Request::share(user_data, handle, share),
+				Request::Cancel => {
+					expect_response = false;
+					io::Request::cancel(user_data)
+				}
+				Request::Timeout { deadline } => {
+					expect_response = false;
+					io::Request::timeout(user_data, deadline)
+				}
+			};
+			if flags.link {
+				request = request.link();
+			}
+			if flags.link_handle {
+				request = request.link_handle();
+			}
+			self.inner.enqueue_request(request).map_err(|_| Full)?;
 			if expect_response {
 				self.requests_in_flight += 1;
 			}
@@ -174,6 +222,15 @@ impl Queue {
 	pub fn wait(&mut self, timeout: Duration) -> Monotonic {
 		syscall::wait_io_queue(Some(self.inner.base.cast()), timeout).expect("failed to wait queue")
 	}
+
+	/// Wait until a request on any of the given objects finishes or the timeout expires.
+	///
+	/// The queue must be polled first so the kernel sees the requests.
+	///
+	/// Returns the index of the handle of the object.
+	pub fn wait_any(&mut self, handles: &[Handle], timeout: Duration) -> error::Result<usize> {
+		syscall::wait_any(handles, timeout)
+	}
 }
 
 impl Drop for Queue {
@@ -198,6 +255,21 @@ pub enum Request {
 	Write {
 		buffer: &'static [u8],
 	},
+	ReadAt {
+		buffer: &'static mut [MaybeUninit<u8>],
+		offset: u64,
+	},
+	WriteAt {
+		buffer: &'static [u8],
+		offset: u64,
+	},
+	/// The buffers are filled in order.
+	ReadVectored {
+		buffers: &'static mut [IoSliceMut<'static>],
+	},
+	WriteVectored {
+		buffers: &'static [IoSlice<'static>],
+	},
 	GetMeta {
 		property: &'static TinySlice<u8>,
 		value: &'static mut TinySlice<MaybeUninit<u8>>,
@@ -222,6 +294,25 @@ pub enum Request {
 	Share {
 		share: Handle,
 	},
+	/// Cancel the request with the same `user_data`. The handle is ignored.
+	Cancel,
+	/// Cancel the request with the same `user_data` with [`error::Error::TimedOut`] if it hasn't
+	/// finished before the deadline. The handle is ignored.
+	Timeout {
+		deadline: Monotonic,
+	},
+}
+
+/// Flags that affect how a request is processed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Flags {
+	/// Only run the next request if this request succeeds.
+	///
+	/// Close requests are always run to avoid leaking objects.
+	pub link: bool,
+	/// Use the handle of the object opened or created earlier in the chain.
+	/// The handle passed with the request is ignored.
+	pub link_handle: bool,
 }
 
 #[derive(Debug)]
@@ -1,6 +1,7 @@
 //! # Async I/O queue with runtime.
 
 #![no_std]
+#![feature(let_else)]
 #![deny(unused)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
@@ -8,7 +9,7 @@ extern crate alloc;
 
 pub use nora_io_queue::{error, Handle, Monotonic, Pow2Size, SeekFrom};
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use arena::Arena;
 use async_completion::{Buf, BufMut};
 use core::{
@@ -65,9 +66,22 @@ impl Queue {
 
 	/// Submit a request involving reading into byte buffers.
 	fn submit_read_buffer<B: BufMut, F>(
+		&self,
+		buffer: B,
+		handle: Handle,
+		wrap: F,
+	) -> Result<BufferFuture<'_, B>, Full<B>>
+	where
+		F: FnOnce(&'static mut [MaybeUninit<u8>]) -> Request,
+	{
+		self.submit_read_buffer_with_flags(buffer, handle, Default::default(), wrap)
+	}
+
+	fn submit_read_buffer_with_flags<B: BufMut, F>(
 		&self,
 		mut buffer: B,
 		handle: Handle,
+		flags: q::Flags,
 		wrap: F,
 	) -> Result<BufferFuture<'_, B>, Full<B>>
 	where
@@ -78,10 +92,12 @@ impl Queue {
 		// SAFETY: The buffer will live at least as long as the BufferFuture,
 		// even if it is mem::forgot()ten
 		let buf = unsafe { extend_lifetime_mut(buf_as_slice_total_mut(&mut buffer)) };
-		let res = self
-			.inner
-			.borrow_mut()
-			.submit(i.into_raw().0 as u64, handle, wrap(buf));
+		let res = self.inner.borrow_mut().submit_with_flags(
+			i.into_raw().0 as u64,
+			handle,
+			wrap(buf),
+			flags,
+		);
 		match res {
 			Ok(_) => Ok(BufferFuture {
 				queue: self,
@@ -102,6 +118,19 @@ impl Queue {
 		handle: Handle,
 		wrap: F,
 	) -> Result<BufferFuture<'_, B>, Full<B>>
+	where
+		F: FnOnce(&'static [u8]) -> Request,
+	{
+		self.submit_write_buffer_with_flags(buffer, handle, Default::default(), wrap)
+	}
+
+	fn submit_write_buffer_with_flags<B: Buf, F>(
+		&self,
+		buffer: B,
+		handle: Handle,
+		flags: q::Flags,
+		wrap: F,
+	) -> Result<BufferFuture<'_, B>, Full<B>>
 	where
 		F: FnOnce(&'static [u8]) -> Request,
 	{
@@ -110,10 +139,12 @@ impl Queue {
 		// SAFETY: The buffer will live at least as long as the BufferFuture,
 		// even if it is mem::forgot()ten
 		let buf = unsafe { extend_lifetime(buf_as_slice_init(&buffer)) };
-		let res = self
-			.inner
-			.borrow_mut()
-			.submit(i.into_raw().0 as u64, handle, wrap(buf));
+		let res = self.inner.borrow_mut().submit_with_flags(
+			i.into_raw().0 as u64,
+			handle,
+			wrap(buf),
+			flags,
+		);
 		match res {
 			Ok(_) => Ok(BufferFuture {
 				queue: self,
@@ -207,6 +238,34 @@ impl Queue {
 			.map(|fut| Write { fut })
 	}
 
+	/// Read data from an object at the given offset. The seek head is not advanced.
+	pub fn submit_read_at<B>(
+		&self,
+		handle: Handle,
+		buf: B,
+		offset: u64,
+	) -> Result<Read<'_, B>, Full<B>>
+	where
+		B: BufMut,
+	{
+		self.submit_read_buffer(buf, handle, |buffer| Request::ReadAt { buffer, offset })
+			.map(|fut| Read { fut })
+	}
+
+	/// Write data to an object at the given offset. The seek head is not advanced.
+	pub fn submit_write_at<B>(
+		&self,
+		handle: Handle,
+		data: B,
+		offset: u64,
+	) -> Result<Write<'_, B>, Full<B>>
+	where
+		B: Buf,
+	{
+		self.submit_write_buffer(data, handle, |buffer| Request::WriteAt { buffer, offset })
+			.map(|fut| Write { fut })
+	}
+
 	/// Open an object.
 	pub fn submit_open<B>(&self, handle: Handle, path: B) -> Result<Open<'_, B>, Full<B>>
 	where
@@ -259,6 +318,121 @@ impl Queue {
 		.map(|fut| GetMeta { fut })
 	}
 
+	/// Open an object and read from it without waiting for the open request to finish.
+	///
+	/// The read request is cancelled if the object could not be opened.
+	pub fn submit_open_read<P, B>(
+		&self,
+		handle: Handle,
+		path: P,
+		buf: B,
+	) -> Result<OpenRead<'_, P, B>, Full<(P, B)>>
+	where
+		P: Buf,
+		B: BufMut,
+	{
+		self.submit_open_read_chain(handle, path, buf, false)
+	}
+
+	/// Open an object, read from it and close it in a single chain of requests.
+	pub fn submit_open_read_close<P, B>(
+		&self,
+		handle: Handle,
+		path: P,
+		buf: B,
+	) -> Result<OpenReadClose<'_, P, B>, Full<(P, B)>>
+	where
+		P: Buf,
+		B: BufMut,
+	{
+		self.submit_open_read_chain(handle, path, buf, true)
+			.map(|fut| OpenReadClose { fut })
+	}
+
+	/// Submit an open request linked to a read request and optionally a close request.
+	fn submit_open_read_chain<P, B>(
+		&self,
+		handle: Handle,
+		path: P,
+		buf: B,
+		close: bool,
+	) -> Result<OpenRead<'_, P, B>, Full<(P, B)>>
+	where
+		P: Buf,
+		B: BufMut,
+	{
+		// Don't submit the chain partially, as the kernel may then process it without the
+		// remaining requests.
+		if !self.inner.borrow().can_submit(2 + u32::from(close), 2) {
+			return Err(Full((path, buf)));
+		}
+		let flags = q::Flags {
+			link: true,
+			link_handle: false,
+		};
+		let open =
+			self.submit_write_buffer_with_flags(path, handle, flags, |path| Request::Open { path });
+		let flags = q::Flags {
+			link: close,
+			link_handle: true,
+		};
+		let read = self
+			.submit_read_buffer_with_flags(buf, handle, flags, |buffer| Request::Read { buffer });
+		let (Ok(open), Ok(read)) = (open, read) else { unreachable!() };
+		if close {
+			let flags = q::Flags {
+				link: false,
+				link_handle: true,
+			};
+			let r =
+				self.inner
+					.borrow_mut()
+					.submit_with_flags(u64::MAX, handle, Request::Close, flags);
+			debug_assert!(matches!(r, Ok(false)));
+		}
+		Ok(OpenRead {
+			open,
+			read,
+			opened: None,
+		})
+	}
+
+	/// Wait for a timer to expire.
+	pub fn submit_wait_timer(&self, timer: Handle) -> Result<WaitTimer<'_>, Full<()>> {
+		let buf = Vec::with_capacity(8);
+		self.submit_read_buffer(buf, timer, |buffer| Request::Read { buffer })
+			.map(|fut| WaitTimer { fut })
+			.map_err(|_| Full(()))
+	}
+
+	/// Get the amount of times a timer expired since the last wait without resetting it.
+	///
+	/// Unlike [`Self::submit_wait_timer`] this doesn't wait for the timer to expire.
+	pub fn submit_peek_timer(&self, timer: Handle) -> Result<WaitTimer<'_>, Full<()>> {
+		let buf = Vec::with_capacity(8);
+		self.submit_read_buffer(buf, timer, |buffer| Request::Peek { buffer })
+			.map(|fut| WaitTimer { fut })
+			.map_err(|_| Full(()))
+	}
+
+	/// Cancel an in-flight request. The response will still arrive.
+	fn cancel(&self, index: arena::Handle<()>) {
+		// If the queue is full we'll just have to wait for the request to finish.
+		let _ = self
+			.inner
+			.borrow_mut()
+			.submit(index.into_raw().0 as u64, 0, Request::Cancel);
+	}
+
+	/// Cancel an in-flight request if it hasn't finished before the deadline.
+	fn set_deadline(&self, index: arena::Handle<()>, deadline: Monotonic) -> Result<(), Full<()>> {
+		self.inner
+			.borrow_mut()
+			.submit(index.into_raw().0 as u64, 0, Request::Timeout { deadline })
+			.map(|b| debug_assert!(!b))
+			.map_err(|_| Full(()))
+	}
+
 	pub fn process(&self) {
 		let mut inner = self.inner.borrow_mut();
 		let mut inflight = self.inflight_buffers.borrow_mut();
@@ -286,6 +460,17 @@ impl Queue {
 	pub fn wait(&self, timeout: Duration) -> Option<Monotonic> {
 		(self.ready_responses.get() == 0).then(|| self.inner.borrow_mut().wait(timeout))
 	}
+
+	/// Wait until a request on any of the given objects finishes or the timeout expires.
+	///
+	/// Returns the index of the handle of the object, or `None` if no request finished or if
+	/// there are responses that have not been processed yet.
+	pub fn wait_any(&self, handles: &[Handle], timeout: Duration) -> Option<usize> {
+		if self.ready_responses.get() != 0 {
+			return None;
+		}
+		self.inner.borrow_mut().wait_any(handles, timeout).ok()
+	}
 }
 
 /// # Safety
@@ -392,6 +577,7 @@ impl<B: Buf> Drop for BufferFuture<'_, B> {
 				| Some(s @ BufferFutureState::InflightWithWaker(_)) => {
 					// We can't drop the buffer yet as it is still in use by the queue.
 					*s = BufferFutureState::Cancelled(Box::new(buf));
+					self.queue.cancel(i);
 				}
 				Some(BufferFutureState::Finished(_)) | None => {}
 				Some(BufferFutureState::Cancelled(_)) => unreachable!(),
@@ -451,6 +637,7 @@ impl<B: Buf, Bm: Buf> Drop for BufferFuture2<'_, B, Bm> {
 				| Some(s @ BufferFutureState::InflightWithWaker(_)) => {
 					// We can't drop the buffer yet as it is still in use by the queue.
 					*s = BufferFutureState::Cancelled(Box::new(buf));
+					self.queue.cancel(i);
 				}
 				Some(BufferFutureState::Finished(_)) | None => {}
 				Some(BufferFutureState::Cancelled(_)) => unreachable!(),
@@ -473,6 +660,35 @@ fn poll_set_len<B: BufMut>(
 	})
 }
 
+/// Implement `set_deadline` for futures of a single request.
+macro_rules! impl_set_deadline {
+	{ $([$($g:tt)*] $ty:ty)* } => {
+		$(
+			impl<$($g)*> $ty {
+				/// Cancel the request with [`error::Error::TimedOut`] if it hasn't finished
+				/// before the deadline.
+				pub fn set_deadline(&self, deadline: Monotonic) -> Result<(), Full<()>> {
+					self.fut
+						.queue
+						.set_deadline(self.fut.inflight_index, deadline)
+				}
+			}
+		)*
+	};
+}
+
+impl_set_deadline! {
+	[B: BufMut] Read<'_, B>
+	[B: BufMut] Peek<'_, B>
+	[B: Buf] Write<'_, B>
+	[B: Buf] Open<'_, B>
+	[B: Buf] Create<'_, B>
+	[] Seek<'_>
+	[] Share<'_>
+	[B: Buf, Bm: BufMut] GetMeta<'_, B, Bm>
+	[] WaitTimer<'_>
+}
+
 /// A pending read request.
 pub struct Read<'a, B: BufMut> {
 	fut: BufferFuture<'a, B>,
@@ -594,3 +810,66 @@ impl<B: Buf, Bm: BufMut> Future for GetMeta<'_, B, Bm> {
 		})
 	}
 }
+
+/// A pending open request linked to a read request.
+pub struct OpenRead<'a, P: Buf, B: BufMut> {
+	open: BufferFuture<'a, P>,
+	read: BufferFuture<'a, B>,
+	opened: Option<(error::Result<u64>, P)>,
+}
+
+impl<P: Buf, B: BufMut> Future for OpenRead<'_, P, B> {
+	type Output = (error::Result<Handle>, error::Result<usize>, P, B);
+
+	/// Check if both the open and read request have finished.
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		if self.opened.is_none() {
+			match Pin::new(&mut self.open).poll(cx) {
+				Poll::Ready(r) => self.opened = Some(r),
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+		poll_set_len(&mut self.read, cx).map(|(r, buf)| {
+			let (h, path) = self.opened.take().expect("path already taken");
+			(h.map(|h| h as _), r, path, buf)
+		})
+	}
+}
+
+/// A pending open request linked to a read and close request.
+pub struct OpenReadClose<'a, P: Buf, B: BufMut> {
+	fut: OpenRead<'a, P, B>,
+}
+
+impl<P: Buf, B: BufMut> Future for OpenReadClose<'_, P, B> {
+	type Output = (error::Result<usize>, P, B);
+
+	/// Check if the open and read request have finished.
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		Pin::new(&mut self.fut)
+			.poll(cx)
+			.map(|(h, r, path, buf)| (h.and(r), path, buf))
+	}
+}
+
+/// A pending read request on a timer.
+pub struct WaitTimer<'a> {
+	fut: BufferFuture<'a, Vec<u8>>,
+}
+
+impl Future for WaitTimer<'_> {
+	/// The amount of times the timer expired since the last wait. This is `0` if the timer
+	/// won't expire anymore.
+	type Output = error::Result<u64>;
+
+	/// Check if the timer has expired.
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		poll_set_len(&mut self.fut, cx).map(|(r, buf)| {
+			r.map(|_| {
+				let mut n = [0; 8];
+				n[..buf.len()].copy_from_slice(&buf);
+				u64::from_le_bytes(n)
+			})
+		})
+	}
+}
@@ -118,6 +118,11 @@ impl Buffers {
 		}
 	}
 
+	#[inline(always)]
+	pub fn block_size(&self) -> u32 {
+		self.block_size
+	}
+
 	#[inline]
 	pub fn get<'a>(&'a self, slice: Slice) -> Data<'a> {
 		let max = slice.offset as usize * self.block_size as usize;
@@ -22,6 +22,9 @@ use core::{
 	sync::atomic::{AtomicU32, Ordering},
 };
 
+/// Set in the response value if it refers to a shared object instead of a handle.
+const OBJECT_FLAG: u64 = 1 << 32;
+
 const REQUESTS_MASK: u32 = (1 << 7) - 1;
 const RESPONSES_MASK: u32 = (1 << 7) - 1;
 
@@ -105,6 +108,7 @@ impl ServerQueue {
 			Response::Handle(h) => v.set_handle(h),
 			Response::Amount(a) => v.set_amount(a),
 			Response::Raw(a) => v.set_raw(a),
+			Response::Object(h) => v.set_raw(OBJECT_FLAG | u64::from(h)),
 			Response::Slice(s) => v.set_slice(s.into_raw()),
 		};
 		let mut r = raw::Response::default();
@@ -188,6 +192,19 @@ impl ServerQueue {
 						job_id,
 						share: args.share(),
 					},
+					T::Cancel => R::Cancel { job_id },
+					T::ReadAt => R::ReadAt {
+						job_id,
+						offset_amount: Slice::from_raw(args.slice()),
+					},
+					T::WriteAt => R::WriteAt {
+						job_id,
+						data_offset: Slice::from_raw(args.slice()),
+					},
+					T::WriteVectored => R::WriteVectored {
+						job_id,
+						slices: Slice::from_raw(args.slice()),
+					},
 				},
 			)
 		})
@@ -253,6 +270,18 @@ impl ClientQueue {
 				SeekFrom::End(f) => (job_id, T::SeekEnd, v.set_offset_s(f)),
 			},
 			R::Share { job_id, share } => (job_id, T::Share, v.set_share(share)),
+			R::Cancel { job_id } => (job_id, T::Cancel, ()),
+			R::ReadAt {
+				job_id,
+				offset_amount,
+			} => (job_id, T::ReadAt, v.set_slice(offset_amount.into_raw())),
+			R::WriteAt {
+				job_id,
+				data_offset,
+			} => (job_id, T::WriteAt, v.set_slice(data_offset.into_raw())),
+			R::WriteVectored { job_id, slices } => {
+				(job_id, T::WriteVectored, v.set_slice(slices.into_raw()))
+			}
 		};
 		let mut r = raw::Request::default();
 		r.set_ty(ty);
@@ -343,6 +372,37 @@ pub enum Request {
 		job_id: JobId,
 		share: Handle,
 	},
+	/// The client is no longer interested in the result of a job.
+	///
+	/// No response is expected to this request. The job itself must still be completed, either
+	/// normally or with an error. Jobs that already completed should be ignored.
+	Cancel {
+		job_id: JobId,
+	},
+	/// Read data at an offset without using or advancing the seek head.
+	///
+	/// The slice contains the offset as a little-endian `u64` followed by the amount of bytes to
+	/// read as a little-endian `u32`.
+	ReadAt {
+		job_id: JobId,
+		offset_amount: Slice,
+	},
+	/// Write data at an offset without using or advancing the seek head.
+	///
+	/// The slice contains the data followed by the offset as a little-endian `u64`. The offset
+	/// comes last so the data stays aligned to blocks.
+	WriteAt {
+		job_id: JobId,
+		data_offset: Slice,
+	},
+	/// Write data from multiple buffers as if they were a single buffer.
+	///
+	/// The slice contains a list of slices encoded with [`Slice::to_le_bytes`], one for each
+	/// buffer. The server must free the list and all buffers in it.
+	WriteVectored {
+		job_id: JobId,
+		slices: Slice,
+	},
 }
 
 pub enum Response {
@@ -352,6 +412,8 @@ pub enum Response {
 	Amount(u32),
 	Raw(u64),
 	Slice(Slice),
+	/// An object that was shared with the table, which is handed to the client directly.
+	Object(Handle),
 }
 
 pub struct AnyResponse(u64);
@@ -363,6 +425,14 @@ impl AnyResponse {
 			.ok_or(self.0 as _)
 	}
 
+	/// Get the ID of the shared object if the response refers to one.
+	pub fn as_object(&self) -> Option<Handle> {
+		self.get()
+			.ok()
+			.filter(|v| v & OBJECT_FLAG != 0)
+			.map(|v| v as Handle)
+	}
+
 	pub fn as_slice(&self) -> Result<Slice, i16> {
 		use norost_ipc_spec::Data;
 		self.get()
@@ -377,6 +447,25 @@ pub struct Slice {
 }
 
 impl Slice {
+	/// The size of a slice in a list of slices.
+	pub const ENCODED_SIZE: usize = 8;
+
+	/// Encode the offset and length as little-endian `u32`s.
+	pub fn to_le_bytes(self) -> [u8; Self::ENCODED_SIZE] {
+		let mut b = [0; Self::ENCODED_SIZE];
+		b[..4].copy_from_slice(&self.offset.to_le_bytes());
+		b[4..].copy_from_slice(&self.length.to_le_bytes());
+		b
+	}
+
+	pub fn from_le_bytes(b: [u8; Self::ENCODED_SIZE]) -> Self {
+		let [a, b, c, d, e, f, g, h] = b;
+		Self {
+			offset: u32::from_le_bytes([a, b, c, d]),
+			length: u32::from_le_bytes([e, f, g, h]),
+		}
+	}
+
 	fn from_raw(raw: raw::Slice) -> Self {
 		Self {
 			offset: raw.offset(),
@@ -394,3 +483,20 @@ impl Slice {
 
 #[derive(Debug)]
 pub struct Full;
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn slice_bytes() {
+		let s = Slice {
+			offset: 0x1234_5678,
+			length: 0x9abc_def0,
+		};
+		let b = s.to_le_bytes();
+		assert_eq!(b, [0x78, 0x56, 0x34, 0x12, 0xf0, 0xde, 0xbc, 0x9a]);
+		let s = Slice::from_le_bytes(b);
+		assert_eq!((s.offset, s.length), (0x1234_5678, 0x9abc_def0));
+	}
+}
@@ -1,6 +1,6 @@
 macro_rules! impl_ {
 	{ $($v:ident $i:literal)* } => {
-		#[derive(Debug)]
+		#[derive(Clone, Copy, Debug)]
 		#[non_exhaustive]
 		pub enum Error {
 			$($v = -$i,)*
@@ -27,6 +27,7 @@ impl_! {
 	CantCreateObject 6
 	InvalidObject 7
 	InvalidData 8
+	TimedOut 9
 }
 
 impl<T: raw::RawError> From<T> for Error {
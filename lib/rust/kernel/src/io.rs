@@ -11,7 +11,9 @@
 // member in both the request and response ring, which saves a tiny bit of space in user
 // programs on some platforms (e.g. 1 byte on x86 for LEA rd, [rs] vs LEA rd, [rs + off8])
 
+use crate::time::Monotonic;
 use core::{
+	marker::PhantomData,
 	mem::{self, MaybeUninit},
 	ops::{Deref, DerefMut},
 	ptr::NonNull,
@@ -35,12 +37,14 @@ pub struct Empty;
 pub struct Request {
 	/// The type of request.
 	pub ty: u8,
+	/// Flags that affect how the request is processed, e.g. [`Request::LINK`].
+	pub flags: u8,
 	/// Storage for 8-bit arguments.
-	pub arguments_8: [u8; 3],
+	pub arguments_8: [u8; 2],
 	/// Handle to object to perform the operation on.
 	pub handle: Handle,
 	/// Storage for 64-bit arguments. This storage is also used for pointers.
-	pub arguments_64: [u64; 2],
+	pub arguments_64: [u64; 3],
 	/// User data which will be returned with the response.
 	pub user_data: u64,
 }
@@ -57,13 +61,61 @@ impl Request {
 	pub const SEEK: u8 = 8;
 	pub const CLOSE: u8 = 9;
 	pub const SHARE: u8 = 10;
+	/// Cancel the request with the same `user_data`.
+	///
+	/// There is no response to this request. Instead, the cancelled request completes with
+	/// [`Error::Cancelled`] if it hadn't finished yet. If the request is waiting in a chain the
+	/// requests linked after it are cancelled too.
+	///
+	/// [`Error::Cancelled`]: crate::error::Error::Cancelled
+	pub const CANCEL: u8 = 11;
+	/// Set a deadline for the request with the same `user_data`.
+	///
+	/// If the request hasn't finished by then it is cancelled and completes with
+	/// [`Error::TimedOut`]. There is no response to this request.
+	///
+	/// [`Error::TimedOut`]: crate::error::Error::TimedOut
+	pub const TIMEOUT: u8 = 12;
+	/// Read data at the given offset. The seek head is neither used nor advanced.
+	pub const READ_AT: u8 = 13;
+	/// Write data at the given offset. The seek head is neither used nor advanced.
+	pub const WRITE_AT: u8 = 14;
+	/// Read data into multiple buffers, which are filled in order.
+	///
+	/// The buffers are passed as an array of [`IoSliceMut`] with at most
+	/// [`IO_SLICES_MAX`] entries.
+	pub const READ_VECTORED: u8 = 15;
+	/// Write data from multiple buffers as if they were a single buffer.
+	///
+	/// The buffers are passed as an array of [`IoSlice`] with at most [`IO_SLICES_MAX`] entries.
+	pub const WRITE_VECTORED: u8 = 16;
+
+	/// Only run the next request if this request succeeds.
+	///
+	/// Consecutive linked requests form a chain, which ends with the first request that doesn't
+	/// have this flag set. Requests in a chain are run in order. If a request fails all remaining
+	/// requests complete with [`Error::Cancelled`], except [`Request::CLOSE`] which is still run
+	/// so objects opened in the chain aren't leaked.
+	///
+	/// [`Request::CANCEL`] and [`Request::TIMEOUT`] can't be linked and end the chain.
+	///
+	/// [`Error::Cancelled`]: crate::error::Error::Cancelled
+	pub const LINK: u8 = 1 << 0;
+	/// Use the handle of the object returned by the last [`Request::OPEN`] or
+	/// [`Request::CREATE`] in the chain instead of [`Request::handle`].
+	///
+	/// This allows e.g. reading from and closing an object without waiting for it to be opened.
+	/// The request fails with [`Error::InvalidObject`] if there is no such object.
+	///
+	/// [`Error::InvalidObject`]: crate::error::Error::InvalidObject
+	pub const LINK_HANDLE: u8 = 1 << 1;
 
 	#[inline(always)]
 	pub fn read(user_data: u64, handle: Handle, buf: &mut [u8]) -> Self {
 		Self {
 			ty: Self::READ,
 			handle,
-			arguments_64: [buf.as_ptr() as u64, buf.len() as u64],
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -74,7 +126,7 @@ impl Request {
 		Self {
 			ty: Self::READ,
 			handle,
-			arguments_64: [buf.as_ptr() as u64, buf.len() as u64],
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -85,7 +137,7 @@ impl Request {
 		Self {
 			ty: Self::PEEK,
 			handle,
-			arguments_64: [buf.as_ptr() as u64, buf.len() as u64],
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -96,7 +148,7 @@ impl Request {
 		Self {
 			ty: Self::PEEK,
 			handle,
-			arguments_64: [buf.as_ptr() as u64, buf.len() as u64],
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -107,7 +159,67 @@ impl Request {
 		Self {
 			ty: Self::WRITE,
 			handle,
-			arguments_64: [buf.as_ptr() as u64, buf.len() as u64],
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, 0],
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn read_at(user_data: u64, handle: Handle, buf: &mut [u8], offset: u64) -> Self {
+		Self {
+			ty: Self::READ_AT,
+			handle,
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, offset],
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn read_at_uninit(
+		user_data: u64,
+		handle: Handle,
+		buf: &mut [MaybeUninit<u8>],
+		offset: u64,
+	) -> Self {
+		Self {
+			ty: Self::READ_AT,
+			handle,
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, offset],
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn write_at(user_data: u64, handle: Handle, buf: &[u8], offset: u64) -> Self {
+		Self {
+			ty: Self::WRITE_AT,
+			handle,
+			arguments_64: [buf.as_ptr() as u64, buf.len() as u64, offset],
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn read_vectored(user_data: u64, handle: Handle, bufs: &mut [IoSliceMut<'_>]) -> Self {
+		Self {
+			ty: Self::READ_VECTORED,
+			handle,
+			arguments_64: [bufs.as_ptr() as u64, bufs.len() as u64, 0],
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn write_vectored(user_data: u64, handle: Handle, bufs: &[IoSlice<'_>]) -> Self {
+		Self {
+			ty: Self::WRITE_VECTORED,
+			handle,
+			arguments_64: [bufs.as_ptr() as u64, bufs.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -123,8 +235,8 @@ impl Request {
 		Self {
 			ty: Self::GET_META,
 			handle,
-			arguments_8: [property.len_u8(), value.len_u8(), 0],
-			arguments_64: [property.as_ptr() as u64, value.as_mut_ptr() as u64],
+			arguments_8: [property.len_u8(), value.len_u8()],
+			arguments_64: [property.as_ptr() as u64, value.as_mut_ptr() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -140,8 +252,8 @@ impl Request {
 		Self {
 			ty: Self::GET_META,
 			handle,
-			arguments_8: [property.len_u8(), value.len_u8(), 0],
-			arguments_64: [property.as_ptr() as u64, value.as_mut_ptr() as u64],
+			arguments_8: [property.len_u8(), value.len_u8()],
+			arguments_64: [property.as_ptr() as u64, value.as_mut_ptr() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -157,8 +269,8 @@ impl Request {
 		Self {
 			ty: Self::SET_META,
 			handle,
-			arguments_8: [property.len_u8(), value.len_u8(), 0],
-			arguments_64: [property.as_ptr() as u64, value.as_ptr() as u64],
+			arguments_8: [property.len_u8(), value.len_u8()],
+			arguments_64: [property.as_ptr() as u64, value.as_ptr() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -169,7 +281,7 @@ impl Request {
 		Self {
 			ty: Self::OPEN,
 			handle,
-			arguments_64: [path.as_ptr() as u64, path.len() as u64],
+			arguments_64: [path.as_ptr() as u64, path.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -180,7 +292,7 @@ impl Request {
 		Self {
 			ty: Self::CREATE,
 			handle,
-			arguments_64: [path.as_ptr() as u64, path.len() as u64],
+			arguments_64: [path.as_ptr() as u64, path.len() as u64, 0],
 			user_data,
 			..Default::default()
 		}
@@ -192,8 +304,8 @@ impl Request {
 		Self {
 			ty: Self::SEEK,
 			handle,
-			arguments_8: [t, 0, 0],
-			arguments_64: [n, 0],
+			arguments_8: [t, 0],
+			arguments_64: [n, 0, 0],
 			user_data,
 			..Default::default()
 		}
@@ -214,7 +326,26 @@ impl Request {
 		Self {
 			ty: Self::SHARE,
 			handle,
-			arguments_64: [share.into(), 0],
+			arguments_64: [share.into(), 0, 0],
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn cancel(user_data: u64) -> Self {
+		Self {
+			ty: Self::CANCEL,
+			user_data,
+			..Default::default()
+		}
+	}
+
+	#[inline(always)]
+	pub fn timeout(user_data: u64, deadline: Monotonic) -> Self {
+		Self {
+			ty: Self::TIMEOUT,
+			arguments_64: [deadline.as_nanos(), 0, 0],
 			user_data,
 			..Default::default()
 		}
@@ -229,6 +360,20 @@ impl Request {
 			..Default::default()
 		}
 	}
+
+	/// Link this request to the next request. See [`Request::LINK`].
+	#[inline(always)]
+	pub fn link(mut self) -> Self {
+		self.flags |= Self::LINK;
+		self
+	}
+
+	/// Use the handle of the object opened in the chain. See [`Request::LINK_HANDLE`].
+	#[inline(always)]
+	pub fn link_handle(mut self) -> Self {
+		self.flags |= Self::LINK_HANDLE;
+		self
+	}
 }
 
 pub struct TinySlice<T>([T]);
@@ -306,6 +451,85 @@ impl<T> DerefMut for TinySlice<T> {
 #[derive(Debug)]
 pub struct TooLarge;
 
+/// The maximum amount of buffers in a single vectored request.
+pub const IO_SLICES_MAX: usize = 1024;
+
+/// A buffer to write data from in vectored requests.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct IoSlice<'a> {
+	base: *const u8,
+	len: usize,
+	_marker: PhantomData<&'a [u8]>,
+}
+
+unsafe impl Send for IoSlice<'_> {}
+unsafe impl Sync for IoSlice<'_> {}
+
+impl<'a> IoSlice<'a> {
+	#[inline]
+	pub fn new(buf: &'a [u8]) -> Self {
+		Self {
+			base: buf.as_ptr(),
+			len: buf.len(),
+			_marker: PhantomData,
+		}
+	}
+
+	#[inline]
+	pub fn advance(&mut self, n: usize) {
+		*self = Self::new(&self.as_slice()[n..])
+	}
+
+	#[inline]
+	pub fn as_slice(&self) -> &'a [u8] {
+		// SAFETY: the slice is borrowed for 'a
+		unsafe { slice::from_raw_parts(self.base, self.len) }
+	}
+}
+
+/// A buffer to read data into in vectored requests.
+#[repr(C)]
+pub struct IoSliceMut<'a> {
+	base: *mut u8,
+	len: usize,
+	_marker: PhantomData<&'a mut [u8]>,
+}
+
+unsafe impl Send for IoSliceMut<'_> {}
+unsafe impl Sync for IoSliceMut<'_> {}
+
+impl<'a> IoSliceMut<'a> {
+	#[inline]
+	pub fn new(buf: &'a mut [u8]) -> Self {
+		Self {
+			base: buf.as_mut_ptr(),
+			len: buf.len(),
+			_marker: PhantomData,
+		}
+	}
+
+	#[inline]
+	pub fn advance(&mut self, n: usize) {
+		assert!(n <= self.len, "advancing past end of buffer");
+		// SAFETY: n is in range
+		self.base = unsafe { self.base.add(n) };
+		self.len -= n;
+	}
+
+	#[inline]
+	pub fn as_slice(&self) -> &[u8] {
+		// SAFETY: the slice is borrowed mutably for 'a
+		unsafe { slice::from_raw_parts(self.base, self.len) }
+	}
+
+	#[inline]
+	pub fn as_mut_slice(&mut self) -> &mut [u8] {
+		// SAFETY: the slice is borrowed mutably for 'a
+		unsafe { slice::from_raw_parts_mut(self.base, self.len) }
+	}
+}
+
 pub struct DoIo<'a> {
 	pub handle: Handle,
 	pub op: DoIoOp<'a>,
@@ -322,6 +546,19 @@ pub enum DoIoOp<'a> {
 	PeekUninit { buf: &'a mut [MaybeUninit<u8>] },
 	/// Write data to an object.
 	Write { data: &'a [u8] },
+	/// Read data from an object at the given offset.
+	ReadAt { buf: &'a mut [u8], offset: u64 },
+	/// Read data from an object at the given offset.
+	ReadAtUninit {
+		buf: &'a mut [MaybeUninit<u8>],
+		offset: u64,
+	},
+	/// Write data to an object at the given offset.
+	WriteAt { data: &'a [u8], offset: u64 },
+	/// Read data from an object into multiple buffers.
+	ReadVectored { bufs: &'a mut [IoSliceMut<'a>] },
+	/// Write data from multiple buffers to an object.
+	WriteVectored { bufs: &'a [IoSlice<'a>] },
 	/// Open an object at the given location.
 	Open { path: &'a [u8] },
 	/// Get meta-information about an object.
@@ -363,6 +600,23 @@ impl DoIo<'_> {
 			DoIoOp::Peek { buf } => (R::PEEK, h, N2(buf.as_ptr() as _, buf.len())),
 			DoIoOp::PeekUninit { buf } => (R::PEEK, h, N2(buf.as_ptr() as _, buf.len())),
 			DoIoOp::Write { data } => (R::WRITE, h, N2(data.as_ptr() as _, data.len())),
+			DoIoOp::ReadAt { buf, offset } => {
+				(R::READ_AT, h, N3(buf.as_ptr() as _, buf.len(), offset as _))
+			}
+			DoIoOp::ReadAtUninit { buf, offset } => {
+				(R::READ_AT, h, N3(buf.as_ptr() as _, buf.len(), offset as _))
+			}
+			DoIoOp::WriteAt { data, offset } => (
+				R::WRITE_AT,
+				h,
+				N3(data.as_ptr() as _, data.len(), offset as _),
+			),
+			DoIoOp::ReadVectored { bufs } => {
+				(R::READ_VECTORED, h, N2(bufs.as_ptr() as _, bufs.len()))
+			}
+			DoIoOp::WriteVectored { bufs } => {
+				(R::WRITE_VECTORED, h, N2(bufs.as_ptr() as _, bufs.len()))
+			}
 			DoIoOp::GetMeta { property, value } => (
 				R::GET_META,
 				h,
@@ -415,9 +669,10 @@ impl Default for Request {
 	fn default() -> Self {
 		Self {
 			ty: u8::MAX,
-			arguments_8: [0; 3],
+			flags: 0,
+			arguments_8: [0; 2],
 			handle: Default::default(),
-			arguments_64: [0; 2],
+			arguments_64: [0; 3],
 			user_data: 0,
 		}
 	}
@@ -691,6 +946,17 @@ impl Queue {
 		unsafe { self.response_ring().wait_any(f) }
 	}
 
+	/// Get how many requests can be submitted before the queue is full.
+	#[inline]
+	pub fn requests_free(&self) -> u32 {
+		let ring = unsafe { self.request_ring() };
+		let used = ring
+			.user_index
+			.load(Ordering::Relaxed)
+			.wrapping_sub(ring.kernel_index.load(Ordering::Relaxed));
+		self.requests_mask + 1 - used
+	}
+
 	/// Get how many responses are in the queue.
 	#[inline]
 	pub fn responses_available(&self) -> u32 {
@@ -1080,4 +1346,100 @@ mod test {
 			assert!(queue.dequeue_response().is_err());
 		}
 	}
+
+	#[test]
+	fn request_layout() {
+		let r = Request::default();
+		let base = &r as *const Request as usize;
+		assert_eq!(mem::size_of::<Request>(), 40);
+		assert_eq!(core::ptr::addr_of!(r.flags) as usize - base, 1);
+		assert_eq!(core::ptr::addr_of!(r.handle) as usize - base, 4);
+		assert_eq!(core::ptr::addr_of!(r.arguments_64) as usize - base, 8);
+		assert_eq!(core::ptr::addr_of!(r.user_data) as usize - base, 32);
+	}
+
+	#[test]
+	fn read_at() {
+		let mut buf = [0; 16];
+		let (ptr, len) = (buf.as_ptr() as u64, buf.len() as u64);
+		let r = Request::read_at(7, 3, &mut buf, 1 << 40);
+		assert_eq!(r.ty, Request::READ_AT);
+		assert_eq!((r.handle, r.user_data), (3, 7));
+		assert_eq!(r.arguments_64, [ptr, len, 1 << 40]);
+	}
+
+	#[test]
+	fn write_at() {
+		let buf = [0; 16];
+		let r = Request::write_at(7, 3, &buf, u64::MAX);
+		assert_eq!(r.ty, Request::WRITE_AT);
+		assert_eq!((r.handle, r.user_data), (3, 7));
+		assert_eq!(
+			r.arguments_64,
+			[buf.as_ptr() as u64, buf.len() as u64, u64::MAX]
+		);
+	}
+
+	#[test]
+	fn dequeue_request_arguments() {
+		let base = Box::new([0; 4096]);
+		let mut queue = Queue {
+			base: NonNull::from(&*base).cast(),
+			requests_mask: 1,
+			responses_mask: 1,
+		};
+		let buf = [0; 16];
+		unsafe {
+			queue
+				.enqueue_request(Request::write_at(1, 2, &buf, 1 << 50))
+				.unwrap();
+			let req = queue.dequeue_request().unwrap();
+			assert_eq!(req.ty, Request::WRITE_AT);
+			assert_eq!(req.arguments_64[2], 1 << 50);
+		}
+	}
+
+	#[test]
+	fn link() {
+		let r = Request::open(1, 2, b"file").link();
+		assert_eq!(r.ty, Request::OPEN);
+		assert_eq!(r.flags, Request::LINK);
+		let r = Request::close(3, 0).link_handle();
+		assert_eq!(r.ty, Request::CLOSE);
+		assert_eq!(r.flags, Request::LINK_HANDLE);
+		let r = Request::read(4, 0, &mut []).link().link_handle();
+		assert_eq!(r.flags, Request::LINK | Request::LINK_HANDLE);
+	}
+
+	#[test]
+	fn dequeue_chain() {
+		let base = Box::new([0; 4096]);
+		let mut queue = Queue {
+			base: NonNull::from(&*base).cast(),
+			requests_mask: 3,
+			responses_mask: 3,
+		};
+		let mut buf = [0; 16];
+		unsafe {
+			queue
+				.enqueue_request(Request::open(1, 2, b"file").link())
+				.unwrap();
+			queue
+				.enqueue_request(Request::read(2, 0, &mut buf).link().link_handle())
+				.unwrap();
+			queue
+				.enqueue_request(Request::close(3, 0).link_handle())
+				.unwrap();
+			let flags = [
+				Request::LINK,
+				Request::LINK | Request::LINK_HANDLE,
+				Request::LINK_HANDLE,
+			];
+			for (i, f) in (1..).zip(flags) {
+				let req = queue.dequeue_request().unwrap();
+				assert_eq!((req.user_data, req.flags), (i, f));
+			}
+			assert!(queue.dequeue_request().is_err());
+		}
+	}
 }
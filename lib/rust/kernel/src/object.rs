@@ -1,5 +1,8 @@
-use crate::{Handle, RWX};
-use core::ops::RangeInclusive;
+use crate::{time::Monotonic, Handle, RWX};
+use core::{
+	ops::{BitOr, RangeInclusive},
+	time::Duration,
+};
 
 macro_rules! impl_ {
 	{ $($v:ident $i:literal)* } => {
@@ -27,6 +30,10 @@ impl_! {
 	StreamTable 4
 	PermissionMask 5
 	Pipe 6
+	Pty 7
+	Attenuate 8
+	Timer 9
+	Event 10
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -94,7 +101,11 @@ p2s!(s i64);
 p2s!(s i128);
 p2s!(s isize);
 
-pub enum NewObject {
+/// Arguments for creating a new object.
+///
+/// `S` is the type of the slices passed to the kernel. It is `&[u8]` when encoding and
+/// [`RawSlice`] when decoding, as the kernel must validate the memory itself.
+pub enum NewObject<S> {
 	SubRange {
 		handle: Handle,
 		range: RangeInclusive<usize>,
@@ -120,6 +131,83 @@ pub enum NewObject {
 		rwx: RWX,
 	},
 	Pipe,
+	/// Create a master and slave PTY pair.
+	Pty,
+	/// Create a wrapper that only allows the given operations on an object.
+	///
+	/// If `prefix` is not empty only paths under the prefix can be accessed. It may be at most
+	/// [`ATTENUATE_PREFIX_MAX`] bytes long.
+	Attenuate {
+		handle: Handle,
+		allow: Operations,
+		prefix: S,
+	},
+	/// Create a timer that expires at the given deadline.
+	///
+	/// If `interval` is not `None` the timer expires periodically afterwards.
+	Timer {
+		deadline: Monotonic,
+		interval: Option<Duration>,
+	},
+	/// Create a counter that can be used to signal other processes.
+	Event,
+}
+
+/// The maximum length of the prefix of [`NewObject::Attenuate`].
+pub const ATTENUATE_PREFIX_MAX: usize = 4096;
+
+/// A slice in the address space of the caller. It is not validated in any way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawSlice {
+	pub ptr: usize,
+	pub len: usize,
+}
+
+/// A set of operations that can be performed on an object.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Operations(u16);
+
+impl Operations {
+	pub const OPEN: Self = Self(1 << 0);
+	pub const CREATE: Self = Self(1 << 1);
+	pub const DESTROY: Self = Self(1 << 2);
+	pub const READ: Self = Self(1 << 3);
+	pub const WRITE: Self = Self(1 << 4);
+	pub const SEEK: Self = Self(1 << 5);
+	pub const SHARE: Self = Self(1 << 6);
+	pub const GET_META: Self = Self(1 << 7);
+	pub const SET_META: Self = Self(1 << 8);
+	/// Mapping the object into memory.
+	pub const MAP: Self = Self(1 << 9);
+
+	pub const NONE: Self = Self(0);
+	pub const ALL: Self = Self((1 << 10) - 1);
+	/// All operations that can't modify the object.
+	pub const READ_ONLY: Self = Self(Self::OPEN.0 | Self::READ.0 | Self::SEEK.0 | Self::GET_META.0);
+
+	#[inline]
+	pub fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	#[inline]
+	pub fn into_raw(self) -> u16 {
+		self.0
+	}
+
+	#[inline]
+	pub fn from_raw(raw: u16) -> Option<Self> {
+		Self::ALL.contains(Self(raw)).then(|| Self(raw))
+	}
+}
+
+impl BitOr for Operations {
+	type Output = Self;
+
+	#[inline]
+	fn bitor(self, rhs: Self) -> Self {
+		Self(self.0 | rhs.0)
+	}
 }
 
 pub enum NewObjectArgs {
@@ -129,7 +217,7 @@ pub enum NewObjectArgs {
 	N3(usize, usize, usize),
 }
 
-impl NewObject {
+impl NewObject<&[u8]> {
 	#[inline]
 	pub fn into_args(self) -> (usize, NewObjectArgs) {
 		use NewObjectArgs::*;
@@ -158,10 +246,33 @@ impl NewObject {
 				(PermissionMask, N2(handle as _, rwx.into_raw() as _))
 			}
 			Self::Pipe => (Pipe, N0),
+			Self::Pty => (Pty, N0),
+			Self::Attenuate {
+				handle,
+				allow,
+				prefix,
+			} => (
+				Attenuate,
+				N3(
+					handle as _,
+					usize::from(allow.into_raw()) | prefix.len() << 16,
+					prefix.as_ptr() as _,
+				),
+			),
+			Self::Timer { deadline, interval } => (
+				Timer,
+				N2(
+					deadline.as_nanos() as _,
+					interval.map_or(0, |i| i.as_nanos().try_into().unwrap_or(u64::MAX)) as _,
+				),
+			),
+			Self::Event => (Event, N0),
 		};
 		(t as _, a)
 	}
+}
 
+impl NewObject<RawSlice> {
 	#[inline]
 	pub fn try_from_args(ty: usize, a: usize, b: usize, c: usize) -> Option<Self> {
 		use NewObjectType::*;
@@ -184,6 +295,20 @@ impl NewObject {
 				rwx: RWX::try_from_raw((b & 7) as u8)?,
 			},
 			Pipe => Self::Pipe,
+			Pty => Self::Pty,
+			Attenuate => Self::Attenuate {
+				handle: a as _,
+				allow: Operations::from_raw(b as u16)?,
+				prefix: RawSlice {
+					ptr: c,
+					len: b >> 16,
+				},
+			},
+			Timer => Self::Timer {
+				deadline: Monotonic::from_nanos(a as _),
+				interval: (b != 0).then(|| Duration::from_nanos(b as _)),
+			},
+			Event => Self::Event,
 		})
 	}
 }
@@ -200,6 +325,54 @@ mod test {
 		assert_eq!(v, n);
 	}
 
+	#[test]
+	fn attenuate_args() {
+		let prefix: &[u8] = b"drivers";
+		let allow = Operations::OPEN | Operations::READ;
+		let (ty, args) = NewObject::Attenuate {
+			handle: 3,
+			allow,
+			prefix,
+		}
+		.into_args();
+		let (a, b, c) = match args {
+			NewObjectArgs::N3(a, b, c) => (a, b, c),
+			_ => panic!(),
+		};
+		match NewObject::try_from_args(ty, a, b, c) {
+			Some(NewObject::Attenuate {
+				handle,
+				allow: al,
+				prefix: p,
+			}) => {
+				let raw = RawSlice {
+					ptr: prefix.as_ptr() as _,
+					len: prefix.len(),
+				};
+				assert_eq!((handle, al, p), (3, allow, raw))
+			}
+			_ => panic!(),
+		}
+	}
+
+	#[test]
+	fn timer_args() {
+		let deadline = Monotonic::from_nanos(1 << 40);
+		let interval = Some(Duration::from_millis(16));
+		let (ty, args) = NewObject::Timer { deadline, interval }.into_args();
+		let (a, b) = match args {
+			NewObjectArgs::N2(a, b) => (a, b),
+			_ => panic!(),
+		};
+		match NewObject::try_from_args(ty, a, b, 0) {
+			Some(NewObject::Timer {
+				deadline: d,
+				interval: i,
+			}) => assert_eq!((d, i), (deadline, interval)),
+			_ => panic!(),
+		}
+	}
+
 	#[test]
 	fn pow2size_inval() {
 		let n = 1 << 20 | 1 << 11;
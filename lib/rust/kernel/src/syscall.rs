@@ -17,6 +17,9 @@ pub const ID_WAIT_THREAD: usize = 11;
 pub const ID_EXIT_THREAD: usize = 12;
 pub const ID_CREATE_IO_QUEUE: usize = 13;
 pub const ID_DESTROY_IO_QUEUE: usize = 14;
+pub const ID_FUTEX_WAIT: usize = 15;
+pub const ID_FUTEX_WAKE: usize = 16;
+pub const ID_WAIT_ANY: usize = 17;
 
 use crate::{
 	error, io,
@@ -30,6 +33,7 @@ use core::{
 	num::NonZeroUsize,
 	ptr::{self, NonNull},
 	str,
+	sync::atomic::AtomicU32,
 	time::Duration,
 };
 
@@ -231,7 +235,7 @@ pub fn do_io(request: io::DoIo<'_>) -> error::Result<u64> {
 }
 
 #[inline]
-pub fn new_object(args: NewObject) -> error::Result<(Handle, Handle)> {
+pub fn new_object(args: NewObject<&[u8]>) -> error::Result<(Handle, Handle)> {
 	use NewObjectArgs::*;
 	let (ty, args) = args.into_args();
 	ret(match args {
@@ -328,6 +332,48 @@ pub fn wait_thread(handle: Handle) -> error::Result<()> {
 	ret(syscall!(ID_WAIT_THREAD(handle))).map(|_| ())
 }
 
+/// Wait until another thread calls [`futex_wake`] on the same address or the timeout expires.
+///
+/// Returns immediately if the value of `address` is not equal to `expected`. The thread may also
+/// wake spuriously, so the caller must check the value again.
+#[inline]
+pub fn futex_wait(address: &AtomicU32, expected: u32, timeout: Duration) -> error::Result<()> {
+	let address: *const AtomicU32 = address;
+	let expected = expected as usize;
+	ret(match duration_to_sys(timeout) {
+		(l, None) => syscall!(ID_FUTEX_WAIT(address, expected, l)),
+		(l, Some(h)) => syscall!(ID_FUTEX_WAIT(address, expected, l, h)),
+	})
+	.map(|_| ())
+}
+
+/// Wake up to `count` threads waiting on the given address.
+///
+/// Returns the amount of threads that were woken.
+#[inline]
+pub fn futex_wake(address: &AtomicU32, count: usize) -> error::Result<usize> {
+	ret(syscall!(ID_FUTEX_WAKE(address as *const AtomicU32, count))).map(|(_, n)| n)
+}
+
+/// The maximum amount of handles [`wait_any`] accepts.
+pub const WAIT_ANY_MAX: usize = 1024;
+
+/// Wait until a pending request in an I/O queue on any of the given objects finishes or the
+/// timeout expires.
+///
+/// At most [`WAIT_ANY_MAX`] handles can be given.
+///
+/// Returns the index of the handle of the object.
+#[inline]
+pub fn wait_any(handles: &[Handle], timeout: Duration) -> error::Result<usize> {
+	let (ptr, len) = (handles.as_ptr(), handles.len());
+	ret(match duration_to_sys(timeout) {
+		(l, None) => syscall!(ID_WAIT_ANY(ptr, len, l)),
+		(l, Some(h)) => syscall!(ID_WAIT_ANY(ptr, len, l, h)),
+	})
+	.map(|(_, i)| i)
+}
+
 #[inline]
 pub fn exit(code: u8) -> ! {
 	unsafe {
@@ -36,6 +36,19 @@ impl Monotonic {
 		self.ns / 1_000_000_000
 	}
 
+	#[inline]
+	pub fn checked_add(&self, dt: Duration) -> Option<Self> {
+		u64::try_from(dt.as_nanos())
+			.ok()
+			.and_then(|dt| self.ns.checked_add(dt))
+			.map(|ns| Self { ns })
+	}
+
+	#[inline]
+	pub fn saturating_add(&self, dt: Duration) -> Self {
+		self.checked_add(dt).unwrap_or(Self { ns: u64::MAX })
+	}
+
 	#[inline]
 	pub fn checked_duration_since(&self, earlier: Monotonic) -> Option<Duration> {
 		self.ns
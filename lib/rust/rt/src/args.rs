@@ -25,6 +25,7 @@ pub const ID_STDERR: u32 = 2;
 pub const ID_FILE_ROOT: u32 = 3;
 pub const ID_NET_ROOT: u32 = 4;
 pub const ID_PROCESS_ROOT: u32 = 5;
+pub const ID_RANDOM: u32 = 6;
 
 pub struct Args {
 	count: u16,
@@ -192,6 +193,7 @@ pub(crate) unsafe fn init(arguments: Option<NonNull<u8>>) {
 				ID_FILE_ROOT => globals.file_root_handle.store(handle, Ordering::Relaxed),
 				ID_NET_ROOT => globals.net_root_handle.store(handle, Ordering::Relaxed),
 				ID_PROCESS_ROOT => globals.process_root_handle.store(handle, Ordering::Relaxed),
+				ID_RANDOM => globals.random_handle.store(handle, Ordering::Relaxed),
 				_ => {} // Just ignore.
 			}
 			arguments = arguments.wrapping_add(1);
@@ -12,6 +12,7 @@ static mut GLOBALS_VAL: Globals = Globals {
 	file_root_handle: AtomicHandle::new(Handle::MAX),
 	net_root_handle: AtomicHandle::new(Handle::MAX),
 	process_root_handle: AtomicHandle::new(Handle::MAX),
+	random_handle: AtomicHandle::new(Handle::MAX),
 };
 
 pub(crate) static GLOBALS: GlobalsDeref = GlobalsDeref;
@@ -31,4 +32,5 @@ pub(crate) struct Globals {
 	pub file_root_handle: AtomicHandle,
 	pub net_root_handle: AtomicHandle,
 	pub process_root_handle: AtomicHandle,
+	pub random_handle: AtomicHandle,
 }
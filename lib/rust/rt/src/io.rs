@@ -1,7 +1,7 @@
 pub use norostb_kernel::{
 	error::{Error, Result},
-	io::{SeekFrom, TinySlice},
-	object::{NewObject, Pow2Size},
+	io::{IoSlice, IoSliceMut, SeekFrom, TinySlice, IO_SLICES_MAX},
+	object::{NewObject, Operations, Pow2Size},
 	syscall::RWX,
 	Handle,
 };
@@ -46,52 +46,7 @@ transmute_handle!(stderr, set_stderr -> stderr_handle);
 transmute_handle!(file_root, set_file_root -> file_root_handle);
 transmute_handle!(net_root, set_net_root -> net_root_handle);
 transmute_handle!(process_root, set_process_root -> process_root_handle);
-
-#[derive(Copy, Clone)]
-pub struct IoSlice<'a>(&'a [u8]);
-
-impl<'a> IoSlice<'a> {
-	#[inline]
-	pub fn new(buf: &'a [u8]) -> IoSlice<'a> {
-		IoSlice(buf)
-	}
-
-	#[inline]
-	pub fn advance(&mut self, n: usize) {
-		self.0 = &self.0[n..]
-	}
-
-	#[inline]
-	pub fn as_slice(&self) -> &[u8] {
-		self.0
-	}
-}
-
-pub struct IoSliceMut<'a>(&'a mut [u8]);
-
-impl<'a> IoSliceMut<'a> {
-	#[inline]
-	pub fn new(buf: &'a mut [u8]) -> IoSliceMut<'a> {
-		IoSliceMut(buf)
-	}
-
-	#[inline]
-	pub fn advance(&mut self, n: usize) {
-		let slice = mem::replace(&mut self.0, &mut []);
-		let (_, remaining) = slice.split_at_mut(n);
-		self.0 = remaining;
-	}
-
-	#[inline]
-	pub fn as_slice(&self) -> &[u8] {
-		self.0
-	}
-
-	#[inline]
-	pub fn as_mut_slice(&mut self) -> &mut [u8] {
-		self.0
-	}
-}
+transmute_handle!(random, set_random -> random_handle);
 
 #[inline(always)]
 pub fn read(handle: Handle, buf: &mut [u8]) -> Result<usize> {
@@ -132,6 +87,53 @@ pub fn write(handle: Handle, data: &[u8]) -> Result<usize> {
 	.map(|v| v as _)
 }
 
+/// Read data at the given offset. The seek head is not advanced.
+#[inline(always)]
+pub fn read_at(handle: Handle, buf: &mut [u8], offset: u64) -> Result<usize> {
+	// SAFETY: the kernel won't deinitialize unread bytes
+	read_at_uninit(handle, unsafe { mem::transmute(buf) }, offset)
+}
+
+/// Read data at the given offset. The seek head is not advanced.
+#[inline(always)]
+pub fn read_at_uninit(handle: Handle, buf: &mut [MaybeUninit<u8>], offset: u64) -> Result<usize> {
+	syscall::do_io(DoIo {
+		handle,
+		op: DoIoOp::ReadAtUninit { buf, offset },
+	})
+	.map(|v| v as _)
+}
+
+/// Write data at the given offset. The seek head is not advanced.
+#[inline(always)]
+pub fn write_at(handle: Handle, data: &[u8], offset: u64) -> Result<usize> {
+	syscall::do_io(DoIo {
+		handle,
+		op: DoIoOp::WriteAt { data, offset },
+	})
+	.map(|v| v as _)
+}
+
+/// Read data into multiple buffers, which are filled in order.
+#[inline(always)]
+pub fn read_vectored(handle: Handle, bufs: &mut [IoSliceMut<'_>]) -> Result<usize> {
+	syscall::do_io(DoIo {
+		handle,
+		op: DoIoOp::ReadVectored { bufs },
+	})
+	.map(|v| v as _)
+}
+
+/// Write data from multiple buffers as if they were a single buffer.
+#[inline(always)]
+pub fn write_vectored(handle: Handle, bufs: &[IoSlice<'_>]) -> Result<usize> {
+	syscall::do_io(DoIo {
+		handle,
+		op: DoIoOp::WriteVectored { bufs },
+	})
+	.map(|v| v as _)
+}
+
 #[inline(always)]
 pub fn get_meta(
 	handle: Handle,
@@ -210,7 +212,7 @@ pub fn share(handle: Handle, share: Handle) -> Result<u64> {
 }
 
 #[inline(always)]
-pub fn new_object(args: NewObject) -> Result<(Handle, Handle)> {
+pub fn new_object(args: NewObject<&[u8]>) -> Result<(Handle, Handle)> {
 	syscall::new_object(args)
 }
 
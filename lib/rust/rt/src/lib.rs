@@ -57,6 +57,7 @@ mod globals;
 pub mod io;
 pub mod mem;
 pub mod process;
+pub mod random;
 pub mod sync;
 pub mod table;
 pub mod thread;
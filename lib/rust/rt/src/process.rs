@@ -115,6 +115,7 @@ impl Process {
 			(args::ID_FILE_ROOT, io::file_root()),
 			(args::ID_NET_ROOT, io::net_root()),
 			(args::ID_PROCESS_ROOT, io::process_root()),
+			(args::ID_RANDOM, io::random()),
 		]
 		.into_iter()
 		.flat_map(|(ty, h)| h.map(|h| (ty, h)))
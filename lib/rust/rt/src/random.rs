@@ -0,0 +1,48 @@
+//! Random number generation using the kernel's entropy source.
+
+use crate::io;
+
+/// Fill a buffer with cryptographically secure random bytes.
+///
+/// The `random` object must have been passed to the process with [`crate::args::ID_RANDOM`].
+pub fn fill(mut buf: &mut [u8]) -> io::Result<()> {
+	let random = io::random().ok_or(io::Error::DoesNotExist)?;
+	while !buf.is_empty() {
+		let n = random.read(buf)?;
+		if n == 0 {
+			return Err(io::Error::Unknown);
+		}
+		buf = &mut buf[n..];
+	}
+	Ok(())
+}
+
+/// Generate keys for `RandomState` in the standard library.
+///
+/// # Panics
+///
+/// If no entropy source is available. Predictable keys make hash maps vulnerable to collision
+/// attacks, so there is no fallback.
+pub fn hashmap_random_keys() -> (u64, u64) {
+	let mut buf = [0; 16];
+	fill(&mut buf).expect("failed to generate hash map keys");
+	let (a, b) = buf.split_at(8);
+	(
+		u64::from_le_bytes(a.try_into().unwrap()),
+		u64::from_le_bytes(b.try_into().unwrap()),
+	)
+}
+
+// Only registered outside the standard library, as the symbol would otherwise be defined twice.
+#[cfg(all(feature = "getrandom", not(feature = "rustc-dep-of-std")))]
+getrandom::register_custom_getrandom!(getrandom_custom);
+
+/// Custom backend for the `getrandom` crate, which is used by e.g. `rand`.
+#[cfg(all(feature = "getrandom", not(feature = "rustc-dep-of-std")))]
+fn getrandom_custom(buf: &mut [u8]) -> Result<(), getrandom::Error> {
+	fill(buf).map_err(|e| {
+		// Codes below CUSTOM_START are reserved by getrandom.
+		let code = getrandom::Error::CUSTOM_START + (e as i32).unsigned_abs();
+		core::num::NonZeroU32::new(code).unwrap().into()
+	})
+}
@@ -1 +1,55 @@
+use super::MutexGuard;
+use core::{
+	sync::atomic::{AtomicU32, Ordering},
+	time::Duration,
+};
+use norostb_kernel::{error::Error, syscall};
 
+#[derive(Debug, Default)]
+pub struct Condvar {
+	/// Incremented on every notification so waiters can detect missed wakeups.
+	counter: AtomicU32,
+}
+
+impl Condvar {
+	pub const fn new() -> Self {
+		Self {
+			counter: AtomicU32::new(0),
+		}
+	}
+
+	/// Unlock the mutex and wait until this condition variable is notified.
+	///
+	/// The thread may wake spuriously, so the condition must be checked again.
+	pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+		self.wait_timeout(guard, Duration::MAX).0
+	}
+
+	/// Unlock the mutex and wait until this condition variable is notified or the timeout
+	/// expires.
+	///
+	/// Returns `true` if the timeout expired.
+	pub fn wait_timeout<'a, T>(
+		&self,
+		mut guard: MutexGuard<'a, T>,
+		timeout: Duration,
+	) -> (MutexGuard<'a, T>, bool) {
+		let counter = self.counter.load(Ordering::Relaxed);
+		let r = MutexGuard::unlocked(&mut guard, || {
+			syscall::futex_wait(&self.counter, counter, timeout)
+		});
+		(guard, matches!(r, Err(Error::TimedOut)))
+	}
+
+	/// Wake up one thread waiting on this condition variable.
+	pub fn notify_one(&self) {
+		self.counter.fetch_add(1, Ordering::Relaxed);
+		let _ = syscall::futex_wake(&self.counter, 1);
+	}
+
+	/// Wake up all threads waiting on this condition variable.
+	pub fn notify_all(&self) {
+		self.counter.fetch_add(1, Ordering::Relaxed);
+		let _ = syscall::futex_wake(&self.counter, usize::MAX);
+	}
+}
@@ -4,6 +4,7 @@ mod raw_mutex;
 mod raw_rwlock;
 mod rwlock;
 
+pub use condvar::Condvar;
 pub use mutex::{Mutex, MutexGuard};
 pub use raw_mutex::RawMutex;
 pub use raw_rwlock::RawRwLock;
@@ -32,6 +32,16 @@ impl<T> Mutex<T> {
 	}
 }
 
+impl<T> MutexGuard<'_, T> {
+	/// Temporarily unlock the mutex while running the given closure.
+	pub(super) fn unlocked<R>(guard: &mut Self, f: impl FnOnce() -> R) -> R {
+		guard.0.lock.unlock();
+		let r = f();
+		guard.0.lock.lock();
+		r
+	}
+}
+
 // SAFETY: we synchronize access to the inner value.
 unsafe impl<T> Sync for Mutex<T> {}
 
@@ -1,33 +1,24 @@
-use crate::thread;
-use core::sync::atomic::Ordering;
-
-// We use an u32 because some platforms such as RISC-V don't have native
-// u8 or u16 atomic instructions. While it can be emulated it is quite a bit less efficient.
-#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
-mod atomic {
-	pub use core::sync::atomic::AtomicU32 as Atomic;
-	pub type AtomicVal = u32;
-}
-#[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
-mod atomic {
-	pub use core::sync::atomic::AtomicU8 as Atomic;
-	pub type AtomicVal = u8;
-}
-
-use atomic::*;
+use core::{
+	sync::atomic::{AtomicU32, Ordering},
+	time::Duration,
+};
+use norostb_kernel::syscall;
 
+// The lock must be an u32 as the kernel only supports 32-bit futexes.
 #[derive(Debug)]
 pub struct RawMutex {
-	lock: Atomic,
+	lock: AtomicU32,
 }
 
-const UNLOCKED: AtomicVal = 0;
-const LOCKED: AtomicVal = 1;
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// The lock is held and other threads may be waiting for it.
+const CONTENDED: u32 = 2;
 
 impl RawMutex {
 	pub const fn new() -> Self {
 		Self {
-			lock: Atomic::new(UNLOCKED),
+			lock: AtomicU32::new(UNLOCKED),
 		}
 	}
 
@@ -40,19 +31,29 @@ impl RawMutex {
 
 	#[inline]
 	pub fn lock(&self) {
-		while !self.try_lock() {
-			thread::yield_now();
+		if !self.try_lock() {
+			self.lock_contended();
+		}
+	}
+
+	#[cold]
+	fn lock_contended(&self) {
+		// Mark the lock as contended so the thread holding it will wake us.
+		while self.lock.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+			let _ = syscall::futex_wait(&self.lock, CONTENDED, Duration::MAX);
 		}
 	}
 
 	#[inline]
 	pub fn unlock(&self) {
-		debug_assert_eq!(
+		debug_assert_ne!(
 			self.lock.load(Ordering::Relaxed),
-			LOCKED,
+			UNLOCKED,
 			"unlocked during lock"
 		);
-		self.lock.store(UNLOCKED, Ordering::Release);
+		if self.lock.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+			let _ = syscall::futex_wake(&self.lock, 1);
+		}
 	}
 }
 
@@ -1,38 +1,59 @@
-use crate::thread;
 use core::{
 	intrinsics,
-	sync::atomic::{AtomicUsize, Ordering},
+	sync::atomic::{AtomicU32, Ordering},
+	time::Duration,
 };
+use norostb_kernel::syscall;
 
+// The lock must be an u32 as the kernel only supports 32-bit futexes.
 #[derive(Debug)]
 pub struct RawRwLock {
-	lock: AtomicUsize,
+	lock: AtomicU32,
+	/// The amount of threads waiting for the lock.
+	waiters: AtomicU32,
 }
 
-const WRITE_LOCK_BIT: usize = 1 << (usize::BITS - 1);
+const WRITE_LOCK_BIT: u32 = 1 << (u32::BITS - 1);
 
 impl RawRwLock {
 	pub const fn new() -> Self {
 		Self {
-			lock: AtomicUsize::new(0),
+			lock: AtomicU32::new(0),
+			waiters: AtomicU32::new(0),
 		}
 	}
 
 	/// Wait for the write bit to be cleared.
 	#[inline]
-	fn wait_for_write_bit_clear(&self, v: &mut usize) {
+	fn wait_for_write_bit_clear(&self, v: &mut u32) {
 		// Wait for the write lock bit to be cleared
 		while *v & WRITE_LOCK_BIT != 0 {
-			thread::yield_now();
+			self.wait(*v);
 			*v = self.lock.load(Ordering::Relaxed);
 		}
 		debug_assert_eq!(*v & WRITE_LOCK_BIT, 0, "lock bit is already set");
 	}
 
+	/// Wait until the lock changes from the given value.
+	#[cold]
+	fn wait(&self, v: u32) {
+		self.waiters.fetch_add(1, Ordering::SeqCst);
+		let _ = syscall::futex_wait(&self.lock, v, Duration::MAX);
+		self.waiters.fetch_sub(1, Ordering::Relaxed);
+	}
+
+	/// Wake all waiting threads, if any.
+	#[inline]
+	fn wake(&self) {
+		if self.waiters.load(Ordering::SeqCst) > 0 {
+			let _ = syscall::futex_wake(&self.lock, usize::MAX);
+		}
+	}
+
 	#[inline]
 	pub fn try_read(&self) -> bool {
 		let v = self.lock.load(Ordering::Relaxed);
-		if v & WRITE_LOCK_BIT == 0 {
+		if v & WRITE_LOCK_BIT != 0 {
 			false
 		} else {
 			check_read_overflow(v + 1);
@@ -61,7 +82,7 @@ impl RawRwLock {
 	#[inline]
 	pub fn try_write(&self) -> bool {
 		self.lock
-			.compare_exchange(0, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+			.compare_exchange(0, u32::MAX, Ordering::Acquire, Ordering::Relaxed)
 			.is_ok()
 	}
 
@@ -83,34 +104,33 @@ impl RawRwLock {
 			}
 		}
 		// Now that the write bit is set we just need to wait for all readers to bugger off.
-		while self
-			.lock
-			.compare_exchange(
-				WRITE_LOCK_BIT,
-				usize::MAX,
-				Ordering::Acquire,
-				Ordering::Relaxed,
-			)
-			.is_err()
-		{
-			thread::yield_now();
+		while let Err(v) = self.lock.compare_exchange(
+			WRITE_LOCK_BIT,
+			u32::MAX,
+			Ordering::Acquire,
+			Ordering::Relaxed,
+		) {
+			self.wait(v);
 		}
 	}
 
 	#[inline]
 	pub fn read_unlock(&self) {
-		// No writes can have occured, so Relaxed ordering is fine.
-		self.lock.fetch_sub(1, Ordering::Relaxed);
+		// The last reader has to wake the writer, if any.
+		if self.lock.fetch_sub(1, Ordering::SeqCst) == WRITE_LOCK_BIT + 1 {
+			self.wake();
+		}
 	}
 
 	#[inline]
 	pub fn write_unlock(&self) {
 		debug_assert_eq!(
 			self.lock.load(Ordering::Relaxed),
-			usize::MAX,
+			u32::MAX,
 			"write lock got released"
 		);
-		self.lock.store(0, Ordering::Release);
+		self.lock.store(0, Ordering::SeqCst);
+		self.wake();
 	}
 }
 
@@ -122,7 +142,7 @@ impl Default for RawRwLock {
 
 /// Ensure the counter didn't overflow. If it did, abort immediately.
 #[inline]
-fn check_read_overflow(v: usize) {
+fn check_read_overflow(v: u32) {
 	if v & WRITE_LOCK_BIT != 0 {
 		intrinsics::abort()
 	}
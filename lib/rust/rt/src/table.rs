@@ -14,10 +14,16 @@ pub struct Object(Handle);
 impl Object {
 	/// Create a new local object.
 	#[inline(always)]
-	pub fn new(args: NewObject) -> io::Result<(Self, Self)> {
+	pub fn new(args: NewObject<&[u8]>) -> io::Result<(Self, Self)> {
 		io::new_object(args).map(|(a, b)| (Self(a), Self(b)))
 	}
 
+	/// Create a new handle to the same object.
+	#[inline(always)]
+	pub fn duplicate(&self) -> io::Result<Self> {
+		Self::new(NewObject::Duplicate { handle: self.0 }).map(|(o, _)| o)
+	}
+
 	#[inline(always)]
 	pub fn open(&self, path: &[u8]) -> io::Result<Self> {
 		io::open(self.0, path).map(Self)
@@ -72,6 +78,39 @@ impl Object {
 		io::write(self.0, data)
 	}
 
+	#[inline(always)]
+	pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+		io::read_at(self.0, buf, offset)
+	}
+
+	#[inline]
+	pub fn read_at_uninit<'a>(
+		&self,
+		buf: &'a mut [MaybeUninit<u8>],
+		offset: u64,
+	) -> io::Result<(&'a mut [u8], &'a mut [MaybeUninit<u8>])> {
+		io::read_at_uninit(self.0, buf, offset).map(|l| {
+			let (i, u) = buf.split_at_mut(l);
+			// SAFETY: all bytes in i are initialized
+			(unsafe { MaybeUninit::slice_assume_init_mut(i) }, u)
+		})
+	}
+
+	#[inline]
+	pub fn write_at(&self, data: &[u8], offset: u64) -> io::Result<usize> {
+		io::write_at(self.0, data, offset)
+	}
+
+	#[inline]
+	pub fn read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+		io::read_vectored(self.0, bufs)
+	}
+
+	#[inline]
+	pub fn write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+		io::write_vectored(self.0, bufs)
+	}
+
 	#[inline]
 	pub fn get_meta(
 		&self,
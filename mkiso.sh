@@ -31,6 +31,7 @@ cp target/$TARGET_BOOT/$build_dir/noraboot $O/boot/noraboot
 cp boot/$ARCH/grub/grub.cfg $O/boot/grub/grub.cfg
 
 cp init.toml $A/init.toml
+cp net_filter.toml $A/net_filter.toml
 
 if [ "$1" == --release ] # stuff's broken otherwise
 then
@@ -55,6 +56,9 @@ install base    gui_cli            gui_cli
 install base    image_viewer       image_viewer
 install base    jail               jail
 install base    minish             minish
+install base    net_filter         net_filter
+install base    scp                scp
+install base    sftp_server        sftp_server
 install base    ssh                ssh
 install base    static_http_server static_http_server
 install base    window_manager     window_manager